@@ -0,0 +1,209 @@
+use eframe::egui;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::{gui::render::RenderContext, model};
+
+#[cfg(test)]
+use crate::gui::{node, style::GraphStyle};
+
+/// The comment's screen-space box; comments live in graph space, so they pan and zoom
+/// with the nodes.
+pub(crate) fn comment_rect(ctx: &RenderContext, comment: &model::Comment) -> egui::Rect {
+    egui::Rect::from_min_size(ctx.graph_to_screen(comment.pos), comment.size * ctx.scale)
+}
+
+/// Comment images decoded and uploaded once per `CommentImage::id`. A `None` entry is an
+/// image that failed to decode, so it is not retried every frame.
+#[derive(Default)]
+pub struct CommentTextures {
+    textures: HashMap<Uuid, Option<egui::TextureHandle>>,
+}
+
+impl std::fmt::Debug for CommentTextures {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CommentTextures")
+            .field("images", &self.textures.len())
+            .finish()
+    }
+}
+
+impl CommentTextures {
+    fn texture(
+        &mut self,
+        ctx: &egui::Context,
+        image: &model::CommentImage,
+    ) -> Option<&egui::TextureHandle> {
+        self.textures
+            .entry(image.id)
+            .or_insert_with(|| {
+                let rgba = image
+                    .to_rgba()
+                    .inspect_err(|err| tracing::warn!("comment image {}: {err:#}", image.id))
+                    .ok()?;
+                let size = [image.width as usize, image.height as usize];
+                let pixels = egui::ColorImage::from_rgba_unmultiplied(size, &rgba);
+                let name = format!("comment_image_{}", image.id);
+                Some(ctx.load_texture(name, pixels, egui::TextureOptions::LINEAR))
+            })
+            .as_ref()
+    }
+
+    /// Frees the textures of images no comment in `graph` shows any more.
+    fn retain_shown(&mut self, graph: &model::Graph) {
+        self.textures.retain(|id, _| {
+            graph
+                .comments
+                .iter()
+                .any(|comment| comment.image.as_ref().is_some_and(|image| image.id == *id))
+        });
+    }
+}
+
+/// Draws every comment under the groups and nodes: a tinted box with its image fitted
+/// inside, and its text wrapped to the box and clipped to it. Comments do not sense the
+/// pointer, so presses reach the nodes and canvas beneath.
+pub fn render_comments(ctx: &RenderContext, graph: &model::Graph, textures: &mut CommentTextures) {
+    textures.retain_shown(graph);
+    let painter = ctx.painter();
+    for comment in &graph.comments {
+        let rect = comment_rect(ctx, comment);
+        if !painter.clip_rect().intersects(rect) {
+            continue;
+        }
+        painter.rect_filled(rect, ctx.layout.corner_radius, ctx.style.comment_fill);
+        painter.rect_stroke(
+            rect,
+            ctx.layout.corner_radius,
+            ctx.style.comment_stroke,
+            egui::StrokeKind::Inside,
+        );
+        let text_rect = rect.shrink(ctx.layout.padding);
+        if let Some(image) = &comment.image
+            && let Some(texture) = textures.texture(ctx.ui().ctx(), image)
+            && text_rect.width() > 0.0
+            && text_rect.height() > 0.0
+        {
+            let size = egui::vec2(image.width as f32, image.height as f32);
+            let fit = (text_rect.size() / size).min_elem();
+            let uv = egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0));
+            painter.image(
+                texture.id(),
+                egui::Rect::from_center_size(text_rect.center(), size * fit),
+                uv,
+                egui::Color32::WHITE,
+            );
+        }
+        if comment.text.is_empty() || text_rect.width() <= 0.0 || text_rect.height() <= 0.0 {
+            continue;
+        }
+        let galley = painter.layout(
+            comment.text.clone(),
+            ctx.body_font.clone(),
+            ctx.text_color,
+            text_rect.width(),
+        );
+        painter
+            .with_clip_rect(text_rect.intersect(painter.clip_rect()))
+            .galley(text_rect.min, galley, ctx.text_color);
+    }
+}
+
+#[test]
+fn comments_follow_pan_and_zoom() {
+    let mut graph = model::Graph::test_graph();
+    graph.zoom = 2.0;
+    graph.pan = egui::vec2(-30.0, 15.0);
+    let comment = graph.comments[0].clone();
+    let ctx = egui::Context::default();
+    let output = ctx.run(egui::RawInput::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let painter = ui.painter().clone();
+            let style = GraphStyle::new(ui.visuals(), graph.zoom);
+            let render_ctx = RenderContext::new(
+                ui,
+                &painter,
+                ui.max_rect(),
+                &graph,
+                &node::NodeLayout::default(),
+                &style,
+            );
+            let rect = comment_rect(&render_ctx, &comment);
+            let origin = ui.max_rect().min + graph.pan;
+            assert_eq!(rect.min, origin + comment.pos.to_vec2() * 2.0);
+            assert_eq!(rect.size(), comment.size * 2.0);
+            render_comments(&render_ctx, &graph, &mut CommentTextures::default());
+        });
+    });
+    let texts: Vec<String> = output
+        .shapes
+        .iter()
+        .filter_map(|clipped| match &clipped.shape {
+            egui::Shape::Text(text) => Some(text.galley.text().to_string()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(texts, [comment.text]);
+}
+
+#[test]
+fn comment_images_upload_once_and_are_freed_with_their_comment() {
+    let mut graph = model::Graph::default();
+    let image = model::CommentImage::from_rgba(2, 1, &[255, 0, 0, 255, 0, 0, 255, 255])
+        .expect("pixels fit the size");
+    let image_id = image.id;
+    graph.add_image_comment(egui::pos2(20.0, 20.0), image);
+    let mut broken = model::CommentImage::from_rgba(1, 1, &[0; 4]).expect("one pixel");
+    broken.png_base64 = "not png".to_string();
+    let broken_id = broken.id;
+    graph.add_image_comment(egui::pos2(200.0, 20.0), broken);
+    for comment in &mut graph.comments {
+        comment.size = egui::vec2(100.0, 50.0);
+    }
+
+    let ctx = egui::Context::default();
+    let mut textures = CommentTextures::default();
+    let frame = |graph: &model::Graph, textures: &mut CommentTextures| {
+        let output = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let painter = ui.painter().clone();
+                let style = GraphStyle::new(ui.visuals(), graph.zoom);
+                let layout = node::NodeLayout::default();
+                let render_ctx =
+                    RenderContext::new(ui, &painter, ui.max_rect(), graph, &layout, &style);
+                render_comments(&render_ctx, graph, textures);
+            });
+        });
+        output
+            .shapes
+            .iter()
+            .filter(|clipped| {
+                matches!(&clipped.shape, egui::Shape::Mesh(mesh)
+                    if mesh.texture_id != egui::TextureId::default())
+            })
+            .count()
+    };
+
+    assert_eq!(
+        frame(&graph, &mut textures),
+        1,
+        "only the decodable image draws"
+    );
+    assert!(textures.textures[&image_id].is_some());
+    assert!(textures.textures[&broken_id].is_none());
+    let texture_id = textures.textures[&image_id]
+        .as_ref()
+        .map(|handle| handle.id());
+    frame(&graph, &mut textures);
+    assert_eq!(
+        textures.textures[&image_id]
+            .as_ref()
+            .map(|handle| handle.id()),
+        texture_id,
+        "the texture is reused"
+    );
+
+    graph.comments.clear();
+    frame(&graph, &mut textures);
+    assert!(textures.textures.is_empty());
+}
@@ -0,0 +1,170 @@
+use eframe::egui;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::{gui::graph::GraphUi, model};
+
+const ERROR_COLOR: egui::Color32 = egui::Color32::from_rgb(235, 80, 80);
+const VALID_COLOR: egui::Color32 = egui::Color32::from_rgb(110, 200, 120);
+
+/// Live `validate_detailed` summary for the top panel. The issue count is cached by
+/// `Graph::content_hash`, so validation reruns only after an edit.
+#[derive(Debug, Default)]
+pub struct ValidationStatus {
+    content_hash: Option<u64>,
+    issue_count: usize,
+}
+
+impl ValidationStatus {
+    /// Revalidates if the graph changed since the last call; returns the issue count.
+    pub fn refresh(&mut self, graph: &model::Graph) -> usize {
+        let hash = graph.content_hash();
+        if self.content_hash != Some(hash) {
+            self.issue_count = graph.validate_detailed().len();
+            self.content_hash = Some(hash);
+        }
+        self.issue_count
+    }
+
+    /// Draws the "valid" / "N issues" chip; returns whether it was clicked.
+    pub fn render_chip(&mut self, ui: &mut egui::Ui, graph: &model::Graph) -> bool {
+        let (text, color) = match self.refresh(graph) {
+            0 => ("valid".to_string(), VALID_COLOR),
+            1 => ("1 issue".to_string(), ERROR_COLOR),
+            count => (format!("{count} issues"), ERROR_COLOR),
+        };
+        ui.add(
+            egui::Button::new(egui::RichText::new(text).color(color).small())
+                .stroke(egui::Stroke::new(1.0, color))
+                .corner_radius(8.0),
+        )
+        .on_hover_text("Open the graph check")
+        .clicked()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GraphCheckReport {
+    open: bool,
+    diagnostics: Vec<model::Diagnostic>,
+}
+
+impl GraphCheckReport {
+    /// Runs `Graph::diagnostics` with node rects measured by `graph_ui` and opens the
+    /// window.
+    pub fn run(&mut self, ui: &egui::Ui, graph: &model::Graph, graph_ui: &GraphUi) {
+        self.diagnostics = graph.diagnostics(&graph_ui.node_rects(ui, graph));
+        self.open = true;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+        self.diagnostics.clear();
+    }
+
+    pub fn render(
+        &mut self,
+        ctx: &egui::Context,
+        graph: &mut model::Graph,
+        graph_ui: &mut GraphUi,
+    ) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        let mut rerun = false;
+        let mut fix_indices = false;
+        let mut focus_request: Option<Uuid> = None;
+        let mut node_rects: HashMap<Uuid, egui::Rect> = HashMap::new();
+        egui::Window::new("Graph check")
+            .open(&mut open)
+            .resizable(true)
+            .show(ctx, |ui| {
+                let errors = self
+                    .diagnostics
+                    .iter()
+                    .filter(|diagnostic| diagnostic.severity == model::Severity::Error)
+                    .count();
+                let warnings = self.diagnostics.len() - errors;
+                ui.horizontal(|ui| {
+                    ui.label(format!("{errors} errors, {warnings} warnings"));
+                    rerun = ui.button("Re-check").clicked();
+                    if !graph_ui.read_only && !graph.misordered_connections().is_empty() {
+                        fix_indices = ui
+                            .button("Fix output indices")
+                            .on_hover_text(
+                                "Reconnect inputs to the output their saved name refers to",
+                            )
+                            .clicked();
+                    }
+                });
+                if rerun || fix_indices {
+                    // Fixing output indices rewires inputs only, so node sizes hold.
+                    node_rects = graph_ui.node_rects(ui, graph);
+                }
+                ui.separator();
+
+                if self.diagnostics.is_empty() {
+                    ui.label("No issues found");
+                    return;
+                }
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for diagnostic in &self.diagnostics {
+                        ui.horizontal(|ui| {
+                            let (label, color) = match diagnostic.severity {
+                                model::Severity::Error => ("error", ERROR_COLOR),
+                                model::Severity::Warning => {
+                                    ("warning", egui::Color32::from_rgb(240, 205, 90))
+                                }
+                            };
+                            ui.colored_label(color, label);
+                            let node = diagnostic
+                                .node_id
+                                .and_then(|id| graph.nodes.iter().find(|node| node.id == id));
+                            match node {
+                                Some(node) => {
+                                    if ui.link(&node.name).clicked() {
+                                        focus_request = Some(node.id);
+                                    }
+                                }
+                                None => {
+                                    ui.label("graph");
+                                }
+                            }
+                            ui.label(&diagnostic.message);
+                        });
+                    }
+                });
+            });
+
+        self.open = open;
+        if fix_indices {
+            graph.fix_connection_output_indices();
+            rerun = true;
+        }
+        if rerun {
+            self.diagnostics = graph.diagnostics(&node_rects);
+        }
+        if let Some(node_id) = focus_request {
+            graph_ui.focus_node(graph, node_id);
+        }
+    }
+}
+
+#[test]
+fn validation_status_recounts_only_after_edits() {
+    let mut graph = model::Graph::test_graph();
+    let mut status = ValidationStatus::default();
+    assert_eq!(status.refresh(&graph), 0);
+
+    graph.zoom = 0.0;
+    assert_eq!(status.refresh(&graph), 1);
+    let cached = status.content_hash;
+    assert_eq!(status.refresh(&graph), 1);
+    assert_eq!(status.content_hash, cached);
+
+    graph.zoom = 1.0;
+    assert_eq!(status.refresh(&graph), 0);
+}
@@ -2,22 +2,43 @@ use eframe::egui;
 
 use crate::{
     gui::{
-        node,
-        render::{RenderContext, WidgetRenderer},
+        comment, group, node,
+        playback::PlaybackState,
+        render::{self, RenderContext, WidgetRenderer},
+        style::{GraphStyleCache, StyleOverrides},
     },
     model,
 };
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 const MIN_ZOOM: f32 = 0.2;
 const MAX_ZOOM: f32 = 4.0;
 const MAX_BREAKER_LENGTH: f32 = 900.0;
+/// Zoom factor per + / - key press.
+const KEYBOARD_ZOOM_STEP: f32 = 1.2;
 
+/// One wire: an input can take several sources, but never the same output twice.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct ConnectionKey {
     target_node_id: Uuid,
     input_index: usize,
+    source_node_id: Uuid,
+    output_index: usize,
+}
+
+impl ConnectionKey {
+    fn new(target_node_id: Uuid, input_index: usize, connection: &model::Connection) -> Self {
+        Self {
+            target_node_id,
+            input_index,
+            source_node_id: connection.node_id,
+            output_index: connection.output_index,
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -33,12 +54,187 @@ impl ConnectionBreaker {
     }
 }
 
+/// Primary drag on empty canvas that the breaker binding does not claim. Corners are
+/// kept in graph space, so the rectangle stays on the same nodes while the view pans or
+/// zooms mid-drag.
+#[derive(Debug, Default)]
+struct MarqueeSelection {
+    pub active: bool,
+    pub start: egui::Pos2,
+    pub current: egui::Pos2,
+    pub mode: MarqueeMode,
+}
+
+/// What releasing a marquee does with the nodes it touches, from the modifiers held when
+/// it was pressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum MarqueeMode {
+    /// No modifier: the touched nodes become the selection.
+    #[default]
+    Replace,
+    /// Shift: the touched nodes join the selection.
+    Add,
+    /// Ctrl/Cmd or Alt: the touched nodes leave the selection.
+    Subtract,
+}
+
+impl MarqueeMode {
+    fn from_modifiers(modifiers: egui::Modifiers) -> Self {
+        if modifiers.shift {
+            Self::Add
+        } else if modifiers.command || modifiers.ctrl || modifiers.alt {
+            Self::Subtract
+        } else {
+            Self::Replace
+        }
+    }
+
+    fn apply(self, selected: &mut HashSet<Uuid>, touched: impl IntoIterator<Item = Uuid>) {
+        match self {
+            Self::Replace => *selected = touched.into_iter().collect(),
+            Self::Add => selected.extend(touched),
+            Self::Subtract => {
+                for node_id in touched {
+                    selected.remove(&node_id);
+                }
+            }
+        }
+    }
+}
+
+impl MarqueeSelection {
+    pub fn reset(&mut self) {
+        self.active = false;
+        self.start = egui::Pos2::ZERO;
+        self.current = egui::Pos2::ZERO;
+        self.mode = MarqueeMode::default();
+    }
+
+    fn screen_rect(&self, ctx: &RenderContext) -> egui::Rect {
+        egui::Rect::from_two_pos(
+            ctx.graph_to_screen(self.start),
+            ctx.graph_to_screen(self.current),
+        )
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum PortKind {
+pub enum PortKind {
     Input,
     Output,
 }
 
+/// What the pointer targets, resolved by `hit_test` in this priority order: a port, a
+/// node's close/cache button, a node body (top-most node first), a group title bar, a
+/// wire, empty canvas. A node drawn over a wire therefore always wins, and a port on a
+/// node's edge beats the node. A group's body is not a target; it is click-through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitTarget {
+    Port {
+        node_id: Uuid,
+        index: usize,
+        kind: PortKind,
+    },
+    NodeButton(Uuid),
+    NodeBody(Uuid),
+    GroupTitle(Uuid),
+    Wire {
+        target_node_id: Uuid,
+        input_index: usize,
+        source_node_id: Uuid,
+        output_index: usize,
+    },
+    Canvas,
+}
+
+impl HitTarget {
+    pub fn node_id(self) -> Option<Uuid> {
+        match self {
+            Self::Port { node_id, .. } | Self::NodeButton(node_id) | Self::NodeBody(node_id) => {
+                Some(node_id)
+            }
+            Self::GroupTitle(_) | Self::Wire { .. } | Self::Canvas => None,
+        }
+    }
+
+    /// Wires have no primary-button action, so pressing on one deselects and starts
+    /// the breaker like empty canvas does.
+    pub fn is_background(self) -> bool {
+        matches!(self, Self::Wire { .. } | Self::Canvas)
+    }
+}
+
+/// Right-click popup editing one wire's label.
+#[derive(Debug)]
+struct WireMenu {
+    key: ConnectionKey,
+    pos: egui::Pos2,
+    just_opened: bool,
+}
+
+/// Right-click popup on empty canvas listing `GraphUi::node_templates` to add.
+#[derive(Debug)]
+struct CanvasMenu {
+    /// Screen position of the click, where the popup opens.
+    pos: egui::Pos2,
+    /// The same point in graph space, where the new node goes.
+    graph_pos: egui::Pos2,
+    just_opened: bool,
+}
+
+/// Node outlined in red after a body drop found no free port or was refused.
+#[derive(Debug)]
+struct RejectedDrop {
+    node_id: Uuid,
+    until: f64,
+}
+
+const REJECTED_DROP_SECONDS: f64 = 0.6;
+const PASTE_OFFSET: f32 = 24.0;
+const WIRE_HIT_DISTANCE: f32 = 6.0;
+const NODE_PREVIEW_LINES: usize = 40;
+/// Distance from the graph rect edge (in points) where dragging starts auto-panning.
+const EDGE_PAN_MARGIN: f32 = 40.0;
+/// Auto-pan speed in points per second with the pointer on (or past) the edge.
+const EDGE_PAN_SPEED: f32 = 600.0;
+const VIEW_ANIMATION_SECONDS: f64 = 0.25;
+
+/// Camera glide started by Fit all, View selected, Reset view, or `focus_node`.
+#[derive(Debug)]
+struct ViewAnimation {
+    start_pan: egui::Vec2,
+    start_zoom: f32,
+    target_pan: egui::Vec2,
+    target_zoom: f32,
+    start_time: f64,
+    /// View written by the last step; the camera no longer matching it means the user
+    /// panned or zoomed, which ends the animation.
+    applied: (egui::Vec2, f32),
+}
+
+impl ViewAnimation {
+    fn new(graph: &model::Graph, target: (egui::Vec2, f32), now: f64) -> Self {
+        Self {
+            start_pan: graph.pan,
+            start_zoom: graph.zoom,
+            target_pan: target.0,
+            target_zoom: target.1,
+            start_time: now,
+            applied: (graph.pan, graph.zoom),
+        }
+    }
+
+    /// The eased view at `now` and whether the animation has reached its target.
+    fn view_at(&self, now: f64) -> ((egui::Vec2, f32), bool) {
+        let t = ((now - self.start_time) / VIEW_ANIMATION_SECONDS).clamp(0.0, 1.0) as f32;
+        // Ease-out cubic: fast start, gentle landing.
+        let eased = 1.0 - (1.0 - t).powi(3);
+        let pan = self.start_pan + (self.target_pan - self.start_pan) * eased;
+        let zoom = self.start_zoom + (self.target_zoom - self.start_zoom) * eased;
+        ((pan, zoom), t >= 1.0)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct PortRef {
     node_id: Uuid,
@@ -58,6 +254,8 @@ struct ConnectionDrag {
     start_port: PortRef,
     start_pos: egui::Pos2,
     current_pos: egui::Pos2,
+    /// The input this wire was pulled off, with the connection it had there.
+    detached: Option<(PortRef, model::Connection)>,
 }
 
 impl Default for ConnectionDrag {
@@ -72,6 +270,7 @@ impl Default for ConnectionDrag {
             start_port: placeholder,
             start_pos: egui::Pos2::ZERO,
             current_pos: egui::Pos2::ZERO,
+            detached: None,
         }
     }
 }
@@ -82,28 +281,459 @@ impl ConnectionDrag {
         self.start_port = port.port;
         self.start_pos = port.center;
         self.current_pos = port.center;
+        self.detached = None;
     }
 
     pub fn reset(&mut self) {
         self.active = false;
+        self.detached = None;
     }
 }
 
-#[derive(Debug, Default)]
+/// What dropping a wire on an input that is already connected does with the old wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OccupiedInputDrop {
+    /// Drop the old wire; Ctrl/Cmd+Z restores it.
+    #[default]
+    Replace,
+    /// When the dragged wire was pulled off another input, reconnect the displaced
+    /// source there; otherwise replace.
+    Swap,
+}
+
+/// How wires are routed between ports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ConnectionStyle {
+    /// Cubic bezier shaped by `GraphStyle::curve_tension`.
+    #[default]
+    Bezier,
+    /// Right-angle steps: out of the output, vertical at
+    /// `GraphStyle::orthogonal_mid_fraction`, then into the input.
+    Orthogonal,
+}
+
+/// `ConnectionStyle` with the style knob that shapes it, as `WireShape::new` needs it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WireRoute {
+    Bezier { tension: f32 },
+    Orthogonal { mid_fraction: f32 },
+}
+
+impl Default for WireRoute {
+    fn default() -> Self {
+        Self::Bezier { tension: 1.0 }
+    }
+}
+
+impl WireRoute {
+    fn new(connection_style: ConnectionStyle, style: &crate::gui::style::GraphStyle) -> Self {
+        match connection_style {
+            ConnectionStyle::Bezier => Self::Bezier {
+                tension: style.curve_tension,
+            },
+            ConnectionStyle::Orthogonal => Self::Orthogonal {
+                mid_fraction: style.orthogonal_mid_fraction,
+            },
+        }
+    }
+}
+
+/// Screen-space path of one wire, shared by drawn connections and the drag preview.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WireShape {
+    /// Start, the two control points, end.
+    Bezier([egui::Pos2; 4]),
+    /// Corners of the step route: start, both ends of the vertical leg, end.
+    Orthogonal([egui::Pos2; 4]),
+}
+
+impl WireShape {
+    /// The wire from `start` (a port of `start_kind`) to `end`, leaving and entering
+    /// horizontally in each port's exit direction.
+    fn new(
+        route: WireRoute,
+        start: egui::Pos2,
+        end: egui::Pos2,
+        start_kind: PortKind,
+        scale: f32,
+    ) -> Self {
+        match route {
+            WireRoute::Bezier { tension } => {
+                let offset = node::bezier_control_offset(start, end, scale, tension);
+                let offset = match start_kind {
+                    PortKind::Output => egui::vec2(offset, 0.0),
+                    PortKind::Input => egui::vec2(-offset, 0.0),
+                };
+                Self::Bezier([start, start + offset, end - offset, end])
+            }
+            WireRoute::Orthogonal { mid_fraction } => {
+                let mid_x = egui::lerp(start.x..=end.x, mid_fraction);
+                Self::Orthogonal([
+                    start,
+                    egui::pos2(mid_x, start.y),
+                    egui::pos2(mid_x, end.y),
+                    end,
+                ])
+            }
+        }
+    }
+
+    /// Points along the wire for hit tests: `steps` segments of a bezier, or the exact
+    /// corners of a step route.
+    fn samples(&self, steps: usize) -> Vec<egui::Pos2> {
+        match self {
+            Self::Bezier([p0, p1, p2, p3]) => sample_cubic_bezier(*p0, *p1, *p2, *p3, steps),
+            Self::Orthogonal(corners) => corners.to_vec(),
+        }
+    }
+
+    /// Where a wire label sits: halfway along a bezier, or the middle of the vertical leg.
+    fn midpoint(&self) -> egui::Pos2 {
+        match self {
+            Self::Bezier(_) => self.samples(2)[1],
+            Self::Orthogonal([_, top, bottom, _]) => top.lerp(*bottom, 0.5),
+        }
+    }
+
+    /// Direction the wire travels as it reaches its end: the bezier derivative at t = 1,
+    /// or the last step leg with any length. Zero for a degenerate wire.
+    fn end_tangent(&self) -> egui::Vec2 {
+        match self {
+            Self::Bezier([_, _, control, end]) => 3.0 * (*end - *control),
+            Self::Orthogonal(corners) => corners
+                .windows(2)
+                .rev()
+                .map(|leg| leg[1] - leg[0])
+                .find(|leg| leg.length_sq() > 0.0)
+                .unwrap_or(egui::Vec2::ZERO),
+        }
+    }
+
+    fn shape(&self, stroke: egui::Stroke) -> egui::Shape {
+        match self {
+            Self::Bezier(points) => bezier_shape(*points, stroke),
+            Self::Orthogonal(corners) => egui::Shape::line(corners.to_vec(), stroke),
+        }
+    }
+}
+
+/// Passes of `GraphUi::render` measured by the debug timing overlay, in draw order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderStage {
+    Background,
+    ConnectionRebuild,
+    ConnectionDraw,
+    NodeBodies,
+    Ports,
+    Labels,
+}
+
+impl RenderStage {
+    pub const ALL: [Self; 6] = [
+        Self::Background,
+        Self::ConnectionRebuild,
+        Self::ConnectionDraw,
+        Self::NodeBodies,
+        Self::Ports,
+        Self::Labels,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Background => "background",
+            Self::ConnectionRebuild => "wire rebuild",
+            Self::ConnectionDraw => "wire draw",
+            Self::NodeBodies => "node bodies",
+            Self::Ports => "ports",
+            Self::Labels => "labels",
+        }
+    }
+}
+
+/// Time spent per `RenderStage` in one frame, summed over the draw passes.
+#[derive(Debug, Default, Clone)]
+pub struct RenderTimings {
+    stages: [Duration; RenderStage::ALL.len()],
+}
+
+impl RenderTimings {
+    pub fn get(&self, stage: RenderStage) -> Duration {
+        self.stages[stage as usize]
+    }
+
+    pub fn total(&self) -> Duration {
+        self.stages.iter().sum()
+    }
+
+    /// Starts a span; reads no clock when timing is off.
+    fn start(timings: &Option<Self>) -> Option<Instant> {
+        timings.as_ref().map(|_| Instant::now())
+    }
+
+    fn record(timings: &mut Option<Self>, stage: RenderStage, start: Option<Instant>) {
+        if let (Some(timings), Some(start)) = (timings, start) {
+            timings.stages[stage as usize] += start.elapsed();
+        }
+    }
+}
+
+/// What double-clicking a node does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DoubleClickAction {
+    #[default]
+    Focus,
+    Rename,
+    /// Reported to the embedder through `GraphUi::take_open_subgraph_request`.
+    OpenSubgraph,
+    None,
+}
+
+/// Pointer button and held modifiers that start the connection breaker on empty canvas.
+/// The default (primary, no modifiers required) cuts on any plain canvas drag; requiring a
+/// modifier or another button frees plain primary drag for a replacing marquee.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakerBinding {
+    pub button: egui::PointerButton,
+    /// Must all be held at press time; extra modifiers are allowed.
+    pub modifiers: egui::Modifiers,
+}
+
+impl Default for BreakerBinding {
+    fn default() -> Self {
+        Self {
+            button: egui::PointerButton::Primary,
+            modifiers: egui::Modifiers::NONE,
+        }
+    }
+}
+
+impl BreakerBinding {
+    fn pressed(&self, input: &egui::InputState) -> bool {
+        input.pointer.button_pressed(self.button) && input.modifiers.contains(self.modifiers)
+    }
+
+    fn down(&self, input: &egui::InputState) -> bool {
+        input.pointer.button_down(self.button)
+    }
+
+    fn released(&self, input: &egui::InputState) -> bool {
+        input.pointer.button_released(self.button)
+    }
+}
+
+#[derive(Debug)]
+struct NodeRename {
+    node_id: Uuid,
+    text: String,
+    focus_requested: bool,
+}
+
+#[derive(Debug)]
 pub struct GraphUi {
+    pub double_click_action: DoubleClickAction,
+    pub node_layout: node::NodeLayout,
+    /// Multiplier for wheel and Ctrl/Cmd + scroll zoom speed.
+    pub zoom_sensitivity: f32,
+    pub breaker_binding: BreakerBinding,
+    /// Draws wires (with the breaker and drag preview) over the nodes instead of under.
+    pub connections_on_top: bool,
+    pub connection_style: ConnectionStyle,
+    /// Draws an arrowhead where each wire meets its input.
+    pub connection_arrows: bool,
+    /// Presentation mode: pan, zoom, selection, and inspection keep working, but every
+    /// edit (moving, wiring, cutting, removing, pasting, renaming) is disabled and the
+    /// node buttons are hidden. Unlike a camera lock, the view stays free.
+    pub read_only: bool,
+    /// Developer aid: holding Shift over a node shows its serialized JSON.
+    pub debug_node_preview: bool,
+    /// Developer aid: times the render passes and lists them in the bottom-left corner.
+    pub debug_render_timings: bool,
+    /// Dropping a dragged node clear of its group's frame removes it from the group.
+    pub ungroup_on_drag_out: bool,
+    /// Tints node borders by `Graph::node_health` instead of the normal styling.
+    pub health_view: bool,
+    pub occupied_input_drop: OccupiedInputDrop,
+    /// Screen points the pointer must travel from the press before a header drag moves
+    /// the node; shorter drags only select.
+    pub node_drag_threshold: f32,
+    /// Node kinds offered by the right-click "Add node" menu on empty canvas.
+    pub node_templates: model::NodeTemplateRegistry,
     connection_breaker: ConnectionBreaker,
+    marquee: MarqueeSelection,
     connection_drag: ConnectionDrag,
+    pending_focus: Option<Uuid>,
+    view_animation: Option<ViewAnimation>,
+    node_rename: Option<NodeRename>,
+    open_subgraph_request: Option<Uuid>,
+    style_cache: GraphStyleCache,
+    rejected_drop: Option<RejectedDrop>,
+    playback: PlaybackState,
+    wire_menu: Option<WireMenu>,
+    canvas_menu: Option<CanvasMenu>,
+    connections: ConnectionRenderer,
+    comment_textures: comment::CommentTextures,
+    /// Last frame's zoom indicator; presses there never reach the canvas.
+    zoom_indicator_rect: Option<egui::Rect>,
+    hovered_target: Option<HitTarget>,
+    /// Node dragged by its header last frame; its drag ended once this frame has none.
+    dragged_node: Option<Uuid>,
+    /// `Graph::undo_snapshot` from when the primary button went down on a node; pushed
+    /// as an undo checkpoint if the press turns into a header drag.
+    drag_snapshot: Option<String>,
+    notice: Option<String>,
+    render_timings: Option<RenderTimings>,
+    /// Whether a text field had keyboard focus when the last frame ended. egui drops focus
+    /// on Escape before any widget runs, so this keeps that Escape from also deselecting.
+    text_focus_last_frame: bool,
+}
+
+impl Default for GraphUi {
+    fn default() -> Self {
+        Self {
+            double_click_action: DoubleClickAction::default(),
+            node_layout: node::NodeLayout::default(),
+            zoom_sensitivity: 1.0,
+            breaker_binding: BreakerBinding::default(),
+            connections_on_top: false,
+            connection_style: ConnectionStyle::default(),
+            connection_arrows: true,
+            read_only: false,
+            debug_node_preview: false,
+            debug_render_timings: false,
+            ungroup_on_drag_out: true,
+            health_view: false,
+            occupied_input_drop: OccupiedInputDrop::default(),
+            node_drag_threshold: 4.0,
+            node_templates: model::NodeTemplateRegistry::default(),
+            connection_breaker: ConnectionBreaker::default(),
+            marquee: MarqueeSelection::default(),
+            connection_drag: ConnectionDrag::default(),
+            pending_focus: None,
+            view_animation: None,
+            node_rename: None,
+            open_subgraph_request: None,
+            style_cache: GraphStyleCache::default(),
+            rejected_drop: None,
+            playback: PlaybackState::default(),
+            wire_menu: None,
+            canvas_menu: None,
+            connections: ConnectionRenderer::default(),
+            comment_textures: comment::CommentTextures::default(),
+            zoom_indicator_rect: None,
+            hovered_target: None,
+            dragged_node: None,
+            drag_snapshot: None,
+            notice: None,
+            render_timings: None,
+            text_focus_last_frame: false,
+        }
+    }
 }
 
 impl GraphUi {
     pub fn reset(&mut self) {
         self.connection_breaker.reset();
+        self.marquee.reset();
         self.connection_drag.reset();
+        self.pending_focus = None;
+        self.view_animation = None;
+        self.node_rename = None;
+        self.open_subgraph_request = None;
+        self.rejected_drop = None;
+        self.playback.reset();
+        self.wire_menu = None;
+        self.canvas_menu = None;
+        self.connections = ConnectionRenderer::default();
+        self.comment_textures = comment::CommentTextures::default();
+        self.zoom_indicator_rect = None;
+        self.hovered_target = None;
+        self.dragged_node = None;
+        self.drag_snapshot = None;
+        self.notice = None;
+        self.render_timings = None;
+    }
+
+    /// The `hit_test` result under the pointer from the latest `render`; `None` while the
+    /// pointer is outside the graph rect.
+    pub fn hovered_target(&self) -> Option<HitTarget> {
+        self.hovered_target
+    }
+
+    /// Customizes the graph style on top of `GraphStyle::new`; `None` restores the defaults.
+    pub fn set_style_overrides(&mut self, overrides: Option<StyleOverrides>) {
+        self.style_cache.set_overrides(overrides);
+    }
+
+    pub fn take_open_subgraph_request(&mut self) -> Option<Uuid> {
+        self.open_subgraph_request.take()
+    }
+
+    /// Stage timings of the latest `render`; `None` unless `debug_render_timings` is on.
+    pub fn render_timings(&self) -> Option<&RenderTimings> {
+        self.render_timings.as_ref()
+    }
+
+    /// One-line message about the latest edit worth telling the user about, such as a
+    /// wire replaced by a drop.
+    pub fn take_notice(&mut self) -> Option<String> {
+        self.notice.take()
+    }
+
+    /// Graph-space rect (zoom 1) of every unpinned node as this editor lays it out, for
+    /// checks such as `Graph::overlapping_nodes`.
+    pub fn node_rects(&self, ui: &egui::Ui, graph: &model::Graph) -> HashMap<Uuid, egui::Rect> {
+        let (layout, node_widths) =
+            compute_layout_and_widths(ui, ui.painter(), graph, &self.node_layout, 1.0);
+        graph.node_rects(&layout, &node_widths)
+    }
+
+    /// Selects the node and centers the view on it during the next `render`; a missing id
+    /// is ignored.
+    pub fn focus_node(&mut self, graph: &mut model::Graph, node_id: Uuid) {
+        if graph.select_node(node_id) {
+            self.pending_focus = Some(node_id);
+        }
+    }
+
+    /// Sets the camera directly. Zoom is clamped to `MIN_ZOOM..=MAX_ZOOM`; non-finite
+    /// values keep the current pan component or zoom. Cancels a pending `focus_node` and
+    /// any view animation.
+    pub fn set_view(&mut self, graph: &mut model::Graph, pan: egui::Vec2, zoom: f32) {
+        if pan.x.is_finite() {
+            graph.pan.x = pan.x;
+        }
+        if pan.y.is_finite() {
+            graph.pan.y = pan.y;
+        }
+        if zoom.is_finite() {
+            graph.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        }
+        assert!(graph.zoom.is_finite(), "graph zoom must be finite");
+        assert!(
+            graph.pan.x.is_finite() && graph.pan.y.is_finite(),
+            "graph pan must be finite"
+        );
+        self.pending_focus = None;
+        self.view_animation = None;
+    }
+
+    /// Zooms to `new_zoom` (clamped like `set_view`) keeping `anchor` fixed on screen.
+    /// `anchor` is relative to the graph view's top-left corner, e.g. the view center for
+    /// keyboard zoom; wheel zoom uses the cursor.
+    pub fn zoom_about(&mut self, graph: &mut model::Graph, anchor: egui::Pos2, new_zoom: f32) {
+        self.view_animation = None;
+        zoom_about(graph, anchor, new_zoom);
+    }
+
+    /// The camera as `(pan, zoom)`.
+    pub fn current_view(&self, graph: &model::Graph) -> (egui::Vec2, f32) {
+        (graph.pan, graph.zoom)
     }
 
     pub fn render(&mut self, ui: &mut egui::Ui, graph: &mut model::Graph) {
         let breaker = &mut self.connection_breaker;
         let connection_drag = &mut self.connection_drag;
+        let marquee = &mut self.marquee;
 
         let mut fit_all = false;
         let mut view_selected = false;
@@ -112,24 +742,64 @@ impl GraphUi {
             fit_all = ui.button("Fit all").clicked();
             view_selected = ui.button("View selected").clicked();
             reset_view = ui.button("Reset view").clicked();
+            ui.toggle_value(&mut self.health_view, "Health")
+                .on_hover_text(
+                    "Border colors: green is valid and reachable, yellow has warnings, red has \
+                 errors or never reaches a terminal node",
+                );
+            let mut orthogonal = self.connection_style == ConnectionStyle::Orthogonal;
+            if ui
+                .toggle_value(&mut orthogonal, "Step wires")
+                .on_hover_text("Route wires with right angles instead of curves")
+                .changed()
+            {
+                self.connection_style = if orthogonal {
+                    ConnectionStyle::Orthogonal
+                } else {
+                    ConnectionStyle::Bezier
+                };
+            }
+            self.playback.render_controls(ui, graph);
         });
+        if self.playback.update(graph, ui.input(|input| input.time)) {
+            ui.ctx().request_repaint();
+        }
 
         let rect = ui.available_rect_before_wrap();
+        // A collapsed panel leaves nothing to draw, and zoom/fit math would divide by zero.
+        if !has_usable_area(rect) {
+            return;
+        }
         let painter = ui.painter_at(rect);
-        let input_ctx = RenderContext::new(ui, &painter, rect, graph);
+        let read_only = self.read_only;
+        let mut timings = self.debug_render_timings.then(RenderTimings::default);
+        let style = self.style_cache.get(ui.visuals(), graph.zoom);
+        let input_ctx = RenderContext::new(ui, &painter, rect, graph, &self.node_layout, style);
 
-        if reset_view {
-            graph.zoom = 1.0;
-            graph.pan = egui::Vec2::ZERO;
+        if ui.rect_contains_pointer(rect) && !text_field_focused(ui.ctx()) {
+            let keys = ui.input_mut(keyboard_view_keys);
+            reset_view |= keys.reset;
+            fit_all |= keys.fit_all;
+            if keys.zoom_steps != 0 {
+                let zoom = graph.zoom * KEYBOARD_ZOOM_STEP.powi(keys.zoom_steps);
+                zoom_about(graph, egui::Pos2::ZERO + rect.size() * 0.5, zoom);
+            }
         }
-
-        if view_selected {
-            view_selected_node(ui, &painter, rect, graph);
+        let mut view_target = reset_view.then_some((egui::Vec2::ZERO, 1.0));
+        let focus_requested = self.pending_focus.take().is_some();
+        if view_selected || focus_requested {
+            view_target =
+                view_selected_node(ui, &painter, rect, graph, &self.node_layout).or(view_target);
         }
-
         if fit_all {
-            fit_all_nodes(ui, &painter, rect, graph);
+            view_target =
+                fit_all_nodes(ui, &painter, rect, graph, &self.node_layout).or(view_target);
+        }
+        let now = ui.input(|input| input.time);
+        if let Some(target) = view_target {
+            self.view_animation = Some(ViewAnimation::new(graph, target, now));
         }
+        step_view_animation(&mut self.view_animation, ui, graph, now);
 
         let pointer_pos = ui.input(|input| input.pointer.hover_pos());
         let cursor_pos = ui.ctx().pointer_latest_pos().or(pointer_pos);
@@ -141,68 +811,185 @@ impl GraphUi {
         let port_activation = (input_ctx.port_radius * 1.6).max(10.0);
         let ports = collect_ports(
             graph,
-            input_ctx.origin,
+            input_ctx.view,
             &input_ctx.layout,
             &input_ctx.node_widths,
         );
-        let hovered_port = pointer_pos
-            .filter(|pos| input_ctx.rect.contains(*pos))
-            .and_then(|pos| find_port_near(&ports, pos, port_activation));
-        let hovered_port_ref = hovered_port.as_ref();
-        let pointer_over_node = pointer_pos
+        let span = RenderTimings::start(&timings);
+        self.connections.connection_style = self.connection_style;
+        self.connections.connection_arrows = self.connection_arrows;
+        self.connections.rebuild(
+            graph,
+            input_ctx.view,
+            &input_ctx.layout,
+            &input_ctx.node_widths,
+            input_ctx.style,
+            breaker,
+        );
+        RenderTimings::record(&mut timings, RenderStage::ConnectionRebuild, span);
+        let hit = pointer_pos
             .filter(|pos| input_ctx.rect.contains(*pos))
-            .is_some_and(|pos| {
-                graph.nodes.iter().any(|node| {
-                    let node_rect = input_ctx.node_rect(node);
-                    node_rect.contains(pos)
-                })
+            .map(|pos| {
+                let hit = hit_test(
+                    &input_ctx,
+                    graph,
+                    &ports,
+                    port_activation,
+                    &self.connections,
+                    pos,
+                );
+                // Without editing, buttons are hidden, title bars are inert, and ports
+                // only belong to their node.
+                match hit {
+                    HitTarget::GroupTitle(_) if read_only => HitTarget::Canvas,
+                    HitTarget::NodeButton(node_id) | HitTarget::Port { node_id, .. }
+                        if read_only =>
+                    {
+                        HitTarget::NodeBody(node_id)
+                    }
+                    hit => hit,
+                }
             });
+        self.hovered_target = hit;
+        let hovered_port = match hit {
+            Some(HitTarget::Port {
+                node_id,
+                index,
+                kind,
+            }) => ports
+                .iter()
+                .find(|port| {
+                    port.port
+                        == PortRef {
+                            node_id,
+                            index,
+                            kind,
+                        }
+                })
+                .cloned(),
+            _ => None,
+        };
+        let hovered_port_ref = hovered_port.as_ref();
+        let pointer_over_widget = matches!(
+            hit,
+            Some(HitTarget::NodeButton(_) | HitTarget::NodeBody(_) | HitTarget::GroupTitle(_))
+        );
         let pan_id = ui.make_persistent_id("graph_pan");
         let pan_response = ui.interact(
             input_ctx.rect,
             pan_id,
             if breaker.active
                 || connection_drag.active
-                || pointer_over_node
+                || marquee.active
+                || pointer_over_widget
                 || hovered_port.is_some()
             {
-                egui::Sense::hover()
+                egui::Sense::focusable_noninteractive()
             } else {
                 egui::Sense::drag()
             },
         );
+        pan_response.widget_info(|| {
+            egui::WidgetInfo::labeled(
+                egui::WidgetType::Other,
+                true,
+                canvas_accessible_label(graph),
+            )
+        });
+        if pan_response.clicked() || pan_response.drag_started() {
+            pan_response.request_focus();
+        }
+        if pan_response.has_focus() {
+            // Keep arrows for panning; Tab still moves focus on to the nodes.
+            ui.memory_mut(|memory| {
+                memory.set_focus_lock_filter(
+                    pan_id,
+                    egui::EventFilter {
+                        horizontal_arrows: true,
+                        vertical_arrows: true,
+                        ..Default::default()
+                    },
+                );
+            });
+            graph.pan += keyboard_pan_delta(ui);
+        } else if pointer_in_rect && ui.memory(|memory| memory.focused().is_none()) {
+            // Nothing focused, so the arrows would not move focus either.
+            graph.pan += keyboard_pan_delta(ui);
+        }
 
         if pan_response.dragged_by(egui::PointerButton::Primary)
-            && !pointer_over_node
+            && !pointer_over_widget
             && !breaker.active
             && !connection_drag.active
+            && !marquee.active
         {
             graph.pan += pan_response.drag_delta();
         }
-        if middle_down && pointer_in_rect && !breaker.active && !connection_drag.active {
-            assert!(
-                pointer_delta.x.is_finite(),
-                "pointer delta x must be finite"
-            );
-            assert!(
-                pointer_delta.y.is_finite(),
-                "pointer delta y must be finite"
-            );
+        if middle_down
+            && pointer_in_rect
+            && !breaker.active
+            && !connection_drag.active
+            && pointer_delta.is_finite()
+        {
             graph.pan += pointer_delta;
         }
 
         let primary_pressed = ui.input(|input| input.pointer.primary_pressed());
-        let primary_down = ui.input(|input| input.pointer.primary_down());
         let primary_released = ui.input(|input| input.pointer.primary_released());
+        let binding = self.breaker_binding;
+        let (breaker_pressed, breaker_down, breaker_released) = ui.input(|input| {
+            (
+                binding.pressed(input),
+                binding.down(input),
+                binding.released(input),
+            )
+        });
+        let over_zoom_indicator = self
+            .zoom_indicator_rect
+            .zip(pointer_pos)
+            .is_some_and(|(indicator, pos)| indicator.contains(pos));
+        let over_empty_canvas = hit.is_some_and(HitTarget::is_background) && !over_zoom_indicator;
 
+        // The breaker keeps the primary presses its binding names: plain ones when it needs
+        // no modifier, modified ones when they include the modifiers it asks for.
+        let modifiers = ui.input(|input| input.modifiers);
+        let marquee_mode = MarqueeMode::from_modifiers(modifiers);
+        let breaker_claims = binding.button == egui::PointerButton::Primary
+            && if marquee_mode == MarqueeMode::Replace {
+                binding.modifiers.is_none()
+            } else {
+                !binding.modifiers.is_none() && modifiers.contains(binding.modifiers)
+            };
+        let marquee_pressed = primary_pressed && !breaker_claims;
         if !breaker.active
             && !connection_drag.active
             && primary_pressed
-            && pointer_in_rect
-            && !pointer_over_node
-            && hovered_port.is_none()
+            && over_empty_canvas
+            && let Some(pos) = pointer_pos
+        {
+            if marquee_pressed {
+                // The selection changes on release, as `marquee_mode` says.
+                let start = input_ctx.screen_to_graph(pos);
+                *marquee = MarqueeSelection {
+                    active: true,
+                    start,
+                    current: start,
+                    mode: marquee_mode,
+                };
+            } else {
+                graph.selected_nodes.clear();
+            }
+        }
+        if primary_pressed && !read_only && matches!(hit, Some(HitTarget::NodeBody(_))) {
+            self.drag_snapshot = Some(graph.undo_snapshot());
+        }
+        if !read_only
+            && !breaker.active
+            && !connection_drag.active
+            && !marquee.active
+            && breaker_pressed
+            && over_empty_canvas
         {
-            graph.selected_node_id = None;
             breaker.active = true;
             breaker.points.clear();
             if let Some(pos) = pointer_pos {
@@ -216,11 +1003,41 @@ impl GraphUi {
             && pointer_in_rect
             && let Some(port) = hovered_port_ref
         {
-            connection_drag.start(port.clone());
+            let before_detach = graph.undo_snapshot();
+            match detach_input_connection(graph, port.port) {
+                Some(connection) => {
+                    // The whole pull-off gesture, wherever the wire lands, undoes as one.
+                    graph.push_undo_snapshot(before_detach);
+                    let detached = (port.port, connection.clone());
+                    let source_node = graph
+                        .nodes
+                        .iter()
+                        .find(|node| node.id == connection.node_id)
+                        .expect("detached connection source node must exist");
+                    let source_width = input_ctx.node_width(source_node.id);
+                    let center = node::node_output_pos(
+                        input_ctx.view,
+                        source_node,
+                        connection.output_index,
+                        &input_ctx.layout,
+                        source_width,
+                    );
+                    connection_drag.start(PortInfo {
+                        port: PortRef {
+                            node_id: connection.node_id,
+                            index: connection.output_index,
+                            kind: PortKind::Output,
+                        },
+                        center,
+                    });
+                    connection_drag.detached = Some(detached);
+                }
+                None => connection_drag.start(port.clone()),
+            }
         }
 
         if breaker.active
-            && primary_down
+            && breaker_down
             && let Some(pos) = pointer_pos
         {
             let should_add = breaker
@@ -266,93 +1083,349 @@ impl GraphUi {
                         _ => acc,
                     })
             });
+            // Platform input can report non-finite deltas; treat them as no movement.
+            let scroll_delta = finite_or_zero(scroll_delta);
+            let wheel_delta = finite_or_zero(wheel_delta);
             let wheel_scroll = wheel_delta.length_sq() > f32::EPSILON;
-            assert!(scroll_delta.x.is_finite(), "scroll delta x must be finite");
-            assert!(scroll_delta.y.is_finite(), "scroll delta y must be finite");
-            assert!(wheel_delta.x.is_finite(), "wheel delta x must be finite");
-            assert!(wheel_delta.y.is_finite(), "wheel delta y must be finite");
-
-            if wheel_scroll && wheel_delta.y.abs() > f32::EPSILON {
-                let wheel_zoom = (wheel_delta.y * 0.06).exp();
-                assert!(wheel_zoom.is_finite(), "wheel zoom factor must be finite");
-                zoom_delta *= wheel_zoom;
+
+            let horizontal_wheel =
+                wheel_scroll && modifiers.shift && !(modifiers.command || modifiers.ctrl);
+            let zoom_wheel =
+                wheel_scroll && !horizontal_wheel && wheel_delta.y.abs() > f32::EPSILON;
+
+            if zoom_wheel {
+                let wheel_zoom = (wheel_delta.y * 0.06 * self.zoom_sensitivity).exp();
+                if wheel_zoom.is_finite() {
+                    zoom_delta *= wheel_zoom;
+                }
             } else if (modifiers.command || modifiers.ctrl) && scroll_delta.y.abs() > f32::EPSILON {
-                let scroll_zoom = (scroll_delta.y * 0.003).exp();
-                assert!(scroll_zoom.is_finite(), "scroll zoom factor must be finite");
-                zoom_delta *= scroll_zoom;
+                let scroll_zoom = (scroll_delta.y * 0.003 * self.zoom_sensitivity).exp();
+                if scroll_zoom.is_finite() {
+                    zoom_delta *= scroll_zoom;
+                }
             }
 
             if (zoom_delta - 1.0).abs() > f32::EPSILON {
                 let clamped_zoom = (graph.zoom * zoom_delta).clamp(MIN_ZOOM, MAX_ZOOM);
-                assert!(clamped_zoom.is_finite(), "clamped zoom must be finite");
+                debug_assert!(clamped_zoom.is_finite(), "clamped zoom must be finite");
 
                 if (clamped_zoom - graph.zoom).abs() > f32::EPSILON {
                     let cursor = cursor_pos.expect("cursor position must exist while zooming");
-                    assert!(
+                    debug_assert!(
                         input_ctx.rect.contains(cursor),
                         "cursor must be inside graph rect while zooming"
                     );
-                    let origin = input_ctx.rect.min;
-                    let graph_pos = (cursor - origin - graph.pan) / graph.zoom;
-
-                    graph.zoom = clamped_zoom;
-                    graph.pan = cursor - origin - graph_pos * graph.zoom;
+                    let anchor = egui::Pos2::ZERO + (cursor - input_ctx.rect.min);
+                    zoom_about(graph, anchor, clamped_zoom);
                 }
-            } else if !wheel_scroll && scroll_delta.length_sq() > f32::EPSILON {
-                graph.pan += scroll_delta;
+            } else if !zoom_wheel && scroll_delta.length_sq() > f32::EPSILON {
+                graph.pan += scroll_pan_delta(scroll_delta, modifiers.shift);
             }
         }
 
-        let ctx = RenderContext::new(ui, &painter, rect, graph);
-        let render_origin = ctx.rect.min + graph.pan;
+        let style = self.style_cache.get(ui.visuals(), graph.zoom);
+        let ctx = RenderContext::new(ui, &painter, rect, graph, &self.node_layout, style);
+        let flow = self
+            .playback
+            .current()
+            .map(|node_id| playback_highlight(graph, node_id))
+            .or_else(|| {
+                ui.input(|input| FlowDirection::from_modifiers(input.modifiers))
+                    .zip(pointer_pos.filter(|pos| ctx.rect.contains(*pos)))
+                    .and_then(|(direction, pos)| {
+                        topmost_node_at(&ctx, graph, pos)
+                            .map(|node_id| flow_highlight(graph, node_id, direction))
+                    })
+            })
+            .unwrap_or_default();
         let mut background = BackgroundRenderer;
-        let mut connections = ConnectionRenderer::default();
-        let mut node_bodies = NodeBodyRenderer;
-        let mut ports = PortRenderer;
-        let mut labels = NodeLabelRenderer;
+        let connections = &mut self.connections;
 
+        let span = RenderTimings::start(&timings);
         background.render(&ctx, graph);
-        connections.rebuild(graph, render_origin, &ctx.layout, &ctx.node_widths, breaker);
-        connections.render(&ctx, graph);
+        RenderTimings::record(&mut timings, RenderStage::Background, span);
+        comment::render_comments(&ctx, graph, &mut self.comment_textures);
+        GroupRenderer { read_only }.render(&ctx, graph);
+        let span = RenderTimings::start(&timings);
+        connections.rebuild(
+            graph,
+            ctx.view,
+            &ctx.layout,
+            &ctx.node_widths,
+            ctx.style,
+            breaker,
+        );
+        RenderTimings::record(&mut timings, RenderStage::ConnectionRebuild, span);
+        connections.flow_highlighted = flow.connections;
+        // Uses last frame's drag, so the highlighted wire is the one spliced on release.
+        connections.splice_target = self
+            .dragged_node
+            .and_then(|node_id| splice_candidate(&ctx, graph, connections, node_id));
 
-        if breaker.active && breaker.points.len() > 1 {
-            ctx.painter().add(egui::Shape::line(
-                breaker.points.clone(),
-                ctx.style.breaker_stroke,
-            ));
+        if let Some(HitTarget::Wire {
+            target_node_id,
+            input_index,
+            source_node_id,
+            output_index,
+        }) = hit
+            && !read_only
+            && ui.input(|input| input.pointer.secondary_clicked())
+            && let Some(pos) = pointer_pos
+        {
+            self.wire_menu = Some(WireMenu {
+                key: ConnectionKey {
+                    target_node_id,
+                    input_index,
+                    source_node_id,
+                    output_index,
+                },
+                pos,
+                just_opened: true,
+            });
+        }
+        if hit == Some(HitTarget::Canvas)
+            && !read_only
+            && ui.input(|input| input.pointer.secondary_clicked())
+            && let Some(pos) = pointer_pos
+        {
+            self.canvas_menu = Some(CanvasMenu {
+                pos,
+                graph_pos: ctx.screen_to_graph(pos),
+                just_opened: true,
+            });
         }
 
-        if connection_drag.active {
+        let invalid_target = hovered_port_ref.filter(|port| {
+            connection_drag.active
+                && port.port.kind == connection_drag.start_port.kind
+                && port.port != connection_drag.start_port
+        });
+        let drag_preview = connection_drag.active.then(|| {
             if let Some(pos) = pointer_pos {
                 connection_drag.current_pos = pos;
             }
             let end_pos = hovered_port_ref
                 .filter(|port| port.port.kind != connection_drag.start_port.kind)
-                .map(|port| port.center)
+                .map(|port| wire_anchor(port.center, port.port.kind, ctx.style.wire_port_offset))
                 .unwrap_or(connection_drag.current_pos);
-            draw_temporary_connection(
-                ctx.painter(),
-                graph.zoom,
-                connection_drag.start_pos,
-                end_pos,
-                connection_drag.start_port.kind,
-                &ctx.style,
+            let replaces = hovered_port_ref.is_some_and(|port| {
+                drop_replaces_connection(graph, connection_drag.start_port, port.port)
+            });
+            let stroke = if invalid_target.is_some() {
+                ctx.style.invalid_connection_stroke
+            } else if replaces {
+                ctx.style.replace_connection_stroke
+            } else {
+                let stroke = ctx.style.temp_connection_stroke;
+                let data_type = port_data_type(graph, connection_drag.start_port);
+                egui::Stroke::new(stroke.width, ctx.style.port_color(data_type, stroke.color))
+            };
+            (end_pos, stroke)
+        });
+        let wires_on_top = self.connections_on_top;
+        if !wires_on_top {
+            let span = RenderTimings::start(&timings);
+            render_wire_layer(
+                &ctx,
+                graph,
+                connections,
+                breaker,
+                connection_drag,
+                drag_preview,
             );
+            RenderTimings::record(&mut timings, RenderStage::ConnectionDraw, span);
         }
 
-        let interaction = node_bodies.render(&ctx, graph);
-        if let Some(node_id) = interaction.remove_request {
-            graph.remove_node(node_id);
-        }
-        ports.render(&ctx, graph);
-        labels.render(&ctx, graph);
+        // Selected nodes render in a second pass so they sit on top, both visually and
+        // for egui hit-testing (later widgets win).
+        let health = self.health_view.then(|| graph.node_health());
+        let mut interaction = node::NodeInteraction::default();
+        for node_order in node_draw_passes(graph) {
+            let mut node_bodies = NodeBodyRenderer {
+                highlighted_nodes: &flow.nodes,
+                health: health.as_ref(),
+                read_only,
+                drag_threshold: self.node_drag_threshold,
+                node_order: &node_order,
+            };
+            let span = RenderTimings::start(&timings);
+            let pass = node_bodies.render(&ctx, graph);
+            RenderTimings::record(&mut timings, RenderStage::NodeBodies, span);
+            let visible_order: Vec<usize> = node_order
+                .iter()
+                .copied()
+                .filter(|index| Some(graph.nodes[*index].id) != pass.remove_request)
+                .collect();
+            let span = RenderTimings::start(&timings);
+            PortRenderer {
+                node_order: &visible_order,
+            }
+            .render(&ctx, graph);
+            RenderTimings::record(&mut timings, RenderStage::Ports, span);
+            let span = RenderTimings::start(&timings);
+            NodeLabelRenderer {
+                node_order: &visible_order,
+            }
+            .render(&ctx, graph);
+            RenderTimings::record(&mut timings, RenderStage::Labels, span);
 
-        if breaker.active && primary_released {
-            remove_connections(graph, connections.highlighted());
-            breaker.reset();
+            if pass.selection_request.is_some() {
+                interaction.selection_request = pass.selection_request;
+                interaction.extend_selection = pass.extend_selection;
+            }
+            interaction.selection_drag = pass.selection_drag.or(interaction.selection_drag);
+            interaction.remove_request = pass.remove_request.or(interaction.remove_request);
+            interaction.double_click_request = pass
+                .double_click_request
+                .or(interaction.double_click_request);
+            interaction.dragged_node = pass.dragged_node.or(interaction.dragged_node);
+        }
+        if wires_on_top {
+            let span = RenderTimings::start(&timings);
+            render_wire_layer(
+                &ctx,
+                graph,
+                connections,
+                breaker,
+                connection_drag,
+                drag_preview,
+            );
+            RenderTimings::record(&mut timings, RenderStage::ConnectionDraw, span);
+        }
+        if let Some(port) = invalid_target {
+            ctx.painter()
+                .circle_filled(port.center, ctx.port_radius, ctx.style.invalid_port_color);
+            ui.ctx().set_cursor_icon(egui::CursorIcon::NotAllowed);
+        }
+        if breaker.active
+            && let Some(pos) = cursor_pos
+        {
+            // egui has no scissors cursor; the crosshair plus the badge mark cutting.
+            ui.ctx().set_cursor_icon(egui::CursorIcon::Crosshair);
+            render_breaker_badge(&ctx, pos, connections.highlighted().len());
+        }
+        if let Some(node_id) = interaction.remove_request {
+            graph.push_undo_checkpoint();
+            graph.remove_node(node_id);
+        }
+        let released_node = self
+            .dragged_node
+            .filter(|id| interaction.dragged_node != Some(*id));
+        if released_node.is_some()
+            && let Some(snapshot) = self.drag_snapshot.take()
+        {
+            graph.push_undo_snapshot(snapshot);
+        }
+        if let Some(node_id) = released_node
+            && let Some(key) = connections.splice_target.take()
+        {
+            graph.push_undo_checkpoint();
+            if let Some(notice) = splice_node(graph, node_id, key) {
+                self.notice = Some(notice);
+            }
+        }
+        if self.ungroup_on_drag_out
+            && let Some(node_id) = released_node
+        {
+            group::release_dragged_member(&ctx, graph, node_id);
+        }
+        self.dragged_node = interaction.dragged_node;
+        if primary_released && self.dragged_node.is_none() {
+            // A click on a node without a drag edits nothing.
+            self.drag_snapshot = None;
+        }
+        if self.debug_node_preview
+            && ui.input(|input| input.modifiers.shift_only())
+            && let Some(node) = hit
+                .and_then(HitTarget::node_id)
+                .and_then(|id| graph.nodes.iter().find(|node| node.id == id))
+        {
+            egui::Tooltip::always_open(
+                ui.ctx().clone(),
+                ui.layer_id(),
+                ui.id().with(("node_json_preview", node.id)),
+                egui::PopupAnchor::Pointer,
+            )
+            .show(|ui| ui.monospace(node.json_preview(NODE_PREVIEW_LINES)));
+        }
+
+        if (interaction.dragged_node.is_some() || connection_drag.active || marquee.active)
+            && let Some(pos) = cursor_pos
+        {
+            let dt = ui.input(|input| input.stable_dt).min(0.1);
+            let nudge = edge_pan_velocity(rect, pos) * dt;
+            if nudge != egui::Vec2::ZERO {
+                graph.pan += nudge;
+                // Keep the dragged node, the selection moving with it, and the wire's start
+                // under their screen anchors. Pinned nodes ignore pan and need no help.
+                if let Some(dragged_id) = interaction.dragged_node {
+                    let moves_selection = graph.selected_nodes.contains(&dragged_id);
+                    for node in graph.nodes.iter_mut().filter(|node| {
+                        !node.screen_pinned
+                            && (node.id == dragged_id
+                                || moves_selection && graph.selected_nodes.contains(&node.id))
+                    }) {
+                        node.pos -= nudge / graph.zoom;
+                    }
+                }
+                let start_pinned = graph.nodes.iter().any(|node| {
+                    node.id == connection_drag.start_port.node_id && node.screen_pinned
+                });
+                if connection_drag.active && !start_pinned {
+                    connection_drag.start_pos += nudge;
+                }
+                ui.ctx().request_repaint();
+            }
+        }
+
+        if breaker.active && breaker_released {
+            if !connections.highlighted().is_empty() {
+                graph.push_undo_checkpoint();
+            }
+            remove_connections(graph, connections.highlighted());
+            breaker.reset();
+        } else if breaker.active && !breaker_down {
+            // The release was missed (e.g. it happened outside the window): cancel
+            // without cutting.
+            breaker.reset();
+        }
+
+        if marquee.active {
+            if let Some(pos) = pointer_pos {
+                marquee.current = ctx.screen_to_graph(pos);
+            }
+            let marquee_rect = marquee.screen_rect(&ctx);
+            if primary_released {
+                let touched: Vec<Uuid> = graph
+                    .nodes
+                    .iter()
+                    .filter(|node| ctx.node_rect(node).intersects(marquee_rect))
+                    .map(|node| node.id)
+                    .collect();
+                marquee.mode.apply(&mut graph.selected_nodes, touched);
+                marquee.reset();
+            } else if !ui.input(|input| input.pointer.primary_down()) {
+                // Missed release, as for the breaker: cancel without selecting.
+                marquee.reset();
+            } else {
+                ctx.painter().rect(
+                    marquee_rect,
+                    0.0,
+                    ctx.style.marquee_fill,
+                    ctx.style.marquee_stroke,
+                    egui::StrokeKind::Inside,
+                );
+            }
         }
 
         if connection_drag.active && primary_released {
+            // A pulled-off wire already checkpointed when it was detached. Otherwise the
+            // pre-drop state is pushed only if the drop changed the graph, so a wire
+            // dropped on nothing keeps the redo list.
+            let before_drop = connection_drag
+                .detached
+                .is_none()
+                .then(|| graph.undo_snapshot());
             if let Some(target) = hovered_port_ref
                 && target.port.kind != connection_drag.start_port.kind
                 && port_in_activation_range(
@@ -361,13 +1434,325 @@ impl GraphUi {
                     port_activation,
                 )
             {
-                apply_connection(graph, connection_drag.start_port, target.port);
+                if let Some(notice) = drop_connection(
+                    graph,
+                    connection_drag,
+                    target.port,
+                    self.occupied_input_drop,
+                ) {
+                    self.notice = Some(notice);
+                }
+            } else if hovered_port_ref.is_none()
+                && let Some(node_id) = topmost_node_at(&ctx, graph, connection_drag.current_pos)
+            {
+                match auto_drop_port(graph, connection_drag.start_port, node_id) {
+                    // Auto-drop only picks free inputs, so nothing is displaced.
+                    Some(port) => {
+                        if let Err(err) = apply_connection(graph, connection_drag.start_port, port)
+                        {
+                            self.notice = Some(err.to_string());
+                            self.rejected_drop = Some(RejectedDrop {
+                                node_id,
+                                until: ui.input(|input| input.time) + REJECTED_DROP_SECONDS,
+                            });
+                        }
+                    }
+                    None => {
+                        self.rejected_drop = Some(RejectedDrop {
+                            node_id,
+                            until: ui.input(|input| input.time) + REJECTED_DROP_SECONDS,
+                        });
+                    }
+                }
+            }
+            if let Some(snapshot) = before_drop
+                && snapshot != graph.undo_snapshot()
+            {
+                graph.push_undo_snapshot(snapshot);
             }
             connection_drag.reset();
         }
 
+        if let Some(rejected) = &self.rejected_drop {
+            let now = ui.input(|input| input.time);
+            match graph.nodes.iter().find(|node| node.id == rejected.node_id) {
+                Some(node) if now < rejected.until => {
+                    ctx.painter().rect_stroke(
+                        ctx.node_rect(node),
+                        ctx.layout.corner_radius,
+                        ctx.style.invalid_connection_stroke,
+                        egui::StrokeKind::Outside,
+                    );
+                    ui.ctx().request_repaint();
+                }
+                _ => self.rejected_drop = None,
+            }
+        }
+
+        if let Some((dragged_id, delta)) = interaction.selection_drag {
+            move_selection_with(graph, dragged_id, delta, ctx.scale);
+        }
         if let Some(selected_id) = interaction.selection_request {
-            graph.select_node(selected_id);
+            if interaction.extend_selection {
+                graph.add_to_selection(selected_id);
+            } else {
+                graph.select_node(selected_id);
+            }
+        }
+
+        if pointer_in_rect && !text_field_focused(ui.ctx()) {
+            handle_clipboard(ui, graph, read_only);
+            // Escape belongs to the wire menu while it is open, and to a text field that
+            // it just unfocused.
+            let deselect = ui.input_mut(|input| {
+                input.consume_key(
+                    egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                    egui::Key::A,
+                )
+            }) || (self.wire_menu.is_none()
+                && self.canvas_menu.is_none()
+                && !self.text_focus_last_frame
+                && ui.input_mut(|input| {
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::Escape)
+                }));
+            if deselect {
+                graph.selected_nodes.clear();
+            } else if ui
+                .input_mut(|input| input.consume_key(egui::Modifiers::COMMAND, egui::Key::A))
+            {
+                graph.select_all();
+            }
+            let delete = ui.input_mut(|input| {
+                input.consume_key(egui::Modifiers::NONE, egui::Key::Delete)
+                    || input.consume_key(egui::Modifiers::NONE, egui::Key::Backspace)
+            });
+            if delete && !read_only && !graph.selected_nodes.is_empty() {
+                graph.push_undo_checkpoint();
+                for node_id in selection_in_node_order(graph) {
+                    graph.remove_node(node_id);
+                }
+            }
+            if !read_only {
+                // Shift+Z first: a plain Ctrl/Cmd+Z match would also accept Shift.
+                let redo = ui.input_mut(|input| {
+                    input.consume_key(
+                        egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                        egui::Key::Z,
+                    )
+                });
+                let undo = !redo
+                    && ui.input_mut(|input| {
+                        input.consume_key(egui::Modifiers::COMMAND, egui::Key::Z)
+                    });
+                if redo {
+                    self.notice = Some(
+                        if graph.redo() {
+                            "Redid the last undone edit"
+                        } else {
+                            "Nothing to redo"
+                        }
+                        .to_string(),
+                    );
+                } else if undo {
+                    self.notice = Some(
+                        if graph.undo() {
+                            "Undid the last edit"
+                        } else {
+                            "Nothing to undo"
+                        }
+                        .to_string(),
+                    );
+                }
+            }
+        }
+
+        if let Some(node_id) = interaction.double_click_request
+            && graph.nodes.iter().any(|node| node.id == node_id)
+        {
+            match self.double_click_action {
+                DoubleClickAction::Focus => {
+                    if graph.select_node(node_id) {
+                        self.pending_focus = Some(node_id);
+                    }
+                }
+                DoubleClickAction::Rename if read_only => {}
+                DoubleClickAction::Rename => {
+                    let name = graph
+                        .nodes
+                        .iter()
+                        .find(|node| node.id == node_id)
+                        .map(|node| node.name.clone())
+                        .expect("double-clicked node must exist");
+                    self.node_rename = Some(NodeRename {
+                        node_id,
+                        text: name,
+                        focus_requested: false,
+                    });
+                }
+                DoubleClickAction::OpenSubgraph => {
+                    self.open_subgraph_request = Some(node_id);
+                }
+                DoubleClickAction::None => {}
+            }
+        }
+
+        let rename_header_rect = self
+            .node_rename
+            .as_ref()
+            .and_then(|rename| graph.nodes.iter().find(|node| node.id == rename.node_id))
+            .map(|node| {
+                let node_rect = ctx.node_rect(node);
+                egui::Rect::from_min_size(
+                    node_rect.min,
+                    egui::vec2(node_rect.width(), ctx.layout.header_height),
+                )
+            });
+        let heading_font = ctx.heading_font.clone();
+        self.render_node_rename(ui, rename_header_rect, heading_font, graph);
+        self.render_wire_menu(ui, graph);
+        self.render_canvas_menu(ui, graph);
+        self.zoom_indicator_rect = Some(render_zoom_indicator(ui, rect, graph));
+        if let Some(timings) = &timings {
+            render_timings_overlay(ui, rect, timings);
+        }
+        self.render_timings = timings;
+        self.text_focus_last_frame = text_field_focused(ui.ctx());
+    }
+
+    fn render_wire_menu(&mut self, ui: &egui::Ui, graph: &mut model::Graph) {
+        let Some(menu) = &mut self.wire_menu else {
+            return;
+        };
+        let connection = graph
+            .nodes
+            .iter_mut()
+            .find(|node| node.id == menu.key.target_node_id)
+            .and_then(|node| node.inputs.get_mut(menu.key.input_index))
+            .and_then(|input| {
+                input.connections.iter_mut().find(|connection| {
+                    (connection.node_id, connection.output_index)
+                        == (menu.key.source_node_id, menu.key.output_index)
+                })
+            });
+        let Some(connection) = connection else {
+            self.wire_menu = None;
+            return;
+        };
+
+        let just_opened = std::mem::take(&mut menu.just_opened);
+        let mut close = false;
+        let area = egui::Area::new(ui.make_persistent_id("wire_menu"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(menu.pos)
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label("Wire label");
+                    let mut text = connection.label.clone().unwrap_or_default();
+                    let edit = ui
+                        .add(egui::TextEdit::singleline(&mut text).hint_text("e.g. units: meters"));
+                    if just_opened {
+                        edit.request_focus();
+                    }
+                    if edit.changed() {
+                        connection.label = (!text.trim().is_empty()).then_some(text);
+                    }
+                    if edit.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                        close = true;
+                    }
+                    if ui.button("Clear label").clicked() {
+                        connection.label = None;
+                        close = true;
+                    }
+                });
+            });
+
+        let escape = ui.input(|input| input.key_pressed(egui::Key::Escape));
+        if close || escape || (!just_opened && area.response.clicked_elsewhere()) {
+            self.wire_menu = None;
+        }
+    }
+
+    fn render_canvas_menu(&mut self, ui: &egui::Ui, graph: &mut model::Graph) {
+        let Some(menu) = &mut self.canvas_menu else {
+            return;
+        };
+        let just_opened = std::mem::take(&mut menu.just_opened);
+        let mut chosen = None;
+        let mut paste_image = false;
+        let templates = &self.node_templates;
+        let area = egui::Area::new(ui.make_persistent_id("canvas_menu"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(menu.pos)
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label("Add node");
+                    for template in templates.templates() {
+                        if ui.button(&template.name).clicked() {
+                            chosen = Some(template.name.clone());
+                        }
+                    }
+                    ui.separator();
+                    paste_image = ui.button("Paste image").clicked();
+                });
+            });
+
+        if let Some(name) = &chosen {
+            graph.push_undo_checkpoint();
+            let node = templates.instantiate(name, menu.graph_pos);
+            let node_id = graph.add_node_with_unique_name(node);
+            graph.select_node(node_id);
+        }
+        if paste_image {
+            self.notice = paste_image_comment(graph, menu.graph_pos, read_clipboard_image());
+        }
+        let escape = ui.input(|input| input.key_pressed(egui::Key::Escape));
+        if chosen.is_some()
+            || paste_image
+            || escape
+            || (!just_opened && area.response.clicked_elsewhere())
+        {
+            self.canvas_menu = None;
+        }
+    }
+
+    fn render_node_rename(
+        &mut self,
+        ui: &mut egui::Ui,
+        header_rect: Option<egui::Rect>,
+        font: egui::FontId,
+        graph: &mut model::Graph,
+    ) {
+        let Some(rename) = self.node_rename.as_mut() else {
+            return;
+        };
+        let (Some(header_rect), Some(node)) = (
+            header_rect,
+            graph
+                .nodes
+                .iter_mut()
+                .find(|node| node.id == rename.node_id),
+        ) else {
+            self.node_rename = None;
+            return;
+        };
+
+        let response = ui.put(
+            header_rect,
+            egui::TextEdit::singleline(&mut rename.text).font(font),
+        );
+        if !rename.focus_requested {
+            response.request_focus();
+            rename.focus_requested = true;
+            return;
+        }
+
+        if response.lost_focus() {
+            let cancelled = ui.input(|input| input.key_pressed(egui::Key::Escape));
+            let name = rename.text.trim();
+            if !cancelled && !name.is_empty() {
+                node.name = name.to_string();
+            }
+            self.node_rename = None;
         }
     }
 }
@@ -379,26 +1764,71 @@ impl WidgetRenderer for BackgroundRenderer {
     type Output = ();
 
     fn render(&mut self, ctx: &RenderContext, graph: &mut model::Graph) -> Self::Output {
-        draw_dotted_background(ctx.painter(), ctx.rect, graph, &ctx.style);
+        draw_dotted_background(ctx.painter(), ctx.rect, graph, ctx.style);
+    }
+}
+
+#[derive(Debug)]
+struct GroupRenderer {
+    read_only: bool,
+}
+
+impl WidgetRenderer for GroupRenderer {
+    type Output = ();
+
+    fn render(&mut self, ctx: &RenderContext, graph: &mut model::Graph) -> Self::Output {
+        group::render_groups(ctx, graph, self.read_only);
     }
 }
 
+/// Kept across frames: curves are rebuilt only when `curve_fingerprint` changes, so a
+/// static view of a static graph reuses last frame's endpoints.
 #[derive(Debug, Default)]
 struct ConnectionRenderer {
     curves: Vec<ConnectionCurve>,
+    curves_fingerprint: Option<u64>,
+    rebuild_count: usize,
     highlighted: HashSet<ConnectionKey>,
+    flow_highlighted: HashSet<ConnectionKey>,
+    /// See `splice_candidate`; set while a node is dragged over a wire.
+    splice_target: Option<ConnectionKey>,
+    /// Copied from `GraphUi::connection_style` and `connection_arrows` each frame.
+    connection_style: ConnectionStyle,
+    connection_arrows: bool,
+    /// Route of the last rebuild, which the drag preview follows too.
+    route: WireRoute,
+    /// Orphaned connections already warned about, so a dangling wire logs once instead
+    /// of on every rebuild.
+    logged_orphans: HashSet<ConnectionKey>,
 }
 
 impl ConnectionRenderer {
     fn rebuild(
         &mut self,
         graph: &model::Graph,
-        origin: egui::Pos2,
+        view: render::ViewTransform,
         layout: &node::NodeLayout,
         node_widths: &std::collections::HashMap<Uuid, f32>,
+        style: &crate::gui::style::GraphStyle,
         breaker: &ConnectionBreaker,
     ) {
-        self.curves = collect_connection_curves(graph, origin, layout, node_widths);
+        let route = WireRoute::new(self.connection_style, style);
+        let port_offset = style.wire_port_offset;
+        let fingerprint = curve_fingerprint(graph, view, layout, node_widths, route, port_offset);
+        self.route = route;
+        if self.curves_fingerprint != Some(fingerprint) {
+            self.curves = collect_connection_curves(
+                graph,
+                view,
+                layout,
+                node_widths,
+                route,
+                port_offset,
+                &mut self.logged_orphans,
+            );
+            self.curves_fingerprint = Some(fingerprint);
+            self.rebuild_count += 1;
+        }
         self.highlighted = if breaker.active && breaker.points.len() > 1 {
             connection_hits(&self.curves, &breaker.points)
         } else {
@@ -409,49 +1839,305 @@ impl ConnectionRenderer {
     fn highlighted(&self) -> &HashSet<ConnectionKey> {
         &self.highlighted
     }
+
+    /// The wire closest to `pos`, if within `max_distance`.
+    fn curve_at(&self, pos: egui::Pos2, max_distance: f32) -> Option<ConnectionKey> {
+        self.curves_near(pos, max_distance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(key, _)| key)
+    }
+
+    /// Every wire within `max_distance` of `pos`, with its distance.
+    fn curves_near(
+        &self,
+        pos: egui::Pos2,
+        max_distance: f32,
+    ) -> impl Iterator<Item = (ConnectionKey, f32)> + '_ {
+        self.curves
+            .iter()
+            .map(move |curve| {
+                let distance = curve
+                    .samples(24)
+                    .windows(2)
+                    .map(|pair| point_segment_distance(pos, pair[0], pair[1]))
+                    .fold(f32::INFINITY, f32::min);
+                (curve.key, distance)
+            })
+            .filter(move |(_, distance)| *distance <= max_distance)
+    }
 }
 
 impl WidgetRenderer for ConnectionRenderer {
     type Output = ();
 
-    fn render(&mut self, ctx: &RenderContext, _graph: &mut model::Graph) -> Self::Output {
-        draw_connections(ctx.painter(), &self.curves, &self.highlighted, &ctx.style);
+    fn render(&mut self, ctx: &RenderContext, graph: &mut model::Graph) -> Self::Output {
+        draw_connections(ctx.painter(), self, &graph.selected_nodes, ctx.style);
+        if ctx.scale >= ctx.style.label_lod_zoom {
+            for curve in &self.curves {
+                if let Some(label) = &curve.label {
+                    draw_connection_label(ctx, curve.midpoint(), label);
+                }
+            }
+        }
     }
 }
 
+fn draw_connection_label(ctx: &RenderContext, midpoint: egui::Pos2, label: &str) {
+    let galley = ctx.text_cache.galley(ctx.painter(), label, &ctx.body_font);
+    let backdrop = egui::Align2::CENTER_CENTER
+        .anchor_size(midpoint, galley.size())
+        .expand(3.0 * ctx.scale);
+    ctx.painter()
+        .rect_filled(backdrop, 3.0 * ctx.scale, ctx.style.node_fill);
+    ctx.text(midpoint, egui::Align2::CENTER_CENTER, label, &ctx.body_font);
+}
+
 #[derive(Debug)]
-struct NodeBodyRenderer;
+struct NodeBodyRenderer<'a> {
+    highlighted_nodes: &'a HashSet<Uuid>,
+    health: Option<&'a HashMap<Uuid, model::NodeHealth>>,
+    node_order: &'a [usize],
+    read_only: bool,
+    drag_threshold: f32,
+}
 
-impl WidgetRenderer for NodeBodyRenderer {
+impl WidgetRenderer for NodeBodyRenderer<'_> {
     type Output = node::NodeInteraction;
 
     fn render(&mut self, ctx: &RenderContext, graph: &mut model::Graph) -> Self::Output {
-        node::render_node_bodies(ctx, graph)
+        node::render_node_bodies(
+            ctx,
+            graph,
+            self.node_order,
+            self.highlighted_nodes,
+            self.health,
+            self.read_only,
+            self.drag_threshold,
+        )
+    }
+}
+
+/// Node indices split into draw passes: unselected nodes first, then the selection.
+fn node_draw_passes(graph: &model::Graph) -> [Vec<usize>; 2] {
+    let (selected, unselected): (Vec<usize>, Vec<usize>) = (0..graph.nodes.len())
+        .partition(|index| graph.selected_nodes.contains(&graph.nodes[*index].id));
+    [unselected, selected]
+}
+
+/// See `HitTarget` for the priority order.
+fn hit_test(
+    ctx: &RenderContext,
+    graph: &model::Graph,
+    ports: &[PortInfo],
+    port_activation: f32,
+    connections: &ConnectionRenderer,
+    pos: egui::Pos2,
+) -> HitTarget {
+    if let Some(port) = find_port_near(ports, pos, port_activation) {
+        return HitTarget::Port {
+            node_id: port.port.node_id,
+            index: port.port.index,
+            kind: port.port.kind,
+        };
+    }
+    if let Some(node_id) = topmost_node_at(ctx, graph, pos) {
+        let node = graph
+            .nodes
+            .iter()
+            .find(|node| node.id == node_id)
+            .expect("hit node must exist");
+        let buttons = node::node_buttons(ctx, node, ctx.node_rect(node));
+        let on_cache = ctx.layout.cache_row_height(node) > 0.0 && buttons.cache.contains(pos);
+        return if buttons.close.contains(pos) || on_cache {
+            HitTarget::NodeButton(node_id)
+        } else {
+            HitTarget::NodeBody(node_id)
+        };
+    }
+    if let Some(group_id) = group::group_title_at(ctx, graph, pos) {
+        return HitTarget::GroupTitle(group_id);
+    }
+    match connections.curve_at(pos, WIRE_HIT_DISTANCE) {
+        Some(key) => HitTarget::Wire {
+            target_node_id: key.target_node_id,
+            input_index: key.input_index,
+            source_node_id: key.source_node_id,
+            output_index: key.output_index,
+        },
+        None => HitTarget::Canvas,
+    }
+}
+
+/// The visually top-most node under `pos`, following the draw passes.
+fn topmost_node_at(ctx: &RenderContext, graph: &model::Graph, pos: egui::Pos2) -> Option<Uuid> {
+    node_draw_passes(graph)
+        .iter()
+        .flatten()
+        .rev()
+        .map(|index| &graph.nodes[*index])
+        .find(|node| ctx.node_rect(node).contains(pos))
+        .map(|node| node.id)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlowDirection {
+    Upstream,
+    Downstream,
+}
+
+impl FlowDirection {
+    /// Alt highlights upstream sources, Ctrl/Cmd highlights downstream consumers.
+    fn from_modifiers(modifiers: egui::Modifiers) -> Option<Self> {
+        if modifiers.alt {
+            Some(Self::Upstream)
+        } else if modifiers.command || modifiers.ctrl {
+            Some(Self::Downstream)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct FlowHighlight {
+    nodes: HashSet<Uuid>,
+    connections: HashSet<ConnectionKey>,
+}
+
+/// Collects the hovered node plus its upstream or downstream nodes, and the wires
+/// running between them.
+fn flow_highlight(graph: &model::Graph, node_id: Uuid, direction: FlowDirection) -> FlowHighlight {
+    let related = match direction {
+        FlowDirection::Upstream => graph.ancestors(node_id),
+        FlowDirection::Downstream => graph.descendants(node_id),
+    };
+    let mut nodes: HashSet<Uuid> = related.into_iter().collect();
+    nodes.insert(node_id);
+
+    let mut connections = HashSet::new();
+    for node in graph.nodes.iter().filter(|node| nodes.contains(&node.id)) {
+        for (input_index, input) in node.inputs.iter().enumerate() {
+            for connection in &input.connections {
+                if nodes.contains(&connection.node_id) {
+                    connections.insert(ConnectionKey::new(node.id, input_index, connection));
+                }
+            }
+        }
+    }
+
+    FlowHighlight { nodes, connections }
+}
+
+/// The playback node and the wires feeding it.
+fn playback_highlight(graph: &model::Graph, node_id: Uuid) -> FlowHighlight {
+    let node = graph
+        .nodes
+        .iter()
+        .find(|node| node.id == node_id)
+        .expect("playback node must exist");
+    let connections = node
+        .inputs
+        .iter()
+        .enumerate()
+        .flat_map(|(input_index, input)| {
+            input
+                .connections
+                .iter()
+                .map(move |connection| ConnectionKey::new(node_id, input_index, connection))
+        })
+        .collect();
+    FlowHighlight {
+        nodes: HashSet::from([node_id]),
+        connections,
     }
 }
 
 #[derive(Debug)]
-struct PortRenderer;
+struct PortRenderer<'a> {
+    node_order: &'a [usize],
+}
 
-impl WidgetRenderer for PortRenderer {
+impl WidgetRenderer for PortRenderer<'_> {
     type Output = ();
 
     fn render(&mut self, ctx: &RenderContext, graph: &mut model::Graph) -> Self::Output {
-        node::render_ports(ctx, graph);
+        node::render_ports(ctx, graph, self.node_order);
     }
 }
 
 #[derive(Debug)]
-struct NodeLabelRenderer;
+struct NodeLabelRenderer<'a> {
+    node_order: &'a [usize],
+}
 
-impl WidgetRenderer for NodeLabelRenderer {
+impl WidgetRenderer for NodeLabelRenderer<'_> {
     type Output = ();
 
     fn render(&mut self, ctx: &RenderContext, graph: &mut model::Graph) -> Self::Output {
-        node::render_node_labels(ctx, graph);
+        node::render_node_labels(ctx, graph, self.node_order);
     }
 }
 
+fn canvas_accessible_label(graph: &model::Graph) -> String {
+    let selected = match graph.selected_nodes.len() {
+        0 => "no selection".to_string(),
+        1 => graph
+            .nodes
+            .iter()
+            .find(|node| graph.selected_nodes.contains(&node.id))
+            .map_or_else(String::new, |node| format!("selected {}", node.name)),
+        count => format!("{count} nodes selected"),
+    };
+    format!("Node graph, {} nodes, {selected}", graph.nodes.len())
+}
+
+/// View shortcuts read in one frame by `keyboard_view_keys`.
+#[derive(Debug, Default)]
+struct KeyboardViewKeys {
+    /// Net `KEYBOARD_ZOOM_STEP`s: one per + (or =) press, minus one per - press.
+    zoom_steps: i32,
+    reset: bool,
+    fit_all: bool,
+}
+
+/// Consumes + / = and - (zoom about the view center), 0 (reset view), and Home (fit
+/// all). Ctrl/Cmd variants are left to egui's own UI zoom.
+fn keyboard_view_keys(input: &mut egui::InputState) -> KeyboardViewKeys {
+    let mut keys = KeyboardViewKeys::default();
+    let mut count = |key| {
+        let mut presses = 0;
+        while input.consume_key(egui::Modifiers::NONE, key) {
+            presses += 1;
+        }
+        presses
+    };
+    keys.zoom_steps = count(egui::Key::Plus) + count(egui::Key::Equals) - count(egui::Key::Minus);
+    keys.reset = count(egui::Key::Num0) > 0;
+    keys.fit_all = count(egui::Key::Home) > 0;
+    keys
+}
+
+/// Arrow keys pan the focused canvas; Shift pans faster.
+fn keyboard_pan_delta(ui: &egui::Ui) -> egui::Vec2 {
+    ui.input(|input| {
+        let step = if input.modifiers.shift { 160.0 } else { 40.0 };
+        let mut delta = egui::Vec2::ZERO;
+        if input.key_pressed(egui::Key::ArrowLeft) {
+            delta.x += step;
+        }
+        if input.key_pressed(egui::Key::ArrowRight) {
+            delta.x -= step;
+        }
+        if input.key_pressed(egui::Key::ArrowUp) {
+            delta.y += step;
+        }
+        if input.key_pressed(egui::Key::ArrowDown) {
+            delta.y -= step;
+        }
+        delta
+    })
+}
+
 fn draw_dotted_background(
     painter: &egui::Painter,
     rect: egui::Rect,
@@ -463,10 +2149,10 @@ fn draw_dotted_background(
         .clamp(style.dotted_radius_min, style.dotted_radius_max);
     let color = style.dotted_color;
 
-    assert!(spacing.is_finite(), "dot spacing must be finite");
-    assert!(spacing > 0.0, "dot spacing must be positive");
-    assert!(radius.is_finite(), "dot radius must be finite");
-    assert!(radius > 0.0, "dot radius must be positive");
+    debug_assert!(spacing.is_finite(), "dot spacing must be finite");
+    debug_assert!(spacing > 0.0, "dot spacing must be positive");
+    debug_assert!(radius.is_finite(), "dot radius must be finite");
+    debug_assert!(radius > 0.0, "dot radius must be positive");
 
     let origin = rect.min + graph.pan;
     let offset_x = (rect.left() - origin.x).rem_euclid(spacing);
@@ -488,52 +2174,160 @@ fn draw_dotted_background(
 #[derive(Debug, Clone)]
 struct ConnectionCurve {
     key: ConnectionKey,
+    source_node_id: Uuid,
+    source_output_index: usize,
     start: egui::Pos2,
     end: egui::Pos2,
-    control_offset: f32,
+    shape: WireShape,
+    label: Option<String>,
+}
+
+impl ConnectionCurve {
+    fn samples(&self, steps: usize) -> Vec<egui::Pos2> {
+        self.shape.samples(steps)
+    }
+
+    fn midpoint(&self) -> egui::Pos2 {
+        self.shape.midpoint()
+    }
+}
+
+/// Hash of everything `collect_connection_curves` reads: view origin and zoom, layout,
+/// wire route, and per node its position, width, row scroll, port counts, and
+/// connections (including labels).
+fn curve_fingerprint(
+    graph: &model::Graph,
+    view: render::ViewTransform,
+    layout: &node::NodeLayout,
+    node_widths: &std::collections::HashMap<Uuid, f32>,
+    route: WireRoute,
+    port_offset: f32,
+) -> u64 {
+    let mut hasher = std::hash::DefaultHasher::new();
+    let (route_kind, route_knob) = match route {
+        WireRoute::Bezier { tension } => (0u8, tension),
+        WireRoute::Orthogonal { mid_fraction } => (1, mid_fraction),
+    };
+    route_kind.hash(&mut hasher);
+    for value in [
+        view.origin.x,
+        view.origin.y,
+        view.view_min.x,
+        view.view_min.y,
+        view.scale,
+        route_knob,
+        port_offset,
+        layout.header_height,
+        layout.cache_height,
+        layout.row_height,
+        layout.padding,
+    ] {
+        value.to_bits().hash(&mut hasher);
+    }
+    (
+        layout.auto_height,
+        layout.max_visible_rows,
+        layout.min_rows,
+        layout.port_align,
+    )
+        .hash(&mut hasher);
+
+    for node in &graph.nodes {
+        node.id.hash(&mut hasher);
+        (
+            node.pos.x.to_bits(),
+            node.pos.y.to_bits(),
+            node.screen_pinned,
+        )
+            .hash(&mut hasher);
+        node_widths
+            .get(&node.id)
+            .map(|width| width.to_bits())
+            .hash(&mut hasher);
+        (node.row_scroll, node.inputs.len(), node.outputs.len()).hash(&mut hasher);
+        for input in &node.inputs {
+            input.connections.len().hash(&mut hasher);
+            for connection in &input.connections {
+                (
+                    connection.node_id,
+                    connection.output_index,
+                    &connection.label,
+                )
+                    .hash(&mut hasher);
+            }
+        }
+    }
+
+    hasher.finish()
 }
 
 fn collect_connection_curves(
     graph: &model::Graph,
-    origin: egui::Pos2,
+    view: render::ViewTransform,
     layout: &node::NodeLayout,
     node_widths: &std::collections::HashMap<Uuid, f32>,
+    route: WireRoute,
+    port_offset: f32,
+    logged_orphans: &mut HashSet<ConnectionKey>,
 ) -> Vec<ConnectionCurve> {
     let node_lookup: std::collections::HashMap<_, _> =
         graph.nodes.iter().map(|node| (node.id, node)).collect();
+    let port_radius = node::port_radius_for_scale(view.scale);
     let mut curves = Vec::new();
 
     for node in &graph.nodes {
         for (input_index, input) in node.inputs.iter().enumerate() {
-            let Some(connection) = &input.connection else {
-                continue;
-            };
-            let source_node = node_lookup
-                .get(&connection.node_id)
-                .expect("graph validation must guarantee source nodes exist");
-            let source_width = node_widths
-                .get(&connection.node_id)
-                .copied()
-                .expect("node width must be precomputed");
-            let start = node::node_output_pos(
-                origin,
-                source_node,
-                connection.output_index,
-                layout,
-                graph.zoom,
-                source_width,
-            );
-            let end = node::node_input_pos(origin, node, input_index, layout, graph.zoom);
-            let control_offset = node::bezier_control_offset(start, end, graph.zoom);
-            curves.push(ConnectionCurve {
-                key: ConnectionKey {
-                    target_node_id: node.id,
-                    input_index,
-                },
-                start,
-                end,
-                control_offset,
-            });
+            let slot_height = 2.0 * port_radius / input.connections.len().max(1) as f32;
+            for (slot, connection) in input.connections.iter().enumerate() {
+                // The graph can change between validation and drawing (e.g. a node removed
+                // mid-frame), so orphaned connections are skipped rather than trusted.
+                let key = ConnectionKey::new(node.id, input_index, connection);
+                let (Some(source_node), Some(source_width)) = (
+                    node_lookup.get(&connection.node_id),
+                    node_widths.get(&connection.node_id).copied(),
+                ) else {
+                    if logged_orphans.insert(key) {
+                        tracing::warn!(
+                            "skipping connection into {}:{input_index} from missing node {}",
+                            node.id,
+                            connection.node_id
+                        );
+                    }
+                    continue;
+                };
+                if connection.output_index >= source_node.outputs.len() {
+                    if logged_orphans.insert(key) {
+                        tracing::warn!(
+                            "skipping connection into {}:{input_index} from missing output {} of {}",
+                            node.id,
+                            connection.output_index,
+                            connection.node_id
+                        );
+                    }
+                    continue;
+                }
+                let start = node::node_output_pos(
+                    view,
+                    source_node,
+                    connection.output_index,
+                    layout,
+                    source_width,
+                ) + egui::vec2(port_offset, 0.0);
+                // Several sources into one input fan out across the port instead of
+                // converging on a single point.
+                let fan = (slot as f32 + 0.5) * slot_height - port_radius;
+                let end = node::node_input_pos(view, node, input_index, layout)
+                    + egui::vec2(-port_offset, fan);
+                curves.push(ConnectionCurve {
+                    key,
+                    source_node_id: connection.node_id,
+                    source_output_index: connection.output_index,
+                    start,
+                    end,
+                    shape: WireShape::new(route, start, end, PortKind::Output, graph.zoom),
+                    label: connection.label.clone(),
+                });
+            }
         }
     }
 
@@ -542,7 +2336,7 @@ fn collect_connection_curves(
 
 fn collect_ports(
     graph: &model::Graph,
-    origin: egui::Pos2,
+    view: render::ViewTransform,
     layout: &node::NodeLayout,
     node_widths: &std::collections::HashMap<Uuid, f32>,
 ) -> Vec<PortInfo> {
@@ -553,8 +2347,8 @@ fn collect_ports(
             .get(&node.id)
             .copied()
             .expect("node width must be precomputed");
-        for (index, _input) in node.inputs.iter().enumerate() {
-            let center = node::node_input_pos(origin, node, index, layout, graph.zoom);
+        for index in layout.visible_ports(node, node::PortSide::Input) {
+            let center = node::node_input_pos(view, node, index, layout);
 
             ports.push(PortInfo {
                 port: PortRef {
@@ -565,8 +2359,8 @@ fn collect_ports(
                 center,
             });
         }
-        for (index, _output) in node.outputs.iter().enumerate() {
-            let center = node::node_output_pos(origin, node, index, layout, graph.zoom, node_width);
+        for index in layout.visible_ports(node, node::PortSide::Output) {
+            let center = node::node_output_pos(view, node, index, layout, node_width);
 
             ports.push(PortInfo {
                 port: PortRef {
@@ -583,8 +2377,8 @@ fn collect_ports(
 }
 
 fn find_port_near(ports: &[PortInfo], pos: egui::Pos2, radius: f32) -> Option<PortInfo> {
-    assert!(radius.is_finite(), "port activation radius must be finite");
-    assert!(radius > 0.0, "port activation radius must be positive");
+    debug_assert!(radius.is_finite(), "port activation radius must be finite");
+    debug_assert!(radius > 0.0, "port activation radius must be positive");
     let mut best = None;
     let mut best_dist = radius;
 
@@ -599,135 +2393,741 @@ fn find_port_near(ports: &[PortInfo], pos: egui::Pos2, radius: f32) -> Option<Po
     best
 }
 
-fn draw_temporary_connection(
-    painter: &egui::Painter,
-    scale: f32,
-    start: egui::Pos2,
-    end: egui::Pos2,
-    start_kind: PortKind,
-    style: &crate::gui::style::GraphStyle,
+/// Wires, then the breaker stroke, then the drag preview (`(end, stroke)`), so the
+/// interaction strokes stay above regular wires whether the layer is drawn under the
+/// nodes or over them (`GraphUi::connections_on_top`).
+fn render_wire_layer(
+    ctx: &RenderContext,
+    graph: &mut model::Graph,
+    connections: &mut ConnectionRenderer,
+    breaker: &ConnectionBreaker,
+    connection_drag: &ConnectionDrag,
+    drag_preview: Option<(egui::Pos2, egui::Stroke)>,
 ) {
-    assert!(scale.is_finite(), "connection scale must be finite");
-    assert!(scale > 0.0, "connection scale must be positive");
-    let control_offset = node::bezier_control_offset(start, end, scale);
-    let (start_sign, end_sign) = match start_kind {
-        PortKind::Output => (1.0, -1.0),
-        PortKind::Input => (-1.0, 1.0),
-    };
-    let stroke = style.temp_connection_stroke;
-    let shape = egui::epaint::CubicBezierShape::from_points_stroke(
-        [
+    connections.render(ctx, graph);
+    if breaker.active && breaker.points.len() > 1 {
+        paint_wire(
+            ctx.painter(),
+            ctx.style,
+            ctx.style.breaker_stroke,
+            |stroke| egui::Shape::line(breaker.points.clone(), stroke),
+        );
+    }
+    if let Some((end_pos, stroke)) = drag_preview {
+        let start = wire_anchor(
+            connection_drag.start_pos,
+            connection_drag.start_port.kind,
+            ctx.style.wire_port_offset,
+        );
+        let shape = WireShape::new(
+            connections.route,
             start,
-            start + egui::vec2(control_offset * start_sign, 0.0),
-            end + egui::vec2(control_offset * end_sign, 0.0),
-            end,
-        ],
-        false,
-        egui::Color32::TRANSPARENT,
-        stroke,
-    );
-    painter.add(shape);
+            end_pos,
+            connection_drag.start_port.kind,
+            graph.zoom,
+        );
+        paint_wire(ctx.painter(), ctx.style, stroke, |stroke| {
+            shape.shape(stroke)
+        });
+    }
+}
+
+/// Where a wire attached to a port at `center` starts: `offset` out along the port's
+/// exit direction, so wires clear the port circle.
+fn wire_anchor(center: egui::Pos2, kind: PortKind, offset: f32) -> egui::Pos2 {
+    match kind {
+        PortKind::Output => center + egui::vec2(offset, 0.0),
+        PortKind::Input => center - egui::vec2(offset, 0.0),
+    }
+}
+
+fn bezier_shape(points: [egui::Pos2; 4], stroke: egui::Stroke) -> egui::Shape {
+    egui::epaint::CubicBezierShape::from_points_stroke(
+        points,
+        false,
+        egui::Color32::TRANSPARENT,
+        stroke,
+    )
+    .into()
+}
+
+/// Every wire-like stroke (connections, drag preview, breaker) goes through here so the
+/// optional feather underlay is applied consistently; `shape` builds the same geometry
+/// for whichever stroke it is given.
+fn paint_wire(
+    painter: &egui::Painter,
+    style: &crate::gui::style::GraphStyle,
+    stroke: egui::Stroke,
+    shape: impl Fn(egui::Stroke) -> egui::Shape,
+) {
+    if let Some(feather) = style.feather_stroke(stroke) {
+        painter.add(shape(feather));
+    }
+    painter.add(shape(stroke));
 }
 
 fn port_in_activation_range(cursor: &egui::Pos2, port_center: egui::Pos2, radius: f32) -> bool {
-    assert!(radius.is_finite(), "port activation radius must be finite");
-    assert!(radius > 0.0, "port activation radius must be positive");
+    debug_assert!(radius.is_finite(), "port activation radius must be finite");
+    debug_assert!(radius > 0.0, "port activation radius must be positive");
     cursor.distance(port_center) <= radius
 }
 
-fn apply_connection(graph: &mut model::Graph, start: PortRef, end: PortRef) {
+/// Adds the wire between `start` and `end`, returning the connection it pushed out of
+/// a full input. A wire between incompatible port types or one that would close a cycle
+/// is refused and the graph is left as is.
+fn apply_connection(
+    graph: &mut model::Graph,
+    start: PortRef,
+    end: PortRef,
+) -> anyhow::Result<Option<model::Connection>> {
     assert!(start.kind != end.kind, "ports must be of opposite types");
     let (output_port, input_port) = match (start.kind, end.kind) {
         (PortKind::Output, PortKind::Input) => (start, end),
         (PortKind::Input, PortKind::Output) => (end, start),
         _ => {
-            return;
+            return Ok(None);
         }
     };
+    if let Err(err) = graph.check_port_types(
+        output_port.node_id,
+        output_port.index,
+        input_port.node_id,
+        input_port.index,
+    ) {
+        anyhow::bail!("Not connected: {err}");
+    }
+    if graph.connection_creates_cycle(output_port.node_id, input_port.node_id) {
+        anyhow::bail!(
+            "Not connected: {} already depends on {}, so the wire would create a cycle",
+            node_name(graph, output_port.node_id),
+            node_name(graph, input_port.node_id)
+        );
+    }
 
-    let output_node = graph
+    Ok(graph
+        .add_connection(
+            output_port.node_id,
+            output_port.index,
+            input_port.node_id,
+            input_port.index,
+        )
+        .expect("dragged ports must exist in the graph"))
+}
+
+/// Whether dropping a wire from `start` on `end` pushes an existing wire out: `end` is
+/// the opposite kind of port and the input is full without already holding this wire.
+fn drop_replaces_connection(graph: &model::Graph, start: PortRef, end: PortRef) -> bool {
+    let (output_port, input_port) = match (start.kind, end.kind) {
+        (PortKind::Output, PortKind::Input) => (start, end),
+        (PortKind::Input, PortKind::Output) => (end, start),
+        _ => return false,
+    };
+    let input = &graph
         .nodes
         .iter()
-        .find(|node| node.id == output_port.node_id)
-        .expect("output node must exist");
-    assert!(
-        output_port.index < output_node.outputs.len(),
-        "output index must be valid for output node"
+        .find(|node| node.id == input_port.node_id)
+        .expect("input port node must exist")
+        .inputs[input_port.index];
+    input.is_full()
+        && !input.connections.iter().any(|connection| {
+            connection.node_id == output_port.node_id
+                && connection.output_index == output_port.index
+        })
+}
+
+/// Connects the dragged wire to `end`. Inputs with room keep their other sources; when
+/// a full input displaces a wire, or the wire is refused for closing a cycle, returns a
+/// notice saying so. With
+/// `OccupiedInputDrop::Swap` and a wire pulled off another input, the displaced source
+/// moves to that input instead of being dropped, unless its type does not fit there or
+/// it would close a cycle; then the drop replaces as usual.
+fn drop_connection(
+    graph: &mut model::Graph,
+    drag: &ConnectionDrag,
+    end: PortRef,
+    mode: OccupiedInputDrop,
+) -> Option<String> {
+    let start = drag.start_port;
+    let input_port = match (start.kind, end.kind) {
+        (PortKind::Output, PortKind::Input) => end,
+        (PortKind::Input, PortKind::Output) => start,
+        _ => return None,
+    };
+    if let Some((port, connection)) = &drag.detached
+        && *port == input_port
+    {
+        // Dropped back where it was pulled off: put the original wire back as it was,
+        // label and slot included, so the gesture changes nothing.
+        graph
+            .nodes
+            .iter_mut()
+            .find(|node| node.id == port.node_id)
+            .expect("detached input node must exist")
+            .inputs[port.index]
+            .connections
+            .push(connection.clone());
+        return None;
+    }
+    let displaced = match apply_connection(graph, start, end) {
+        Ok(displaced) => displaced?,
+        Err(err) => return Some(err.to_string()),
+    };
+
+    let swap_into = drag.detached.as_ref().filter(|(port, _)| {
+        mode == OccupiedInputDrop::Swap
+            && *port != input_port
+            && graph
+                .check_port_types(
+                    displaced.node_id,
+                    displaced.output_index,
+                    port.node_id,
+                    port.index,
+                )
+                .is_ok()
+            && !graph.connection_creates_cycle(displaced.node_id, port.node_id)
+    });
+    let displaced_name = output_label(graph, &displaced);
+    let notice = match swap_into {
+        Some((port, _)) => {
+            graph
+                .add_connection(
+                    displaced.node_id,
+                    displaced.output_index,
+                    port.node_id,
+                    port.index,
+                )
+                .expect("detached input and displaced source must exist");
+            format!(
+                "Swapped: {displaced_name} now feeds {}",
+                input_label(graph, *port)
+            )
+        }
+        None => format!(
+            "Replaced the wire from {displaced_name} into {} (Ctrl/Cmd+Z restores it)",
+            input_label(graph, input_port)
+        ),
+    };
+    Some(notice)
+}
+
+/// The wire a dragged `node_id` would be spliced into if released now: the node has
+/// exactly one input and one output, no wires of its own, its center lies within
+/// `WIRE_HIT_DISTANCE` of exactly one wire, and `splice_fits` accepts that wire.
+fn splice_candidate(
+    ctx: &RenderContext,
+    graph: &model::Graph,
+    connections: &ConnectionRenderer,
+    node_id: Uuid,
+) -> Option<ConnectionKey> {
+    let node = graph.nodes.iter().find(|node| node.id == node_id)?;
+    let wired = node.inputs.iter().any(model::Input::is_connected)
+        || graph
+            .connections()
+            .any(|(_, _, connection)| connection.node_id == node_id);
+    if node.inputs.len() != 1 || node.outputs.len() != 1 || wired {
+        return None;
+    }
+    let mut near = connections.curves_near(ctx.node_rect(node).center(), WIRE_HIT_DISTANCE);
+    match (near.next(), near.next()) {
+        (Some((key, _)), None) => splice_fits(graph, node_id, key).is_ok().then_some(key),
+        _ => None,
+    }
+}
+
+/// Whether both wires of splicing `node_id` into `key` pass `apply_connection`'s
+/// checks: source → node input 0 and node output 0 → target must have compatible types
+/// and close no cycle.
+fn splice_fits(graph: &model::Graph, node_id: Uuid, key: ConnectionKey) -> anyhow::Result<()> {
+    graph.check_port_types(key.source_node_id, key.output_index, node_id, 0)?;
+    graph.check_port_types(node_id, 0, key.target_node_id, key.input_index)?;
+    if graph.connection_creates_cycle(key.source_node_id, node_id)
+        || graph.connection_creates_cycle(node_id, key.target_node_id)
+    {
+        anyhow::bail!(
+            "{} already feeds {}, so the wires would create a cycle",
+            node_name(graph, key.target_node_id),
+            node_name(graph, node_id)
+        );
+    }
+    Ok(())
+}
+
+/// Replaces the wire `key` with source → `node_id` → target through `apply_connection`,
+/// returning a notice; `None` when the wire or node is gone. A node `splice_fits`
+/// rejects leaves the wire alone and gets a "Not inserted" notice.
+fn splice_node(graph: &mut model::Graph, node_id: Uuid, key: ConnectionKey) -> Option<String> {
+    let target = PortRef {
+        node_id: key.target_node_id,
+        index: key.input_index,
+        kind: PortKind::Input,
+    };
+    let connection = graph
+        .nodes
+        .iter()
+        .find(|node| node.id == target.node_id)?
+        .inputs
+        .get(target.index)?
+        .connections
+        .iter()
+        .find(|connection| ConnectionKey::new(target.node_id, target.index, connection) == key)?
+        .clone();
+    let node_name = graph
+        .nodes
+        .iter()
+        .find(|node| node.id == node_id)?
+        .name
+        .clone();
+    if let Err(err) = splice_fits(graph, node_id, key) {
+        return Some(format!("Not inserted: {err}"));
+    }
+    let notice = format!(
+        "Inserted {node_name} between {} and {} (Ctrl/Cmd+Z undoes it)",
+        output_label(graph, &connection),
+        input_label(graph, target)
     );
 
-    let input_node = graph
+    remove_connections(graph, &HashSet::from([key]));
+    let port = |node_id, index, kind| PortRef {
+        node_id,
+        index,
+        kind,
+    };
+    for (start, end) in [
+        (
+            port(
+                connection.node_id,
+                connection.output_index,
+                PortKind::Output,
+            ),
+            port(node_id, 0, PortKind::Input),
+        ),
+        (port(node_id, 0, PortKind::Output), target),
+    ] {
+        apply_connection(graph, start, end).expect("splice_fits accepted both wires");
+    }
+    Some(notice)
+}
+
+/// `node.output` for a connection's source.
+fn output_label(graph: &model::Graph, connection: &model::Connection) -> String {
+    graph
+        .nodes
+        .iter()
+        .find(|node| node.id == connection.node_id)
+        .map(|node| {
+            let output = node
+                .outputs
+                .get(connection.output_index)
+                .map_or("?", |output| output.name.as_str());
+            format!("{}.{output}", node.name)
+        })
+        .unwrap_or_else(|| "a missing node".to_string())
+}
+
+/// The `data_type` declared on `port`; empty when the port is untyped.
+fn port_data_type(graph: &model::Graph, port: PortRef) -> &str {
+    let node = graph
+        .nodes
+        .iter()
+        .find(|node| node.id == port.node_id)
+        .expect("port node must exist");
+    match port.kind {
+        PortKind::Input => &node.inputs[port.index].data_type,
+        PortKind::Output => &node.outputs[port.index].data_type,
+    }
+}
+
+/// Name of the node `node_id`, which must exist.
+fn node_name(graph: &model::Graph, node_id: Uuid) -> &str {
+    graph
+        .nodes
+        .iter()
+        .find(|node| node.id == node_id)
+        .map(|node| node.name.as_str())
+        .expect("named node must exist")
+}
+
+/// `node.input` for an input port.
+fn input_label(graph: &model::Graph, port: PortRef) -> String {
+    let node = graph
+        .nodes
+        .iter()
+        .find(|node| node.id == port.node_id)
+        .expect("input port node must exist");
+    format!("{}.{}", node.name, node.inputs[port.index].name)
+}
+
+/// Selected node ids in `nodes` order, so copies keep the draw order.
+fn selection_in_node_order(graph: &model::Graph) -> Vec<Uuid> {
+    graph
+        .nodes
+        .iter()
+        .map(|node| node.id)
+        .filter(|id| graph.selected_nodes.contains(id))
+        .collect()
+}
+
+/// Ctrl/Cmd+C copies the selected nodes, Ctrl/Cmd+V pastes native clipboard text or a
+/// bare `Graph::copy_nodes` payload offset from the source, and Ctrl/Cmd+D duplicates a
+/// single selected node disconnected (`Graph::duplicate_node`) or several with only
+/// their internal wires. Pastes and duplicates become the selection; neither runs when
+/// `read_only`. Other pasted text is ignored.
+fn handle_clipboard(ui: &egui::Ui, graph: &mut model::Graph, read_only: bool) {
+    let offset = egui::vec2(PASTE_OFFSET, PASTE_OFFSET);
+    let events = ui.input(|input| input.events.clone());
+    for event in events {
+        match event {
+            egui::Event::Copy => {
+                let selected = selection_in_node_order(graph);
+                if !selected.is_empty() {
+                    ui.ctx().copy_text(graph.clipboard_text(&selected));
+                }
+            }
+            egui::Event::Paste(text) if !read_only => {
+                let before_paste = graph.undo_snapshot();
+                if let Some(pasted) = graph
+                    .paste_clipboard_text(&text, offset)
+                    .or_else(|| graph.paste_nodes(text.trim(), offset).ok())
+                    && !pasted.is_empty()
+                {
+                    graph.push_undo_snapshot(before_paste);
+                    graph.selected_nodes = pasted.into_iter().collect();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let selected = selection_in_node_order(graph);
+    if !read_only
+        && !selected.is_empty()
+        && ui.input_mut(|input| input.consume_key(egui::Modifiers::COMMAND, egui::Key::D))
+    {
+        graph.push_undo_checkpoint();
+        if let [node_id] = selected[..] {
+            let copy = graph.duplicate_node(node_id);
+            graph.select_node(copy);
+        } else {
+            let payload = graph.copy_nodes(&selected);
+            let duplicated = graph
+                .paste_nodes(&payload, offset)
+                .expect("copy_nodes output must paste");
+            graph.selected_nodes = duplicated.into_iter().collect();
+        }
+    }
+}
+
+/// Port on `node_id` that a wire dropped on the node body connects to: the first input
+/// with room for another source when dragging from an output, the first output when dragging from an input.
+fn auto_drop_port(graph: &model::Graph, start: PortRef, node_id: Uuid) -> Option<PortRef> {
+    if node_id == start.node_id {
+        return None;
+    }
+    let node = graph
+        .nodes
+        .iter()
+        .find(|node| node.id == node_id)
+        .expect("drop target node must exist");
+    let (kind, index) = match start.kind {
+        PortKind::Output => (
+            PortKind::Input,
+            node.inputs.iter().position(|input| !input.is_full())?,
+        ),
+        PortKind::Input => (PortKind::Output, (!node.outputs.is_empty()).then_some(0)?),
+    };
+    Some(PortRef {
+        node_id,
+        index,
+        kind,
+    })
+}
+
+/// Takes the most recent connection out of an occupied input so dragging from it moves
+/// that wire instead of starting a new one.
+fn detach_input_connection(graph: &mut model::Graph, port: PortRef) -> Option<model::Connection> {
+    if port.kind != PortKind::Input {
+        return None;
+    }
+    let node = graph
         .nodes
         .iter_mut()
-        .find(|node| node.id == input_port.node_id)
-        .expect("input node must exist");
+        .find(|node| node.id == port.node_id)
+        .expect("input port node must exist");
     assert!(
-        input_port.index < input_node.inputs.len(),
+        port.index < node.inputs.len(),
         "input index must be valid for input node"
     );
-    input_node.inputs[input_port.index].connection = Some(model::Connection {
-        node_id: output_port.node_id,
-        output_index: output_port.index,
-    });
+    node.inputs[port.index].connections.pop()
+}
+
+/// Moves every selected node except `dragged_id` (which its header drag already moved)
+/// by the screen-space `delta`.
+fn move_selection_with(graph: &mut model::Graph, dragged_id: Uuid, delta: egui::Vec2, scale: f32) {
+    for node in graph
+        .nodes
+        .iter_mut()
+        .filter(|node| node.id != dragged_id && graph.selected_nodes.contains(&node.id))
+    {
+        // Pinned positions are screen offsets, as in the node header drag.
+        node.pos += if node.screen_pinned {
+            delta
+        } else {
+            delta / scale
+        };
+    }
 }
 
+/// The `(pan, zoom)` framing the bounding box of the selected nodes, zooming out only
+/// when it does not fit at zoom 1.
 fn view_selected_node(
     ui: &egui::Ui,
     painter: &egui::Painter,
     rect: egui::Rect,
+    graph: &model::Graph,
+    base_layout: &node::NodeLayout,
+) -> Option<(egui::Vec2, f32)> {
+    if !has_usable_area(rect) {
+        return None;
+    }
+    let (layout, node_widths) = compute_layout_and_widths(ui, painter, graph, base_layout, 1.0);
+    // Pinned nodes are already on screen and ignore the camera.
+    let bounds = graph.selection_bounds(&layout, &node_widths)?;
+    Some(frame_bounds(rect, bounds, 1.0))
+}
+
+/// Moves the camera along the running view animation. A camera that no longer shows
+/// the last step was panned or zoomed by hand, so the animation stops there.
+fn step_view_animation(
+    view_animation: &mut Option<ViewAnimation>,
+    ui: &egui::Ui,
     graph: &mut model::Graph,
+    now: f64,
 ) {
-    let Some(selected_id) = graph.selected_node_id else {
+    let Some(animation) = view_animation else {
         return;
     };
-    let Some(node) = graph.nodes.iter().find(|node| node.id == selected_id) else {
+    if (graph.pan, graph.zoom) != animation.applied {
+        *view_animation = None;
         return;
+    }
+    // No UI animations (reduced motion) means the camera jumps straight there.
+    let (view, done) = if ui.style().animation_time > 0.0 {
+        animation.view_at(now)
+    } else {
+        ((animation.target_pan, animation.target_zoom), true)
     };
+    (graph.pan, graph.zoom) = view;
+    animation.applied = view;
+    if done {
+        *view_animation = None;
+    } else {
+        ui.ctx().request_repaint();
+    }
+}
 
-    let (layout, node_widths) = compute_layout_and_widths(ui, painter, graph, 1.0);
-    let node_width = node_widths
-        .get(&node.id)
-        .copied()
-        .expect("node width must be precomputed");
-    let size = node::node_rect_for_graph(egui::Pos2::ZERO, node, 1.0, &layout, node_width).size();
-    let center = node.pos.to_vec2() + size * 0.5;
-    graph.zoom = 1.0;
-    graph.pan = rect.center() - rect.min - center;
+/// Shared by every zoom entry point; see `GraphUi::zoom_about`.
+fn zoom_about(graph: &mut model::Graph, anchor: egui::Pos2, new_zoom: f32) {
+    if !new_zoom.is_finite() || !anchor.x.is_finite() || !anchor.y.is_finite() {
+        return;
+    }
+    let graph_pos = render::screen_to_graph(egui::Pos2::ZERO + graph.pan, graph.zoom, anchor);
+    graph.zoom = new_zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    graph.pan = anchor - render::graph_to_screen(egui::Pos2::ZERO, graph.zoom, graph_pos);
+    assert!(
+        graph.pan.x.is_finite() && graph.pan.y.is_finite(),
+        "zoomed pan must be finite"
+    );
 }
 
-fn fit_all_nodes(
-    ui: &egui::Ui,
-    painter: &egui::Painter,
+/// Zoom percentage plus a "1:1" reset in the bottom-right corner of the graph rect.
+/// The reset applies immediately rather than through a `ViewAnimation`.
+fn render_zoom_indicator(
+    ui: &mut egui::Ui,
     rect: egui::Rect,
     graph: &mut model::Graph,
-) {
-    if graph.nodes.is_empty() {
-        graph.zoom = 1.0;
-        graph.pan = egui::Vec2::ZERO;
-        return;
+) -> egui::Rect {
+    const MARGIN: f32 = 8.0;
+    let area = rect.shrink(MARGIN);
+    let mut child = ui.new_child(
+        egui::UiBuilder::new()
+            .max_rect(area)
+            .layout(egui::Layout::bottom_up(egui::Align::Max)),
+    );
+    let frame = egui::Frame::new()
+        .fill(child.visuals().extreme_bg_color.gamma_multiply(0.85))
+        .stroke(child.visuals().widgets.noninteractive.bg_stroke)
+        .corner_radius(4.0)
+        .inner_margin(egui::Margin::symmetric(6, 2));
+    frame
+        .show(&mut child, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(zoom_label(graph.zoom));
+                let reset = ui
+                    .small_button("1:1")
+                    .on_hover_text("Reset zoom to 100% around the view center");
+                if reset.clicked() {
+                    reset_zoom_keeping_center(graph, rect);
+                }
+            });
+        })
+        .response
+        .rect
+}
+
+/// Badge below-right of the cursor counting the wires the breaker would cut on release.
+fn render_breaker_badge(ctx: &RenderContext, pos: egui::Pos2, count: usize) {
+    const OFFSET: egui::Vec2 = egui::vec2(14.0, 14.0);
+    const PADDING: egui::Vec2 = egui::vec2(5.0, 2.0);
+    let painter = ctx.painter();
+    let color = ctx.style.breaker_stroke.color;
+    let font = egui::TextStyle::Small.resolve(ctx.ui().style());
+    let galley = painter.layout_no_wrap(format!("✂ {count}"), font, color);
+    let rect = egui::Rect::from_min_size(pos + OFFSET, galley.size() + PADDING * 2.0);
+    painter.rect(
+        rect,
+        3.0,
+        ctx.ui().visuals().extreme_bg_color,
+        egui::Stroke::new(1.0, color),
+        egui::StrokeKind::Inside,
+    );
+    painter.galley(rect.min + PADDING, galley, color);
+}
+
+/// Bottom-left debug readout of `RenderTimings`, one line per stage plus the total.
+fn render_timings_overlay(ui: &mut egui::Ui, rect: egui::Rect, timings: &RenderTimings) {
+    const MARGIN: f32 = 8.0;
+    let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let mut text = String::new();
+    for stage in RenderStage::ALL {
+        text.push_str(&format!(
+            "{:<13}{:>7.3} ms\n",
+            stage.label(),
+            millis(timings.get(stage))
+        ));
     }
+    text.push_str(&format!(
+        "{:<13}{:>7.3} ms",
+        "total",
+        millis(timings.total())
+    ));
 
-    let (layout, node_widths) = compute_layout_and_widths(ui, painter, graph, 1.0);
-    let mut min = egui::pos2(f32::INFINITY, f32::INFINITY);
-    let mut max = egui::pos2(f32::NEG_INFINITY, f32::NEG_INFINITY);
+    let mut child = ui.new_child(
+        egui::UiBuilder::new()
+            .max_rect(rect.shrink(MARGIN))
+            .layout(egui::Layout::bottom_up(egui::Align::Min)),
+    );
+    egui::Frame::new()
+        .fill(child.visuals().extreme_bg_color.gamma_multiply(0.85))
+        .stroke(child.visuals().widgets.noninteractive.bg_stroke)
+        .corner_radius(4.0)
+        .inner_margin(egui::Margin::symmetric(6, 2))
+        .show(&mut child, |ui| ui.monospace(text));
+}
 
-    for node in &graph.nodes {
-        let node_width = node_widths
-            .get(&node.id)
-            .copied()
-            .expect("node width must be precomputed");
-        let rect = node::node_rect_for_graph(egui::Pos2::ZERO, node, 1.0, &layout, node_width);
-        min.x = min.x.min(rect.min.x);
-        min.y = min.y.min(rect.min.y);
-        max.x = max.x.max(rect.max.x);
-        max.y = max.y.max(rect.max.y);
+fn zoom_label(zoom: f32) -> String {
+    format!("{:.0}%", zoom * 100.0)
+}
+
+/// The image on the system clipboard, or `None` when it holds something else. egui only
+/// surfaces text pastes, so images are read straight from the OS clipboard.
+fn read_clipboard_image() -> anyhow::Result<Option<model::CommentImage>> {
+    let image = match arboard::Clipboard::new()?.get_image() {
+        Ok(image) => image,
+        Err(arboard::Error::ContentNotAvailable) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    model::CommentImage::from_rgba(image.width, image.height, &image.bytes).map(Some)
+}
+
+/// Adds `image` as an image comment at `pos` (undoable); returns the notice to show when
+/// there is nothing to paste.
+fn paste_image_comment(
+    graph: &mut model::Graph,
+    pos: egui::Pos2,
+    image: anyhow::Result<Option<model::CommentImage>>,
+) -> Option<String> {
+    match image {
+        Ok(Some(image)) => {
+            graph.push_undo_checkpoint();
+            graph.add_image_comment(pos, image);
+            None
+        }
+        Ok(None) => Some("The clipboard holds no image".to_string()),
+        Err(err) => Some(format!("Could not paste the image: {err:#}")),
+    }
+}
+
+fn reset_zoom_keeping_center(graph: &mut model::Graph, rect: egui::Rect) {
+    zoom_about(graph, egui::Pos2::ZERO + rect.size() * 0.5, 1.0);
+}
+
+/// Pan velocity (points per second) while dragging at `pointer`: zero unless it is within
+/// `EDGE_PAN_MARGIN` of an edge, growing linearly to `EDGE_PAN_SPEED` at the edge and
+/// beyond. The view moves toward that edge, so pan grows away from it.
+fn edge_pan_velocity(rect: egui::Rect, pointer: egui::Pos2) -> egui::Vec2 {
+    let closeness =
+        |distance: f32| ((EDGE_PAN_MARGIN - distance) / EDGE_PAN_MARGIN).clamp(0.0, 1.0);
+    let axis = |pos: f32, min: f32, max: f32| {
+        (closeness(pos - min) - closeness(max - pos)) * EDGE_PAN_SPEED
+    };
+    egui::vec2(
+        axis(pointer.x, rect.min.x, rect.max.x),
+        axis(pointer.y, rect.min.y, rect.max.y),
+    )
+}
+
+fn finite_or_zero(delta: egui::Vec2) -> egui::Vec2 {
+    if delta.is_finite() {
+        delta
+    } else {
+        egui::Vec2::ZERO
+    }
+}
+
+/// Whether a `TextEdit` has keyboard focus. `Context::wants_keyboard_input` is true for
+/// any focused widget, including the canvas and node bodies a click focuses.
+fn text_field_focused(ctx: &egui::Context) -> bool {
+    ctx.memory(|memory| memory.focused())
+        .is_some_and(|id| egui::text_edit::TextEditState::load(ctx, id).is_some())
+}
+
+fn has_usable_area(rect: egui::Rect) -> bool {
+    rect.is_finite() && rect.width() > 0.0 && rect.height() > 0.0
+}
+
+/// The `(pan, zoom)` framing every node and comment; `None` for an unusable rect.
+fn fit_all_nodes(
+    ui: &egui::Ui,
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    graph: &model::Graph,
+    base_layout: &node::NodeLayout,
+) -> Option<(egui::Vec2, f32)> {
+    if !has_usable_area(rect) {
+        return None;
+    }
+    let bounds = scene_bounds(ui, painter, graph, base_layout);
+    if !bounds.is_finite() {
+        return Some((egui::Vec2::ZERO, 1.0));
     }
+    Some(frame_bounds(rect, bounds, MAX_ZOOM))
+}
 
-    let bounds_size = max - min;
+/// The `(pan, zoom)` centering the graph-space `bounds` in `rect` at the largest zoom up
+/// to `max_zoom` that fits them inside the padding.
+fn frame_bounds(rect: egui::Rect, bounds: egui::Rect, max_zoom: f32) -> (egui::Vec2, f32) {
+    let bounds_size = bounds.size();
     assert!(bounds_size.x.is_finite(), "bounds width must be finite");
     assert!(bounds_size.y.is_finite(), "bounds height must be finite");
 
     let padding = 24.0;
-    let available = rect.size() - egui::vec2(padding * 2.0, padding * 2.0);
+    let padded = rect.size() - egui::vec2(padding * 2.0, padding * 2.0);
+    // Rects smaller than the padding fit edge to edge instead of going negative.
+    let available = if padded.x > 0.0 && padded.y > 0.0 {
+        padded
+    } else {
+        rect.size()
+    };
     let zoom_x = if bounds_size.x > 0.0 {
         available.x / bounds_size.x
     } else {
@@ -738,77 +3138,125 @@ fn fit_all_nodes(
     } else {
         1.0
     };
-    let target_zoom = zoom_x.min(zoom_y).clamp(MIN_ZOOM, MAX_ZOOM);
-    graph.zoom = target_zoom;
+    let target_zoom = zoom_x.min(zoom_y).min(max_zoom).clamp(MIN_ZOOM, MAX_ZOOM);
+    let pan = rect.center() - render::graph_to_screen(rect.min, target_zoom, bounds.center());
+    (pan, target_zoom)
+}
+
+/// Graph-space bounds (zoom 1) of every unpinned node, comment, and group frame;
+/// `Rect::NOTHING` when there are none. A group frame pads its members and adds a title
+/// bar above them (`group::frame_around`), so it reaches past the node rects.
+fn scene_bounds(
+    ui: &egui::Ui,
+    painter: &egui::Painter,
+    graph: &model::Graph,
+    base_layout: &node::NodeLayout,
+) -> egui::Rect {
+    let (layout, node_widths) = compute_layout_and_widths(ui, painter, graph, base_layout, 1.0);
+    let mut bounds = graph
+        .bounds(&layout, &node_widths)
+        .unwrap_or(egui::Rect::NOTHING);
+    for comment in &graph.comments {
+        bounds = bounds.union(egui::Rect::from_min_size(comment.pos, comment.size));
+    }
+    let style = crate::gui::style::GraphStyle::new(ui.visuals(), 1.0);
+    for group in &graph.groups {
+        if let Some(members) = graph.group_bounds(group, &layout, &node_widths) {
+            bounds = bounds.union(group::frame_around(&style, members));
+        }
+    }
 
-    let bounds_center = (min.to_vec2() + max.to_vec2()) * 0.5;
-    graph.pan = rect.center() - rect.min - bounds_center * graph.zoom;
+    bounds
 }
 
 fn compute_layout_and_widths(
     ui: &egui::Ui,
     painter: &egui::Painter,
     graph: &model::Graph,
+    base_layout: &node::NodeLayout,
     scale: f32,
 ) -> (node::NodeLayout, std::collections::HashMap<Uuid, f32>) {
-    let layout = node::NodeLayout::default().scaled(scale);
+    let layout = base_layout.scaled(scale);
     layout.assert_valid();
-    let heading_font = node::scaled_font(ui, egui::TextStyle::Heading, scale);
+    let heading_font = node::header_font(ui, &layout, scale);
     let body_font = node::scaled_font(ui, egui::TextStyle::Body, scale);
     let text_color = ui.visuals().text_color();
-    let style = crate::gui::style::GraphStyle::new(ui, scale);
+    let style = crate::gui::style::GraphStyle::new(ui.visuals(), scale);
     style.validate();
     let widths = node::compute_node_widths(
         painter,
+        &crate::gui::render::TextLayoutCache::new(text_color),
         graph,
         &layout,
         &heading_font,
         &body_font,
-        text_color,
         &style,
     );
     (layout, widths)
 }
+/// Draws every wire of `connections` with its highlight stroke, plus an arrowhead in
+/// the same color at the input end when `ConnectionRenderer::connection_arrows` is on.
 fn draw_connections(
     painter: &egui::Painter,
-    curves: &[ConnectionCurve],
-    highlighted: &HashSet<ConnectionKey>,
+    connections: &ConnectionRenderer,
+    selected_nodes: &HashSet<Uuid>,
     style: &crate::gui::style::GraphStyle,
 ) {
-    for curve in curves {
-        let stroke = if highlighted.contains(&curve.key) {
+    let curves = &connections.curves;
+    // Wires touching a selected node draw last, so they stay on top, and use the
+    // selection stroke unless a breaker or flow highlight applies.
+    let touches_selection = |curve: &&ConnectionCurve| {
+        selected_nodes.contains(&curve.key.target_node_id)
+            || selected_nodes.contains(&curve.source_node_id)
+    };
+    let ordered = curves
+        .iter()
+        .filter(|curve| !touches_selection(curve))
+        .chain(curves.iter().filter(touches_selection));
+    for curve in ordered {
+        let stroke = if connections.splice_target == Some(curve.key) {
+            style.splice_connection_stroke
+        } else if connections.highlighted.contains(&curve.key) {
             style.connection_highlight_stroke
+        } else if connections.flow_highlighted.contains(&curve.key) {
+            style.flow_highlight_stroke
+        } else if touches_selection(&curve) {
+            style.selected_connection_stroke
         } else {
-            style.connection_stroke
+            style.output_connection_stroke(curve.source_output_index)
         };
-        let control_offset = curve.control_offset;
-        let shape = egui::epaint::CubicBezierShape::from_points_stroke(
-            [
-                curve.start,
-                curve.start + egui::vec2(control_offset, 0.0),
-                curve.end + egui::vec2(-control_offset, 0.0),
-                curve.end,
-            ],
-            false,
-            egui::Color32::TRANSPARENT,
-            stroke,
-        );
-        painter.add(shape);
+        paint_wire(painter, style, stroke, |stroke| curve.shape.shape(stroke));
+        if connections.connection_arrows {
+            painter.add(egui::Shape::convex_polygon(
+                connection_arrow(curve, style.connection_arrow_size).to_vec(),
+                stroke.color,
+                egui::Stroke::NONE,
+            ));
+        }
     }
 }
 
+/// Arrowhead triangle with its tip on `curve.end`, pointing along the wire's tangent
+/// there (the bezier derivative at t = 1, or the last leg of a step route); `size` is
+/// its length and its base width.
+fn connection_arrow(curve: &ConnectionCurve, size: f32) -> [egui::Pos2; 3] {
+    let tangent = curve.shape.end_tangent();
+    let direction = if tangent.length_sq() > 0.0 {
+        tangent.normalized()
+    } else {
+        egui::Vec2::X
+    };
+    let base = curve.end - direction * size;
+    let half_width = direction.rot90() * (size * 0.5);
+    [curve.end, base + half_width, base - half_width]
+}
+
 fn connection_hits(curves: &[ConnectionCurve], breaker: &[egui::Pos2]) -> HashSet<ConnectionKey> {
     let mut hits = HashSet::new();
     let breaker_segments = breaker.windows(2).map(|pair| (pair[0], pair[1]));
 
     for curve in curves {
-        let samples = sample_cubic_bezier(
-            curve.start,
-            curve.start + egui::vec2(curve.control_offset, 0.0),
-            curve.end + egui::vec2(-curve.control_offset, 0.0),
-            curve.end,
-            24,
-        );
+        let samples = curve.samples(24);
         let curve_segments = samples.windows(2).map(|pair| (pair[0], pair[1]));
         let mut hit = false;
         for (a1, a2) in breaker_segments.clone() {
@@ -830,6 +3278,16 @@ fn connection_hits(curves: &[ConnectionCurve], breaker: &[egui::Pos2]) -> HashSe
     hits
 }
 
+fn point_segment_distance(point: egui::Pos2, a: egui::Pos2, b: egui::Pos2) -> f32 {
+    let segment = b - a;
+    let length_sq = segment.length_sq();
+    if length_sq <= f32::EPSILON {
+        return point.distance(a);
+    }
+    let t = ((point - a).dot(segment) / length_sq).clamp(0.0, 1.0);
+    point.distance(a + segment * t)
+}
+
 fn sample_cubic_bezier(
     p0: egui::Pos2,
     p1: egui::Pos2,
@@ -894,15 +3352,25 @@ fn remove_connections(graph: &mut model::Graph, highlighted: &HashSet<Connection
     }
     for node in &mut graph.nodes {
         for (input_index, input) in node.inputs.iter_mut().enumerate() {
-            let key = ConnectionKey {
-                target_node_id: node.id,
-                input_index,
-            };
-            if highlighted.contains(&key) {
-                input.connection = None;
-            }
+            input.connections.retain(|connection| {
+                !highlighted.contains(&ConnectionKey::new(node.id, input_index, connection))
+            });
         }
     }
+    let targets: HashSet<Uuid> = highlighted.iter().map(|key| key.target_node_id).collect();
+    for node_id in targets {
+        graph.invalidate_outputs(node_id);
+    }
+}
+
+/// Shift maps vertical wheel motion onto the horizontal axis. egui usually does this
+/// already, but some backends only deliver the vertical delta.
+fn scroll_pan_delta(scroll_delta: egui::Vec2, shift: bool) -> egui::Vec2 {
+    if shift && scroll_delta.x.abs() <= f32::EPSILON {
+        egui::vec2(scroll_delta.y, 0.0)
+    } else {
+        scroll_delta
+    }
 }
 
 fn breaker_path_length(points: &[egui::Pos2]) -> f32 {
@@ -911,3 +3379,2145 @@ fn breaker_path_length(points: &[egui::Pos2]) -> f32 {
         .map(|pair| pair[0].distance(pair[1]))
         .sum()
 }
+
+#[test]
+fn set_view_clamps_invalid_input() {
+    let mut graph_ui = GraphUi::default();
+    let mut graph = model::Graph::default();
+
+    graph_ui.set_view(&mut graph, egui::vec2(120.0, -40.0), 1.5);
+    assert_eq!(
+        graph_ui.current_view(&graph),
+        (egui::vec2(120.0, -40.0), 1.5)
+    );
+
+    graph_ui.set_view(&mut graph, egui::vec2(f32::NAN, 10.0), 100.0);
+    assert_eq!(
+        graph_ui.current_view(&graph),
+        (egui::vec2(120.0, 10.0), MAX_ZOOM)
+    );
+
+    graph_ui.set_view(&mut graph, egui::Vec2::ZERO, f32::INFINITY);
+    assert_eq!(graph_ui.current_view(&graph), (egui::Vec2::ZERO, MAX_ZOOM));
+
+    graph_ui.set_view(&mut graph, egui::Vec2::ZERO, -1.0);
+    assert_eq!(graph_ui.current_view(&graph).1, MIN_ZOOM);
+    assert!(graph.validate().is_ok());
+}
+
+#[test]
+fn fanned_in_wires_end_apart_and_are_cut_one_at_a_time() {
+    let mut graph = model::Graph::test_graph();
+    let [value_a, value_b, sum] = [0, 1, 2].map(|index| graph.nodes[index].id);
+    graph.nodes[2].inputs[0].max_connections = None;
+    graph
+        .add_connection(value_b, 0, sum, 0)
+        .expect("ports exist");
+    let widths = graph
+        .nodes
+        .iter()
+        .map(|node| (node.id, 180.0))
+        .collect::<std::collections::HashMap<_, _>>();
+    let layout = node::NodeLayout::default();
+    let view = render::ViewTransform::unit();
+    let curves = collect_connection_curves(
+        &graph,
+        view,
+        &layout,
+        &widths,
+        WireRoute::Bezier { tension: 1.0 },
+        0.0,
+        &mut HashSet::new(),
+    );
+
+    let into_first: Vec<&ConnectionCurve> = curves
+        .iter()
+        .filter(|curve| curve.key.target_node_id == sum && curve.key.input_index == 0)
+        .collect();
+    let [first, second] = into_first[..] else {
+        panic!("both sources should draw a wire into sum.a");
+    };
+    let port = node::node_input_pos(view, &graph.nodes[2], 0, &layout);
+    let radius = node::port_radius_for_scale(view.scale);
+    assert_ne!(first.end, second.end, "fanned wires end apart");
+    assert!(first.end.distance(port) < radius && second.end.distance(port) < radius);
+
+    remove_connections(&mut graph, &HashSet::from([second.key]));
+    let remaining: Vec<Uuid> = graph.nodes[2].inputs[0]
+        .connections
+        .iter()
+        .map(|connection| connection.node_id)
+        .collect();
+    assert_eq!(remaining, vec![value_a]);
+}
+
+#[test]
+fn wire_ends_clear_their_ports_by_the_style_offset() {
+    let graph = model::Graph::test_graph();
+    let widths = graph
+        .nodes
+        .iter()
+        .map(|node| (node.id, 180.0))
+        .collect::<std::collections::HashMap<_, _>>();
+    let layout = node::NodeLayout::default();
+    let view = render::ViewTransform::unit();
+    let centered = collect_connection_curves(
+        &graph,
+        view,
+        &layout,
+        &widths,
+        WireRoute::Bezier { tension: 1.0 },
+        0.0,
+        &mut HashSet::new(),
+    );
+    let offset = collect_connection_curves(
+        &graph,
+        view,
+        &layout,
+        &widths,
+        WireRoute::Bezier { tension: 1.0 },
+        8.0,
+        &mut HashSet::new(),
+    );
+
+    let curve = &centered[0];
+    let target = graph
+        .nodes
+        .iter()
+        .find(|node| node.id == curve.key.target_node_id)
+        .expect("wire target exists");
+    let input = node::node_input_pos(view, target, curve.key.input_index, &layout);
+    assert_eq!(
+        curve.end, input,
+        "a zero offset ends wires on the port center"
+    );
+    for (centered, offset) in centered.iter().zip(&offset) {
+        assert_eq!(offset.start, centered.start + egui::vec2(8.0, 0.0));
+        assert_eq!(offset.end, centered.end - egui::vec2(8.0, 0.0));
+    }
+    assert_eq!(
+        wire_anchor(input, PortKind::Input, 8.0),
+        input - egui::vec2(8.0, 0.0)
+    );
+}
+
+#[test]
+fn orthogonal_wires_step_through_the_mid_point_and_are_cut_there() {
+    let graph = model::Graph::test_graph();
+    let widths = graph
+        .nodes
+        .iter()
+        .map(|node| (node.id, 180.0))
+        .collect::<std::collections::HashMap<_, _>>();
+    let layout = node::NodeLayout::default();
+    let view = render::ViewTransform::unit();
+    let route = WireRoute::Orthogonal { mid_fraction: 0.25 };
+    let curves = collect_connection_curves(
+        &graph,
+        view,
+        &layout,
+        &widths,
+        route,
+        0.0,
+        &mut HashSet::new(),
+    );
+    let curve = &curves[0];
+    let mid_x = curve.start.x + (curve.end.x - curve.start.x) * 0.25;
+    assert_eq!(
+        curve.samples(24),
+        vec![
+            curve.start,
+            egui::pos2(mid_x, curve.start.y),
+            egui::pos2(mid_x, curve.end.y),
+            curve.end,
+        ]
+    );
+    assert_eq!(
+        curve.midpoint(),
+        egui::pos2(mid_x, (curve.start.y + curve.end.y) / 2.0)
+    );
+
+    // A vertical cut through the first horizontal leg hits; one just past the
+    // vertical leg, above the input's height, misses.
+    let leg_y = curve.start.y;
+    let top = leg_y.min(curve.end.y) - 1.0;
+    let crossing = [
+        egui::pos2(mid_x - 4.0, leg_y - 10.0),
+        egui::pos2(mid_x - 4.0, leg_y + 10.0),
+    ];
+    assert!(connection_hits(std::slice::from_ref(curve), &crossing).contains(&curve.key));
+    let beside = [
+        egui::pos2(mid_x + 4.0, top - 40.0),
+        egui::pos2(mid_x + 4.0, top),
+    ];
+    assert!(connection_hits(std::slice::from_ref(curve), &beside).is_empty());
+}
+
+#[test]
+fn connection_arrows_point_into_the_input() {
+    let mut graph = model::Graph::test_graph();
+    // Put value_a's output well below sum's input so the wire arrives at an angle.
+    graph.nodes[0].pos.y += 200.0;
+    let widths = graph
+        .nodes
+        .iter()
+        .map(|node| (node.id, 180.0))
+        .collect::<std::collections::HashMap<_, _>>();
+    let layout = node::NodeLayout::default();
+    let view = render::ViewTransform::unit();
+    let bezier = collect_connection_curves(
+        &graph,
+        view,
+        &layout,
+        &widths,
+        WireRoute::Bezier { tension: 1.0 },
+        0.0,
+        &mut HashSet::new(),
+    );
+    for curve in &bezier {
+        let [tip, left, right] = connection_arrow(curve, 8.0);
+        assert_eq!(tip, curve.end);
+        let base = left.lerp(right, 0.5);
+        assert!((tip.distance(base) - 8.0).abs() < 1e-3);
+        assert!((left.distance(right) - 8.0).abs() < 1e-3);
+        // Beziers leave and enter horizontally, so every arrow points right.
+        assert!(tip.x > base.x && (tip.y - base.y).abs() < 1e-3);
+    }
+
+    let stepped = collect_connection_curves(
+        &graph,
+        view,
+        &layout,
+        &widths,
+        WireRoute::Orthogonal { mid_fraction: 1.0 },
+        0.0,
+        &mut HashSet::new(),
+    );
+    // With the vertical leg at the input, the last leg is vertical; the arrow follows it.
+    let curve = stepped
+        .iter()
+        .find(|curve| curve.source_node_id == graph.nodes[0].id)
+        .expect("value_a feeds sum");
+    let [tip, left, right] = connection_arrow(curve, 8.0);
+    let base = left.lerp(right, 0.5);
+    assert!(tip.y < base.y, "the wire climbs into the input");
+}
+
+#[test]
+fn connection_curves_skip_orphaned_sources() {
+    let mut graph = model::Graph::test_graph();
+    let widths = graph
+        .nodes
+        .iter()
+        .map(|node| (node.id, 180.0))
+        .collect::<std::collections::HashMap<_, _>>();
+    let layout = node::NodeLayout::default();
+    let all = collect_connection_curves(
+        &graph,
+        render::ViewTransform::unit(),
+        &layout,
+        &widths,
+        WireRoute::Bezier { tension: 1.0 },
+        0.0,
+        &mut HashSet::new(),
+    );
+
+    // Drop the source without `remove_node`, leaving its outbound connections dangling.
+    let value_b = graph
+        .nodes
+        .iter()
+        .position(|node| node.name == "value_b")
+        .expect("test graph should contain value_b");
+    let value_b_id = graph.nodes.remove(value_b).id;
+    let mut logged_orphans = HashSet::new();
+    let curves = collect_connection_curves(
+        &graph,
+        render::ViewTransform::unit(),
+        &layout,
+        &widths,
+        WireRoute::Bezier { tension: 1.0 },
+        0.0,
+        &mut logged_orphans,
+    );
+
+    assert!(curves.len() < all.len());
+    assert!(
+        curves
+            .iter()
+            .all(|curve| curve.source_node_id != value_b_id)
+    );
+    let orphans = all.len() - curves.len();
+    assert_eq!(logged_orphans.len(), orphans, "each orphan is logged once");
+    assert!(
+        logged_orphans
+            .iter()
+            .all(|key| key.source_node_id == value_b_id)
+    );
+
+    // Rendering rebuilds the curves as the view moves, without panicking or logging the
+    // same orphans again.
+    let mut harness = GraphUiHarness::new(graph);
+    for step in 0..3 {
+        harness.graph.pan.x += 10.0 * step as f32;
+        harness.frame(Vec::new());
+    }
+    assert!(harness.graph_ui.connections.rebuild_count > 1);
+    assert_eq!(harness.graph_ui.connections.logged_orphans.len(), orphans);
+}
+
+#[test]
+fn fit_all_ignores_degenerate_rect() {
+    let graph = model::Graph::test_graph();
+    let ctx = egui::Context::default();
+    let _ = ctx.run(egui::RawInput::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let painter = ui.painter().clone();
+            let layout = node::NodeLayout::default();
+
+            let collapsed = egui::Rect::from_min_size(egui::pos2(10.0, 10.0), egui::Vec2::ZERO);
+            assert_eq!(
+                fit_all_nodes(ui, &painter, collapsed, &graph, &layout),
+                None
+            );
+
+            let tiny = egui::Rect::from_min_size(egui::pos2(10.0, 10.0), egui::vec2(30.0, 20.0));
+            let (pan, zoom) =
+                fit_all_nodes(ui, &painter, tiny, &graph, &layout).expect("tiny rect is usable");
+            assert!((MIN_ZOOM..=MAX_ZOOM).contains(&zoom));
+            assert!(pan.x.is_finite() && pan.y.is_finite());
+        });
+    });
+}
+
+#[test]
+fn fit_all_frames_comments_and_nodes() {
+    let mut graph = model::Graph::default();
+    let node_id = graph.add_node(
+        model::Node {
+            name: "far".to_string(),
+            pos: egui::pos2(3000.0, 2000.0),
+            ..model::Node::default()
+        },
+        model::InsertOrder::Append,
+    );
+    graph.comments.push(model::Comment {
+        id: Uuid::new_v4(),
+        text: "overview".to_string(),
+        pos: egui::pos2(-500.0, -400.0),
+        size: egui::vec2(1200.0, 900.0),
+        image: None,
+    });
+    let ctx = egui::Context::default();
+    let _ = ctx.run(egui::RawInput::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let painter = ui.painter().clone();
+            let layout = node::NodeLayout::default();
+            let rect = egui::Rect::from_min_size(egui::pos2(0.0, 0.0), egui::vec2(800.0, 600.0));
+            (graph.pan, graph.zoom) =
+                fit_all_nodes(ui, &painter, rect, &graph, &layout).expect("rect is usable");
+
+            let origin = rect.min + graph.pan;
+            let to_screen = |pos| render::graph_to_screen(origin, graph.zoom, pos);
+            let comment = &graph.comments[0];
+            let comment_rect = egui::Rect::from_two_pos(
+                to_screen(comment.pos),
+                to_screen(comment.pos + comment.size),
+            );
+            let node = graph
+                .nodes
+                .iter()
+                .find(|node| node.id == node_id)
+                .expect("node should exist");
+            let node_rect = egui::Rect::from_two_pos(
+                to_screen(node.pos),
+                to_screen(node.pos + egui::vec2(layout.node_width, layout.header_height)),
+            );
+            let view = rect.expand(0.5);
+            assert!(
+                view.contains_rect(comment_rect),
+                "comment should be in view"
+            );
+            assert!(view.contains_rect(node_rect), "node should be in view");
+        });
+    });
+}
+
+#[test]
+fn fit_all_frames_group_title_bars_and_padding() {
+    let mut graph = model::Graph::default();
+    let node_id = graph.add_node(
+        model::Node {
+            name: "member".to_string(),
+            ..model::Node::default()
+        },
+        model::InsertOrder::Append,
+    );
+    graph.groups.push(model::Group {
+        id: Uuid::new_v4(),
+        name: "group".to_string(),
+        node_ids: vec![node_id],
+    });
+    let ctx = egui::Context::default();
+    let _ = ctx.run(egui::RawInput::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let painter = ui.painter().clone();
+            let layout = node::NodeLayout::default();
+            let rect = egui::Rect::from_min_size(egui::pos2(0.0, 0.0), egui::vec2(800.0, 600.0));
+            (graph.pan, graph.zoom) =
+                fit_all_nodes(ui, &painter, rect, &graph, &layout).expect("rect is usable");
+
+            let style = crate::gui::style::GraphStyle::new(ui.visuals(), graph.zoom);
+            let render_ctx = RenderContext::new(ui, &painter, rect, &graph, &layout, &style);
+            let frame = group::group_rect(&render_ctx, &graph, &graph.groups[0])
+                .expect("group has a member");
+            let node_rect = render_ctx.node_rect(&graph.nodes[0]);
+            assert!(
+                frame.min.y < node_rect.min.y,
+                "title bar sits above the node"
+            );
+            assert!(
+                rect.expand(0.5).contains_rect(frame),
+                "group frame {frame:?} should be in view {rect:?}"
+            );
+        });
+    });
+}
+
+#[test]
+fn zoom_about_keeps_anchor_fixed() {
+    let mut graph_ui = GraphUi::default();
+    let mut graph = model::Graph::default();
+    graph_ui.set_view(&mut graph, egui::vec2(-35.0, 80.0), 0.8);
+
+    let anchor = egui::pos2(420.0, 260.0);
+    let graph_pos = |graph: &model::Graph| {
+        render::screen_to_graph(egui::Pos2::ZERO + graph.pan, graph.zoom, anchor)
+    };
+    let before = graph_pos(&graph);
+    for zoom in [2.5, 0.3, MAX_ZOOM * 10.0] {
+        graph_ui.zoom_about(&mut graph, anchor, zoom);
+        assert!((graph_pos(&graph) - before).length() < 1e-3);
+    }
+    assert_eq!(graph.zoom, MAX_ZOOM);
+}
+
+#[test]
+fn compact_card_hit_areas_cover_only_the_header() {
+    let mut graph = model::Graph::test_graph();
+    let portless = model::Node {
+        name: "note".to_string(),
+        pos: egui::pos2(600.0, 40.0),
+        ..model::Node::default()
+    };
+    let portless_id = portless.id;
+    graph.nodes.push(portless);
+    let ctx = egui::Context::default();
+    let _ = ctx.run(egui::RawInput::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let painter = ui.painter().clone();
+            let layout = node::NodeLayout {
+                min_rows: 0,
+                ..node::NodeLayout::default()
+            };
+            let style = crate::gui::style::GraphStyle::new(ui.visuals(), graph.zoom);
+            let ctx = RenderContext::new(ui, &painter, ui.max_rect(), &graph, &layout, &style);
+            let ports = collect_ports(&graph, ctx.view, &ctx.layout, &ctx.node_widths);
+            let connections = ConnectionRenderer::default();
+            let hit = |pos| hit_test(&ctx, &graph, &ports, 10.0, &connections, pos);
+
+            let node = graph.nodes.last().expect("portless node was added");
+            let rect = ctx.node_rect(node);
+            assert_eq!(rect.height(), ctx.layout.header_height);
+            let buttons = node::node_buttons(&ctx, node, rect);
+            assert_eq!(
+                buttons.cache.height(),
+                0.0,
+                "compact cards have no cache row"
+            );
+            assert!(rect.contains_rect(buttons.close));
+
+            assert_eq!(
+                hit(buttons.close.center()),
+                HitTarget::NodeButton(portless_id)
+            );
+            assert_eq!(
+                hit(rect.left_center() + egui::vec2(4.0, 0.0)),
+                HitTarget::NodeBody(portless_id)
+            );
+            assert_eq!(
+                hit(rect.center_bottom() + egui::vec2(0.0, 4.0)),
+                HitTarget::Canvas,
+                "nothing is reserved below the header"
+            );
+        });
+    });
+}
+
+#[test]
+fn hit_test_follows_priority_order() {
+    let mut graph = model::Graph::test_graph();
+    let ctx = egui::Context::default();
+    let _ = ctx.run(egui::RawInput::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let painter = ui.painter().clone();
+            let rect = ui.max_rect();
+            let layout = node::NodeLayout::default();
+            let style = crate::gui::style::GraphStyle::new(ui.visuals(), graph.zoom);
+            let mut connections = ConnectionRenderer::default();
+            let mut hit_at = |graph: &model::Graph, pos: egui::Pos2| {
+                let ctx = RenderContext::new(ui, &painter, rect, graph, &layout, &style);
+                let ports = collect_ports(graph, ctx.view, &ctx.layout, &ctx.node_widths);
+                let activation = (ctx.port_radius * 1.6).max(10.0);
+                connections.rebuild(
+                    graph,
+                    ctx.view,
+                    &ctx.layout,
+                    &ctx.node_widths,
+                    ctx.style,
+                    &ConnectionBreaker::default(),
+                );
+                (
+                    hit_test(&ctx, graph, &ports, activation, &connections, pos),
+                    ctx.node_rect(&graph.nodes[2]),
+                    node::node_input_pos(ctx.view, &graph.nodes[2], 0, &ctx.layout),
+                    node::node_buttons(&ctx, &graph.nodes[2], ctx.node_rect(&graph.nodes[2])).close,
+                    connections.curves[0].midpoint(),
+                    group::group_rect(&ctx, graph, &graph.groups[0]),
+                )
+            };
+            let sum = graph.nodes[2].id;
+            let (_, sum_rect, port, close, wire_mid, group_frame) =
+                hit_at(&graph, egui::Pos2::ZERO);
+
+            // The input port sits on the node's edge: port beats body.
+            assert!(sum_rect.expand(1.0).contains(port));
+            assert!(matches!(
+                hit_at(&graph, port).0,
+                HitTarget::Port { node_id, index: 0, kind: PortKind::Input } if node_id == sum
+            ));
+            assert_eq!(hit_at(&graph, close.center()).0, HitTarget::NodeButton(sum));
+            assert_eq!(
+                hit_at(&graph, sum_rect.center()).0,
+                HitTarget::NodeBody(sum)
+            );
+            assert!(matches!(
+                hit_at(&graph, wire_mid).0,
+                HitTarget::Wire { target_node_id, input_index: 0, .. } if target_node_id == sum
+            ));
+            assert_eq!(
+                hit_at(&graph, rect.max - egui::vec2(5.0, 5.0)).0,
+                HitTarget::Canvas
+            );
+            // The title bar is a target; the padding around members is click-through.
+            let group_frame = group_frame.expect("test group has members");
+            assert_eq!(
+                hit_at(&graph, group_frame.min + egui::vec2(4.0, 4.0)).0,
+                HitTarget::GroupTitle(graph.groups[0].id)
+            );
+            assert!(
+                hit_at(&graph, group_frame.max - egui::vec2(2.0, 2.0))
+                    .0
+                    .is_background()
+            );
+
+            // A node dragged over the wire wins it.
+            let output = graph.nodes.len() - 1;
+            graph.nodes[output].pos = wire_mid - egui::vec2(20.0, 20.0) - rect.min.to_vec2();
+            assert_eq!(
+                hit_at(&graph, wire_mid).0,
+                HitTarget::NodeBody(graph.nodes[output].id)
+            );
+        });
+    });
+}
+
+#[test]
+fn node_dropped_on_a_wire_is_spliced_in_and_undoable() {
+    let mut graph = model::Graph::test_graph();
+    let scale = graph
+        .add_node_from_spec(r#"{"name": "scale", "inputs": ["in"], "outputs": ["out"]}"#)
+        .expect("spec is valid");
+    let (divide, output) = (graph.nodes[3].id, graph.nodes[4].id);
+    let ctx = egui::Context::default();
+    let _ = ctx.run(egui::RawInput::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let painter = ui.painter().clone();
+            let rect = ui.max_rect();
+            let layout = node::NodeLayout::default();
+            let style = crate::gui::style::GraphStyle::new(ui.visuals(), graph.zoom);
+            let mut connections = ConnectionRenderer::default();
+            // Centers `node_id` on the wire into `output`, then looks for a candidate.
+            let mut drop_on_wire = |graph: &mut model::Graph, node_id: Uuid| {
+                let ctx = RenderContext::new(ui, &painter, rect, graph, &layout, &style);
+                connections.rebuild(
+                    graph,
+                    ctx.view,
+                    &ctx.layout,
+                    &ctx.node_widths,
+                    ctx.style,
+                    &ConnectionBreaker::default(),
+                );
+                let curve = connections
+                    .curves
+                    .iter()
+                    .find(|curve| curve.key.target_node_id == output)
+                    .expect("divide feeds output");
+                let (key, midpoint) = (curve.key, curve.midpoint());
+                let node = graph
+                    .nodes
+                    .iter()
+                    .find(|node| node.id == node_id)
+                    .expect("dropped node exists");
+                let shift = (midpoint - ctx.node_rect(node).center()) / ctx.scale;
+                graph
+                    .nodes
+                    .iter_mut()
+                    .find(|node| node.id == node_id)
+                    .expect("dropped node exists")
+                    .pos += shift;
+                let ctx = RenderContext::new(ui, &painter, rect, graph, &layout, &style);
+                (key, splice_candidate(&ctx, graph, &connections, node_id))
+            };
+
+            // Two inputs: not a candidate.
+            let sum = graph.nodes[2].id;
+            assert_eq!(drop_on_wire(&mut graph, sum).1, None);
+
+            let sources = |graph: &model::Graph, node_id: Uuid| -> Vec<Uuid> {
+                let node = graph.nodes.iter().find(|node| node.id == node_id);
+                node.expect("node exists").inputs[0]
+                    .connections
+                    .iter()
+                    .map(|connection| connection.node_id)
+                    .collect()
+            };
+            let set_scale_input_type = |graph: &mut model::Graph, data_type: &str| {
+                let node = graph.nodes.iter_mut().find(|node| node.id == scale);
+                node.expect("scale exists").inputs[0].data_type = data_type.to_string();
+            };
+            // A text input does not fit divide's number output: not offered, and refused.
+            graph.nodes[3].outputs[0].data_type = "number".to_string();
+            set_scale_input_type(&mut graph, "text");
+            let (key, candidate) = drop_on_wire(&mut graph, scale);
+            assert_eq!(candidate, None);
+            let notice = splice_node(&mut graph, scale, key).expect("wire exists");
+            assert!(notice.starts_with("Not inserted"), "{notice}");
+            assert_eq!(sources(&graph, output), vec![divide]);
+
+            set_scale_input_type(&mut graph, "number");
+            let (key, candidate) = drop_on_wire(&mut graph, scale);
+            assert_eq!(candidate, Some(key));
+            graph.push_undo_checkpoint();
+            let notice = splice_node(&mut graph, scale, key).expect("wire exists");
+            assert!(notice.starts_with("Inserted scale between math(divide).divide"));
+            assert_eq!(sources(&graph, scale), vec![divide]);
+            assert_eq!(sources(&graph, output), vec![scale]);
+            assert!(graph.validate().is_ok());
+            // Now wired itself, the node is no longer a candidate anywhere.
+            assert_eq!(drop_on_wire(&mut graph, scale).1, None);
+
+            assert!(graph.undo());
+            assert_eq!(sources(&graph, scale), Vec::<Uuid>::new());
+            assert_eq!(sources(&graph, output), vec![divide]);
+        });
+    });
+}
+
+#[test]
+fn edge_pan_scales_with_edge_closeness() {
+    let rect = egui::Rect::from_min_size(egui::pos2(0.0, 0.0), egui::vec2(800.0, 600.0));
+    assert_eq!(edge_pan_velocity(rect, rect.center()), egui::Vec2::ZERO);
+
+    let near_left = edge_pan_velocity(rect, egui::pos2(30.0, 300.0));
+    let nearer_left = edge_pan_velocity(rect, egui::pos2(10.0, 300.0));
+    assert!(near_left.x > 0.0 && near_left.y == 0.0);
+    assert!(nearer_left.x > near_left.x);
+    assert_eq!(
+        edge_pan_velocity(rect, egui::pos2(-50.0, 300.0)).x,
+        EDGE_PAN_SPEED
+    );
+
+    let bottom_right = edge_pan_velocity(rect, egui::pos2(795.0, 590.0));
+    assert!(bottom_right.x < 0.0 && bottom_right.y < 0.0);
+}
+
+#[test]
+fn zoom_reset_keeps_view_center() {
+    let mut graph = model::Graph {
+        pan: egui::vec2(-35.0, 80.0),
+        zoom: 2.5,
+        ..Default::default()
+    };
+    let rect = egui::Rect::from_min_size(egui::pos2(40.0, 30.0), egui::vec2(800.0, 600.0));
+    let center = |graph: &model::Graph| {
+        let anchor = egui::Pos2::ZERO + rect.size() * 0.5;
+        render::screen_to_graph(egui::Pos2::ZERO + graph.pan, graph.zoom, anchor)
+    };
+    let before = center(&graph);
+
+    assert_eq!(zoom_label(graph.zoom), "250%");
+    reset_zoom_keeping_center(&mut graph, rect);
+    assert_eq!(graph.zoom, 1.0);
+    assert_eq!(zoom_label(graph.zoom), "100%");
+    assert!((center(&graph) - before).length() < 1e-3);
+}
+
+#[test]
+fn connection_curves_rebuild_only_on_change() {
+    let mut graph = model::Graph::test_graph();
+    let layout = node::NodeLayout::default();
+    let node_widths: std::collections::HashMap<Uuid, f32> =
+        graph.nodes.iter().map(|node| (node.id, 120.0)).collect();
+    let breaker = ConnectionBreaker::default();
+    let style = crate::gui::style::GraphStyle::new(&egui::Visuals::dark(), 1.0);
+    let mut renderer = ConnectionRenderer::default();
+    let rebuild = |renderer: &mut ConnectionRenderer, graph: &model::Graph, origin| {
+        renderer.rebuild(
+            graph,
+            render::ViewTransform::new(origin, egui::Vec2::ZERO, 1.0),
+            &layout,
+            &node_widths,
+            &style,
+            &breaker,
+        );
+        renderer.rebuild_count
+    };
+
+    assert_eq!(rebuild(&mut renderer, &graph, egui::Pos2::ZERO), 1);
+    assert_eq!(rebuild(&mut renderer, &graph, egui::Pos2::ZERO), 1);
+
+    graph.nodes[0].pos.x += 10.0;
+    assert_eq!(rebuild(&mut renderer, &graph, egui::Pos2::ZERO), 2);
+    assert_eq!(rebuild(&mut renderer, &graph, egui::Pos2::ZERO), 2);
+    let start = renderer.curves[0].start;
+
+    assert_eq!(rebuild(&mut renderer, &graph, egui::pos2(5.0, 0.0)), 3);
+    assert_eq!(renderer.curves[0].start, start + egui::vec2(5.0, 0.0));
+
+    graph.nodes[2].inputs[0].connections.clear();
+    assert_eq!(rebuild(&mut renderer, &graph, egui::pos2(5.0, 0.0)), 4);
+
+    renderer.connection_style = ConnectionStyle::Orthogonal;
+    assert_eq!(rebuild(&mut renderer, &graph, egui::pos2(5.0, 0.0)), 5);
+    assert!(matches!(renderer.curves[0].shape, WireShape::Orthogonal(_)));
+}
+
+#[test]
+fn breaker_binding_requires_button_and_modifiers() {
+    let press = |button, modifiers| {
+        let pos = egui::pos2(10.0, 10.0);
+        let raw = egui::RawInput {
+            modifiers,
+            events: vec![
+                egui::Event::PointerMoved(pos),
+                egui::Event::PointerButton {
+                    pos,
+                    button,
+                    pressed: true,
+                    modifiers,
+                },
+            ],
+            ..Default::default()
+        };
+        let ctx = egui::Context::default();
+        let mut results = Vec::new();
+        let _ = ctx.run(raw, |ctx| {
+            for binding in [
+                BreakerBinding::default(),
+                BreakerBinding {
+                    button: egui::PointerButton::Primary,
+                    modifiers: egui::Modifiers::ALT,
+                },
+                BreakerBinding {
+                    button: egui::PointerButton::Secondary,
+                    modifiers: egui::Modifiers::NONE,
+                },
+            ] {
+                results.push(ctx.input(|input| binding.pressed(input)));
+            }
+        });
+        results
+    };
+
+    let primary = egui::PointerButton::Primary;
+    assert_eq!(press(primary, egui::Modifiers::NONE), [true, false, false]);
+    assert_eq!(press(primary, egui::Modifiers::ALT), [true, true, false]);
+    assert_eq!(
+        press(egui::PointerButton::Secondary, egui::Modifiers::ALT),
+        [false, false, true]
+    );
+}
+
+/// Drives a `GraphUi` through a headless egui context with synthetic pointer input, one
+/// frame per call, so interaction logic can be tested without a window.
+#[cfg(test)]
+#[derive(Debug)]
+struct GraphUiHarness {
+    ctx: egui::Context,
+    graph_ui: GraphUi,
+    graph: model::Graph,
+    time: f64,
+    /// Cursor requested by the latest frame.
+    cursor: egui::CursorIcon,
+    /// Modifier keys held down through every frame.
+    modifiers: egui::Modifiers,
+    /// AccessKit tree of the latest frame, once `ctx.enable_accesskit()` was called.
+    accesskit: Option<egui::accesskit::TreeUpdate>,
+    /// Shapes painted in the latest frame.
+    shapes: Vec<egui::epaint::ClippedShape>,
+}
+
+#[cfg(test)]
+impl GraphUiHarness {
+    fn new(graph: model::Graph) -> Self {
+        let mut harness = Self {
+            ctx: egui::Context::default(),
+            graph_ui: GraphUi::default(),
+            graph,
+            time: 0.0,
+            cursor: egui::CursorIcon::Default,
+            modifiers: egui::Modifiers::NONE,
+            accesskit: None,
+            shapes: Vec::new(),
+        };
+        harness.frame(Vec::new());
+        harness
+    }
+
+    fn frame(&mut self, events: Vec<egui::Event>) {
+        self.time += 1.0 / 60.0;
+        let input = egui::RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(
+                egui::Pos2::ZERO,
+                egui::vec2(1280.0, 800.0),
+            )),
+            time: Some(self.time),
+            modifiers: self.modifiers,
+            events,
+            ..Default::default()
+        };
+        let (graph_ui, graph) = (&mut self.graph_ui, &mut self.graph);
+        let output = self.ctx.run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| graph_ui.render(ui, graph));
+        });
+        self.cursor = output.platform_output.cursor_icon;
+        self.accesskit = output.platform_output.accesskit_update;
+        self.shapes = output.shapes;
+    }
+
+    /// Text of every text shape painted in the latest frame.
+    fn painted_texts(&self) -> Vec<String> {
+        self.shapes
+            .iter()
+            .filter_map(|clipped| match &clipped.shape {
+                egui::Shape::Text(text) => Some(text.galley.text().to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Label of the AccessKit node holding focus in the latest frame.
+    fn focused_label(&self) -> Option<String> {
+        let update = self.accesskit.as_ref()?;
+        update
+            .nodes
+            .iter()
+            .find(|(id, _)| *id == update.focus)
+            .and_then(|(_, node)| node.label().map(str::to_string))
+    }
+
+    /// Labels of every AccessKit node in the latest frame.
+    fn accessible_labels(&self) -> Vec<String> {
+        self.accesskit
+            .iter()
+            .flat_map(|update| &update.nodes)
+            .filter_map(|(_, node)| node.label().map(str::to_string))
+            .collect()
+    }
+
+    /// Runs frames until the running view animation, if any, has landed.
+    fn settle_view(&mut self) {
+        for _ in 0..60 {
+            if self.graph_ui.view_animation.is_none() {
+                return;
+            }
+            self.frame(Vec::new());
+        }
+        panic!("view animation did not finish");
+    }
+
+    fn button(pos: egui::Pos2, pressed: bool) -> egui::Event {
+        egui::Event::PointerButton {
+            pos,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: egui::Modifiers::NONE,
+        }
+    }
+
+    /// Hovers `pos`, then presses the primary button there (two frames).
+    fn press(&mut self, pos: egui::Pos2) {
+        self.move_to(pos);
+        self.frame(vec![Self::button(pos, true)]);
+    }
+
+    fn move_to(&mut self, pos: egui::Pos2) {
+        self.frame(vec![egui::Event::PointerMoved(pos)]);
+    }
+
+    /// Releases the primary button at `pos`, plus one settling frame.
+    fn release(&mut self, pos: egui::Pos2) {
+        self.frame(vec![Self::button(pos, false)]);
+        self.frame(Vec::new());
+    }
+
+    /// Screen-space `(output, input)` port centers of the wire into `input_index` of
+    /// `target`, undoing the style's `wire_port_offset`.
+    fn wire_ends(&self, target: Uuid, input_index: usize) -> (egui::Pos2, egui::Pos2) {
+        let curve = self
+            .graph_ui
+            .connections
+            .curves
+            .iter()
+            .find(|curve| {
+                curve.key.target_node_id == target && curve.key.input_index == input_index
+            })
+            .expect("wire should have been drawn");
+        let offset = crate::gui::style::GraphStyle::new(&self.ctx.style().visuals, self.graph.zoom)
+            .wire_port_offset;
+        (
+            curve.start - egui::vec2(offset, 0.0),
+            curve.end + egui::vec2(offset, 0.0),
+        )
+    }
+
+    /// Presses and releases `key` with `modifiers` in one frame.
+    fn key(&mut self, key: egui::Key, modifiers: egui::Modifiers) {
+        let event = |pressed| egui::Event::Key {
+            key,
+            physical_key: None,
+            pressed,
+            repeat: false,
+            modifiers,
+        };
+        self.frame(vec![event(true), event(false)]);
+    }
+
+    /// A screen point on the title bar of `target`, found from its first input port.
+    fn header_point(&self, target: Uuid) -> egui::Pos2 {
+        let (_, port) = self.wire_ends(target, 0);
+        let layout = &self.graph_ui.node_layout;
+        let above_port = layout.header_height * 0.5
+            + layout.cache_height
+            + layout.padding
+            + layout.row_height * 0.5;
+        port + egui::vec2(24.0, -above_port) * self.graph.zoom
+    }
+
+    fn is_wired(&self, target: Uuid, input_index: usize) -> bool {
+        self.graph
+            .nodes
+            .iter()
+            .find(|node| node.id == target)
+            .is_some_and(|node| node.inputs[input_index].is_connected())
+    }
+
+    fn breaker_active(&self) -> bool {
+        self.graph_ui.connection_breaker.active
+    }
+
+    fn connection_drag_active(&self) -> bool {
+        self.graph_ui.connection_drag.active
+    }
+}
+
+#[test]
+fn synthetic_breaker_path_cuts_a_wire() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    let sum = harness.graph.nodes[2].id;
+    let (start, end) = harness.wire_ends(sum, 0);
+    let middle = start + (end - start) * 0.5;
+    let wires = harness.graph.connections().count();
+
+    let path = [
+        middle - egui::vec2(0.0, 30.0),
+        middle - egui::vec2(0.0, 10.0),
+        middle + egui::vec2(0.0, 10.0),
+        middle + egui::vec2(0.0, 30.0),
+    ];
+    harness.press(path[0]);
+    assert!(harness.breaker_active());
+    for pos in &path[1..] {
+        harness.move_to(*pos);
+    }
+    assert!(harness.is_wired(sum, 0), "the cut applies on release");
+    assert_eq!(
+        harness.graph_ui.connections.highlighted().len(),
+        1,
+        "the badge counts the wire about to be cut"
+    );
+    assert_eq!(harness.cursor, egui::CursorIcon::Crosshair);
+    harness.release(path[3]);
+    assert_ne!(harness.cursor, egui::CursorIcon::Crosshair);
+
+    assert!(!harness.breaker_active());
+    assert!(!harness.is_wired(sum, 0));
+    assert_eq!(harness.graph.connections().count(), wires - 1);
+}
+
+#[test]
+fn edge_pan_keeps_the_whole_dragged_selection_under_the_pointer() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    let sum = harness.graph.nodes[2].id;
+    let value_a = harness.graph.nodes[0].id;
+    harness.graph.selected_nodes = HashSet::from([sum, value_a]);
+    let offset = |graph: &model::Graph| graph.nodes[2].pos - graph.nodes[0].pos;
+    let before = offset(&harness.graph);
+
+    let start = harness.header_point(sum);
+    harness.press(start);
+    harness.move_to(start + egui::vec2(20.0, 0.0));
+    let near_edge = egui::pos2(1275.0, start.y);
+    harness.move_to(near_edge);
+    let pan = harness.graph.pan;
+    for _ in 0..10 {
+        harness.frame(Vec::new());
+    }
+    assert_ne!(
+        harness.graph.pan, pan,
+        "holding near the edge pans the view"
+    );
+    assert_eq!(
+        offset(&harness.graph),
+        before,
+        "the selection moves as one while the view pans"
+    );
+    harness.release(near_edge);
+}
+
+#[test]
+fn header_drag_moves_the_node_only_past_the_threshold() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    let sum = harness.graph.nodes[2].id;
+    let start = harness.graph.nodes[2].pos;
+    let header = harness.header_point(sum);
+
+    harness.press(header);
+    harness.move_to(header + egui::vec2(2.0, 1.0));
+    harness.release(header + egui::vec2(2.0, 1.0));
+    assert_eq!(
+        harness.graph.nodes[2].pos, start,
+        "a shaky click does not nudge"
+    );
+    assert_eq!(harness.graph.selected_node(), Some(sum));
+
+    harness.press(header);
+    for step in 1..=4 {
+        harness.move_to(header + egui::vec2(5.0 * step as f32, 0.0));
+    }
+    harness.release(header + egui::vec2(20.0, 0.0));
+    let moved = harness.graph.nodes[2].pos - start;
+    assert!(
+        (moved - egui::vec2(20.0, 0.0) / harness.graph.zoom).length() < 1e-3,
+        "past the threshold the node keeps up with the whole drag: {moved:?}"
+    );
+}
+
+#[test]
+fn shift_click_builds_a_selection_that_drags_together() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    let (sum, divide) = (harness.graph.nodes[2].id, harness.graph.nodes[3].id);
+    let sum_header = harness.header_point(sum);
+    let divide_header = harness.header_point(divide);
+
+    harness.press(sum_header);
+    harness.release(sum_header);
+    harness.modifiers = egui::Modifiers::SHIFT;
+    harness.press(divide_header);
+    harness.release(divide_header);
+    harness.modifiers = egui::Modifiers::NONE;
+    assert_eq!(harness.graph.selected_nodes, HashSet::from([sum, divide]));
+    assert_eq!(harness.graph.selected_node(), None);
+
+    let before: Vec<egui::Pos2> = harness.graph.nodes.iter().map(|node| node.pos).collect();
+    let delta = egui::vec2(40.0, 24.0);
+    harness.press(sum_header);
+    for step in 1..=4 {
+        harness.move_to(sum_header + delta * (step as f32 / 4.0));
+    }
+    harness.release(sum_header + delta);
+    for (node, before) in harness.graph.nodes.iter().zip(before) {
+        let expected = if node.id == sum || node.id == divide {
+            before + delta / harness.graph.zoom
+        } else {
+            before
+        };
+        assert!(
+            (node.pos - expected).length() < 1e-3,
+            "{}: {:?} != {expected:?}",
+            node.name,
+            node.pos
+        );
+    }
+    assert_eq!(
+        harness.graph.selected_nodes,
+        HashSet::from([sum, divide]),
+        "dragging a selected node keeps the selection"
+    );
+
+    harness.press(egui::pos2(640.0, 700.0));
+    harness.release(egui::pos2(640.0, 700.0));
+    assert!(harness.graph.selected_nodes.is_empty());
+    harness.key(egui::Key::A, egui::Modifiers::COMMAND);
+    assert_eq!(
+        harness.graph.selected_nodes.len(),
+        harness.graph.nodes.len()
+    );
+}
+
+#[test]
+fn shift_drag_on_canvas_marquee_selects_touched_nodes() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    let (value_a, sum, divide) = (
+        harness.graph.nodes[0].id,
+        harness.graph.nodes[2].id,
+        harness.graph.nodes[3].id,
+    );
+    harness.graph.select_node(value_a);
+    let wires = harness.graph.connections().count();
+    // From empty canvas below-right of divide (clear of the group title bar) up into sum.
+    let start = harness.header_point(divide) + egui::vec2(180.0, 150.0);
+    let end = harness.header_point(sum) + egui::vec2(0.0, 10.0);
+
+    harness.modifiers = egui::Modifiers::SHIFT;
+    harness.press(start);
+    harness.move_to(start.lerp(end, 0.5));
+    assert!(harness.graph_ui.marquee.active);
+    assert!(!harness.breaker_active(), "shift-drag does not cut wires");
+    // The corners live in graph space, so a mid-drag pan carries the start along.
+    let pan = egui::vec2(0.0, 30.0);
+    harness.graph.pan += pan;
+    harness.move_to(end + pan);
+    harness.release(end + pan);
+    harness.modifiers = egui::Modifiers::NONE;
+
+    assert!(!harness.graph_ui.marquee.active);
+    assert_eq!(
+        harness.graph.selected_nodes,
+        HashSet::from([value_a, sum, divide]),
+        "the marquee adds to the selection"
+    );
+    assert_eq!(
+        harness.graph.connections().count(),
+        wires,
+        "no wire was cut"
+    );
+}
+
+#[test]
+fn marquee_modifiers_replace_add_or_subtract() {
+    let ids: Vec<Uuid> = (0..4).map(|_| Uuid::new_v4()).collect();
+    let apply = |mode: MarqueeMode, touched: &[Uuid]| {
+        let mut selected = HashSet::from([ids[0], ids[1]]);
+        mode.apply(&mut selected, touched.iter().copied());
+        selected
+    };
+    let touched = [ids[1], ids[2]];
+    assert_eq!(
+        apply(MarqueeMode::Replace, &touched),
+        HashSet::from([ids[1], ids[2]])
+    );
+    assert_eq!(
+        apply(MarqueeMode::Add, &touched),
+        HashSet::from([ids[0], ids[1], ids[2]])
+    );
+    assert_eq!(
+        apply(MarqueeMode::Subtract, &touched),
+        HashSet::from([ids[0]])
+    );
+    assert_eq!(apply(MarqueeMode::Replace, &[]), HashSet::new());
+
+    let mode = MarqueeMode::from_modifiers;
+    assert_eq!(mode(egui::Modifiers::NONE), MarqueeMode::Replace);
+    assert_eq!(mode(egui::Modifiers::SHIFT), MarqueeMode::Add);
+    assert_eq!(mode(egui::Modifiers::CTRL), MarqueeMode::Subtract);
+    assert_eq!(mode(egui::Modifiers::COMMAND), MarqueeMode::Subtract);
+    assert_eq!(mode(egui::Modifiers::ALT), MarqueeMode::Subtract);
+}
+
+#[test]
+fn marquee_drags_replace_add_and_subtract_on_the_canvas() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    let (value_a, sum, divide) = (
+        harness.graph.nodes[0].id,
+        harness.graph.nodes[2].id,
+        harness.graph.nodes[3].id,
+    );
+    let start = harness.header_point(divide) + egui::vec2(180.0, 150.0);
+    let end = harness.header_point(sum) + egui::vec2(0.0, 10.0);
+    let drag = |harness: &mut GraphUiHarness, modifiers: egui::Modifiers| {
+        harness.modifiers = modifiers;
+        harness.press(start);
+        harness.move_to(start.lerp(end, 0.5));
+        let marquee = harness.graph_ui.marquee.active;
+        harness.move_to(end);
+        harness.release(end);
+        harness.modifiers = egui::Modifiers::NONE;
+        marquee
+    };
+    let wires = harness.graph.connections().count();
+
+    // Default binding: a plain drag cuts wires, Ctrl still subtracts.
+    harness.graph.selected_nodes = HashSet::from([value_a, sum]);
+    assert!(drag(&mut harness, egui::Modifiers::CTRL));
+    assert_eq!(harness.graph.selected_nodes, HashSet::from([value_a]));
+    harness.modifiers = egui::Modifiers::NONE;
+    harness.press(start);
+    harness.move_to(start.lerp(end, 0.5));
+    assert!(harness.breaker_active() && !harness.graph_ui.marquee.active);
+    harness.release(start.lerp(end, 0.5));
+
+    // With the breaker on the secondary button, a plain drag replaces the selection.
+    harness.graph_ui.breaker_binding = BreakerBinding {
+        button: egui::PointerButton::Secondary,
+        modifiers: egui::Modifiers::NONE,
+    };
+    harness.graph.selected_nodes = HashSet::from([value_a]);
+    assert!(drag(&mut harness, egui::Modifiers::NONE));
+    assert_eq!(harness.graph.selected_nodes, HashSet::from([sum, divide]));
+    harness.graph.selected_nodes = HashSet::from([value_a]);
+    assert!(drag(&mut harness, egui::Modifiers::SHIFT));
+    assert_eq!(
+        harness.graph.selected_nodes,
+        HashSet::from([value_a, sum, divide])
+    );
+    assert!(drag(&mut harness, egui::Modifiers::ALT));
+    assert_eq!(harness.graph.selected_nodes, HashSet::from([value_a]));
+    assert_eq!(
+        harness.graph.connections().count(),
+        wires,
+        "no wire was cut"
+    );
+}
+
+#[test]
+fn ctrl_d_and_payload_paste_select_the_new_nodes() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    let (sum, divide) = (harness.graph.nodes[2].id, harness.graph.nodes[3].id);
+    harness.move_to(egui::pos2(640.0, 700.0));
+    harness.graph.select_node(sum);
+    harness.graph.add_to_selection(divide);
+
+    harness.key(egui::Key::D, egui::Modifiers::COMMAND);
+    assert_eq!(harness.graph.nodes.len(), 7);
+    assert_eq!(
+        harness.graph.nodes[6].inputs[0].connections[0].node_id, harness.graph.nodes[5].id,
+        "several nodes duplicate with their internal wires"
+    );
+    let duplicated: HashSet<Uuid> = harness.graph.nodes[5..]
+        .iter()
+        .map(|node| node.id)
+        .collect();
+    assert_eq!(harness.graph.selected_nodes, duplicated);
+
+    let payload = harness.graph.copy_nodes(&[sum]);
+    harness.frame(vec![egui::Event::Paste(payload)]);
+    assert_eq!(harness.graph.nodes.len(), 8);
+    assert_eq!(
+        harness.graph.selected_nodes,
+        HashSet::from([harness.graph.nodes[7].id])
+    );
+
+    // One selected node duplicates disconnected, and the copy becomes the selection.
+    harness.key(egui::Key::D, egui::Modifiers::COMMAND);
+    assert_eq!(harness.graph.nodes.len(), 9);
+    let copy = &harness.graph.nodes[8];
+    assert_eq!(harness.graph.selected_node(), Some(copy.id));
+    assert!(copy.inputs.iter().all(|input| input.connections.is_empty()));
+
+    for _ in 0..3 {
+        harness.key(egui::Key::Z, egui::Modifiers::COMMAND);
+    }
+    assert_eq!(harness.graph.nodes.len(), 5);
+}
+
+#[test]
+fn delete_and_backspace_remove_the_selection_and_its_wires() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    let (sum, divide) = (harness.graph.nodes[2].id, harness.graph.nodes[3].id);
+    let touches = |graph: &model::Graph, id: Uuid| {
+        graph
+            .connections()
+            .any(|(target, _, connection)| target.id == id || connection.node_id == id)
+    };
+    harness.move_to(egui::pos2(640.0, 700.0));
+
+    harness.graph.select_node(sum);
+    harness.key(egui::Key::Delete, egui::Modifiers::NONE);
+    assert_eq!(harness.graph.nodes.len(), 4);
+    assert!(!touches(&harness.graph, sum), "wires from sum are severed");
+    assert!(harness.graph.validate().is_ok());
+
+    harness.graph.select_node(divide);
+    harness.graph_ui.read_only = true;
+    harness.key(egui::Key::Backspace, egui::Modifiers::NONE);
+    assert_eq!(harness.graph.nodes.len(), 4, "read-only keeps the node");
+    harness.graph_ui.read_only = false;
+    harness.key(egui::Key::Backspace, egui::Modifiers::NONE);
+    assert_eq!(harness.graph.nodes.len(), 3);
+    assert!(!touches(&harness.graph, divide));
+    assert!(harness.graph.selected_nodes.is_empty());
+}
+
+#[test]
+fn right_click_on_canvas_opens_the_add_node_menu_at_the_graph_position() {
+    let mut graph = model::Graph::test_graph();
+    graph.zoom = 2.0;
+    graph.pan = egui::vec2(-100.0, 40.0);
+    let mut harness = GraphUiHarness::new(graph);
+    let pos = egui::pos2(900.0, 700.0);
+    harness.move_to(pos);
+    let secondary = |pressed| egui::Event::PointerButton {
+        pos,
+        button: egui::PointerButton::Secondary,
+        pressed,
+        modifiers: egui::Modifiers::NONE,
+    };
+    harness.frame(vec![secondary(true)]);
+    harness.frame(vec![secondary(false)]);
+
+    let menu = harness.graph_ui.canvas_menu.as_ref().expect("menu opened");
+    assert_eq!(menu.pos, pos);
+    let graph_pos = menu.graph_pos;
+    harness.key(egui::Key::Escape, egui::Modifiers::NONE);
+    assert!(harness.graph_ui.canvas_menu.is_none());
+
+    // A node placed at the menu's graph position sits right under the click.
+    let node = harness
+        .graph_ui
+        .node_templates
+        .instantiate("value", graph_pos);
+    let node_id = harness.graph.add_node_with_unique_name(node);
+    harness.move_to(pos + egui::vec2(4.0, 4.0));
+    assert_eq!(
+        harness.graph_ui.hovered_target(),
+        Some(HitTarget::NodeBody(node_id))
+    );
+}
+
+#[test]
+fn escape_and_ctrl_shift_a_clear_the_selection() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    let sum = harness.graph.nodes[2].id;
+    let ctrl = egui::Modifiers {
+        ctrl: true,
+        command: true,
+        ..egui::Modifiers::NONE
+    };
+    harness.move_to(egui::pos2(640.0, 700.0));
+
+    harness.graph.select_node(sum);
+    harness.key(egui::Key::Escape, egui::Modifiers::NONE);
+    assert!(harness.graph.selected_nodes.is_empty());
+
+    harness.graph.select_node(sum);
+    harness.key(egui::Key::A, ctrl | egui::Modifiers::SHIFT);
+    assert!(harness.graph.selected_nodes.is_empty());
+
+    // Escape first ends a rename that has keyboard focus, keeping the selection.
+    harness.graph.select_node(sum);
+    harness.graph_ui.node_rename = Some(NodeRename {
+        node_id: sum,
+        text: "renamed".to_string(),
+        focus_requested: false,
+    });
+    harness.frame(Vec::new());
+    harness.frame(Vec::new());
+    assert!(harness.ctx.wants_keyboard_input(), "rename field has focus");
+    harness.key(egui::Key::Escape, egui::Modifiers::NONE);
+    assert_eq!(harness.graph.selected_node(), Some(sum));
+    harness.frame(Vec::new());
+    assert!(harness.graph_ui.node_rename.is_none());
+    harness.key(egui::Key::Escape, egui::Modifiers::NONE);
+    assert!(harness.graph.selected_nodes.is_empty());
+}
+
+#[test]
+fn synthetic_port_drag_adds_a_connection() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    let sum = harness.graph.nodes[2].id;
+    let (output, input) = harness.wire_ends(sum, 1);
+    harness.graph.nodes[2].inputs[1].connections.clear();
+    harness.frame(Vec::new());
+
+    harness.press(output);
+    assert!(harness.connection_drag_active());
+    for pos in [
+        output + egui::vec2(20.0, 0.0),
+        input - egui::vec2(20.0, 0.0),
+        input,
+    ] {
+        harness.move_to(pos);
+    }
+    harness.release(input);
+
+    assert!(!harness.connection_drag_active());
+    assert!(harness.is_wired(sum, 1));
+    assert_eq!(
+        harness.graph.nodes[2].inputs[1]
+            .connections
+            .first()
+            .map(|connection| connection.node_id),
+        Some(harness.graph.nodes[1].id)
+    );
+}
+
+#[test]
+fn swap_falls_back_to_replace_when_it_would_close_a_cycle() {
+    let mut graph = model::Graph::test_graph();
+    let (value_a, sum, divide) = (graph.nodes[0].id, graph.nodes[2].id, graph.nodes[3].id);
+    // Pull value_a off sum.a and drop it on divide.sum: swapping would wire sum into itself.
+    let connection = graph.nodes[2].inputs[0].connections.remove(0);
+    let drag = ConnectionDrag {
+        active: true,
+        start_port: PortRef {
+            node_id: value_a,
+            index: 0,
+            kind: PortKind::Output,
+        },
+        detached: Some((
+            PortRef {
+                node_id: sum,
+                index: 0,
+                kind: PortKind::Input,
+            },
+            connection,
+        )),
+        ..ConnectionDrag::default()
+    };
+    let end = PortRef {
+        node_id: divide,
+        index: 0,
+        kind: PortKind::Input,
+    };
+    let notice = drop_connection(&mut graph, &drag, end, OccupiedInputDrop::Swap)
+        .expect("a full input reports the wire it displaced");
+    assert!(notice.starts_with("Replaced"), "{notice}");
+    assert!(graph.nodes[2].inputs[0].connections.is_empty());
+    assert_eq!(graph.nodes[3].inputs[0].connections[0].node_id, value_a);
+    assert!(graph.validate().is_ok());
+}
+
+#[test]
+fn dropping_on_an_occupied_input_swaps_or_records_an_undo() {
+    let sources = |harness: &GraphUiHarness| -> Vec<Option<Uuid>> {
+        harness.graph.nodes[2]
+            .inputs
+            .iter()
+            .map(|input| {
+                input
+                    .connections
+                    .first()
+                    .map(|connection| connection.node_id)
+            })
+            .collect()
+    };
+    // Pull the wire off sum's first input and drop it on the second.
+    let pull_first_onto_second = |harness: &mut GraphUiHarness| {
+        let sum = harness.graph.nodes[2].id;
+        let (_, first) = harness.wire_ends(sum, 0);
+        let (_, second) = harness.wire_ends(sum, 1);
+        harness.press(first);
+        harness.move_to(first - egui::vec2(30.0, 0.0));
+        harness.move_to(second);
+        harness.release(second);
+        second
+    };
+    let values = |harness: &GraphUiHarness| (harness.graph.nodes[0].id, harness.graph.nodes[1].id);
+
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    let (value_a, value_b) = values(&harness);
+    harness.graph_ui.occupied_input_drop = OccupiedInputDrop::Swap;
+    pull_first_onto_second(&mut harness);
+    assert_eq!(sources(&harness), vec![Some(value_b), Some(value_a)]);
+    assert!(
+        harness
+            .graph_ui
+            .take_notice()
+            .is_some_and(|notice| notice.starts_with("Swapped"))
+    );
+
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    let (value_a, value_b) = values(&harness);
+    let pointer = pull_first_onto_second(&mut harness);
+    assert_eq!(sources(&harness), vec![None, Some(value_a)]);
+    assert!(
+        harness
+            .graph_ui
+            .take_notice()
+            .is_some_and(|notice| notice.contains("value_b"))
+    );
+    harness.frame(vec![
+        egui::Event::PointerMoved(pointer),
+        egui::Event::Key {
+            key: egui::Key::Z,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers: egui::Modifiers::COMMAND,
+        },
+    ]);
+    assert_eq!(sources(&harness), vec![Some(value_a), Some(value_b)]);
+    assert_eq!(
+        harness.graph_ui.take_notice().as_deref(),
+        Some("Undid the last edit")
+    );
+}
+
+#[test]
+fn apply_connection_refuses_wires_that_close_a_cycle() {
+    let mut graph = model::Graph::test_graph();
+    let (sum, divide) = (graph.nodes[2].id, graph.nodes[3].id);
+    let port = |node_id, kind| PortRef {
+        node_id,
+        index: 0,
+        kind,
+    };
+    let before = serde_json::to_string(&graph).expect("graph serializes");
+    let err = apply_connection(
+        &mut graph,
+        port(divide, PortKind::Output),
+        port(sum, PortKind::Input),
+    )
+    .expect_err("divide already depends on sum");
+    assert!(err.to_string().contains("cycle"), "{err}");
+    assert_eq!(
+        serde_json::to_string(&graph).expect("graph serializes"),
+        before
+    );
+    assert!(graph.validate().is_ok());
+
+    let value_a = graph.nodes[0].id;
+    apply_connection(
+        &mut graph,
+        port(value_a, PortKind::Output),
+        port(divide, PortKind::Input),
+    )
+    .expect("feeding divide from a value stays acyclic");
+}
+
+#[test]
+fn drop_replaces_connection_reports_full_inputs() {
+    let mut graph = model::Graph::test_graph();
+    let (value_a, value_b, sum) = (graph.nodes[0].id, graph.nodes[1].id, graph.nodes[2].id);
+    let port = |node_id, index, kind| PortRef {
+        node_id,
+        index,
+        kind,
+    };
+    let first_input = port(sum, 0, PortKind::Input);
+    // value_a already feeds sum's capped first input.
+    assert!(drop_replaces_connection(
+        &graph,
+        port(value_b, 0, PortKind::Output),
+        first_input
+    ));
+    assert!(drop_replaces_connection(
+        &graph,
+        first_input,
+        port(value_b, 0, PortKind::Output)
+    ));
+    assert!(
+        !drop_replaces_connection(&graph, port(value_a, 0, PortKind::Output), first_input),
+        "re-dropping the existing wire replaces nothing"
+    );
+    assert!(!drop_replaces_connection(
+        &graph,
+        port(value_b, 0, PortKind::Output),
+        port(value_a, 0, PortKind::Output)
+    ));
+
+    graph.nodes[2].inputs[0].max_connections = None;
+    assert!(
+        !drop_replaces_connection(&graph, port(value_b, 0, PortKind::Output), first_input),
+        "an uncapped input keeps its wires"
+    );
+    graph.nodes[2].inputs[0].max_connections = Some(1);
+    graph.nodes[2].inputs[0].connections.clear();
+    assert!(!drop_replaces_connection(
+        &graph,
+        port(value_b, 0, PortKind::Output),
+        first_input
+    ));
+}
+
+#[test]
+fn pulled_off_wire_is_rerouted_restored_or_removed() {
+    let mut graph = model::Graph::test_graph();
+    graph.nodes[2].inputs[0].connections[0].label = Some("left".to_string());
+    let mut harness = GraphUiHarness::new(graph);
+    let sum = harness.graph.nodes[2].id;
+    let before = harness.graph.undo_snapshot();
+
+    // Released back on the input it came from: the wire is untouched and nothing is
+    // left to undo.
+    let (_, input) = harness.wire_ends(sum, 0);
+    harness.press(input);
+    harness.move_to(input - egui::vec2(60.0, 30.0));
+    assert!(harness.graph.nodes[2].inputs[0].connections.is_empty());
+    harness.move_to(input);
+    harness.release(input);
+    let restored = &harness.graph.nodes[2].inputs[0].connections;
+    assert_eq!(restored.len(), 1);
+    assert_eq!(restored[0].label.as_deref(), Some("left"));
+    assert_eq!(harness.graph.undo_snapshot(), before);
+    harness.move_to(egui::pos2(640.0, 700.0));
+    harness.key(egui::Key::Z, egui::Modifiers::COMMAND);
+    assert_eq!(
+        harness.graph_ui.take_notice().as_deref(),
+        Some("Nothing to undo")
+    );
+
+    // Released on empty canvas: the wire is gone.
+    let (_, input) = harness.wire_ends(sum, 0);
+    harness.press(input);
+    harness.move_to(input - egui::vec2(60.0, 30.0));
+    harness.move_to(egui::pos2(640.0, 700.0));
+    harness.release(egui::pos2(640.0, 700.0));
+    assert!(harness.graph.nodes[2].inputs[0].connections.is_empty());
+    assert!(harness.graph.validate().is_ok());
+}
+
+#[test]
+fn apply_connection_checks_port_types() {
+    let mut graph = model::Graph::test_graph();
+    let (value_a, value_b, divide) = (graph.nodes[0].id, graph.nodes[1].id, graph.nodes[3].id);
+    graph.nodes[0].outputs[0].data_type = "number".to_string();
+    graph.nodes[1].outputs[0].data_type = "text".to_string();
+    graph.nodes[3].inputs[1].data_type = "number".to_string();
+    let output = |node_id| PortRef {
+        node_id,
+        index: 0,
+        kind: PortKind::Output,
+    };
+    let divisor = PortRef {
+        node_id: divide,
+        index: 1,
+        kind: PortKind::Input,
+    };
+    let sources = |graph: &model::Graph| -> Vec<Uuid> {
+        graph.nodes[3].inputs[1]
+            .connections
+            .iter()
+            .map(|connection| connection.node_id)
+            .collect()
+    };
+    assert_eq!(
+        sources(&graph),
+        vec![value_b],
+        "the sample wire predates the types"
+    );
+
+    let err = apply_connection(&mut graph, output(value_b), divisor)
+        .expect_err("text does not fit number");
+    assert!(err.to_string().contains("does not fit"), "{err}");
+    assert_eq!(
+        sources(&graph),
+        vec![value_b],
+        "a refused wire changes nothing"
+    );
+
+    let displaced = apply_connection(&mut graph, output(value_a), divisor)
+        .expect("number fits number")
+        .expect("the capped input drops its old wire");
+    assert_eq!(displaced.node_id, value_b);
+    assert_eq!(sources(&graph), vec![value_a]);
+    assert!(graph.validate().is_ok());
+}
+
+#[test]
+fn view_keys_pan_zoom_reset_and_fit() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    harness.move_to(egui::pos2(640.0, 700.0));
+    let (pan, zoom) = (harness.graph.pan, harness.graph.zoom);
+
+    harness.key(egui::Key::Equals, egui::Modifiers::NONE);
+    assert!((harness.graph.zoom - zoom * KEYBOARD_ZOOM_STEP).abs() < 1e-4);
+    harness.key(egui::Key::Minus, egui::Modifiers::NONE);
+    // Both steps zoom about the same view center, so they cancel out.
+    assert!((harness.graph.zoom - zoom).abs() < 1e-4);
+    assert!((harness.graph.pan - pan).length() < 1e-3);
+
+    for _ in 0..20 {
+        harness.key(egui::Key::Plus, egui::Modifiers::NONE);
+    }
+    assert_eq!(harness.graph.zoom, MAX_ZOOM);
+    for _ in 0..30 {
+        harness.key(egui::Key::Minus, egui::Modifiers::NONE);
+    }
+    assert_eq!(harness.graph.zoom, MIN_ZOOM);
+
+    harness.key(egui::Key::Num0, egui::Modifiers::NONE);
+    harness.settle_view();
+    assert_eq!(
+        (harness.graph.pan, harness.graph.zoom),
+        (egui::Vec2::ZERO, 1.0)
+    );
+
+    // Nothing has focus, so the arrows pan with the pointer over the graph.
+    harness.key(egui::Key::ArrowLeft, egui::Modifiers::NONE);
+    assert_eq!(harness.graph.pan, egui::vec2(40.0, 0.0));
+
+    harness.key(egui::Key::Home, egui::Modifiers::NONE);
+    harness.settle_view();
+    let fitted = (harness.graph.pan, harness.graph.zoom);
+    assert_ne!(fitted, (egui::vec2(40.0, 0.0), 1.0), "Home fits every node");
+    harness.key(egui::Key::Home, egui::Modifiers::NONE);
+    harness.settle_view();
+    assert_eq!((harness.graph.pan, harness.graph.zoom), fitted);
+}
+
+#[test]
+fn view_animation_eases_out_to_its_target_and_yields_to_manual_pan() {
+    let graph = model::Graph::default();
+    let animation = ViewAnimation::new(&graph, (egui::vec2(100.0, -40.0), 2.0), 1.0);
+    assert_eq!(animation.view_at(1.0), ((egui::Vec2::ZERO, 1.0), false));
+    let ((half_pan, half_zoom), done) = animation.view_at(1.0 + VIEW_ANIMATION_SECONDS / 2.0);
+    assert!(!done);
+    // Ease-out covers most of the way in the first half.
+    assert!((half_pan.x - 87.5).abs() < 1e-3 && (half_zoom - 1.875).abs() < 1e-4);
+    assert_eq!(
+        animation.view_at(1.0 + VIEW_ANIMATION_SECONDS),
+        ((egui::vec2(100.0, -40.0), 2.0), true)
+    );
+
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    harness.move_to(egui::pos2(640.0, 700.0));
+    harness.graph.pan = egui::vec2(300.0, 200.0);
+    harness.graph.zoom = 2.0;
+    harness.key(egui::Key::Num0, egui::Modifiers::NONE);
+    assert_eq!(
+        harness.graph.zoom, 2.0,
+        "the view glides instead of jumping"
+    );
+    harness.frame(Vec::new());
+    let zoom = harness.graph.zoom;
+    assert!(zoom < 2.0 && zoom > 1.0);
+    harness.settle_view();
+    assert_eq!(
+        (harness.graph.pan, harness.graph.zoom),
+        (egui::Vec2::ZERO, 1.0)
+    );
+
+    harness.key(egui::Key::Home, egui::Modifiers::NONE);
+    harness.frame(Vec::new());
+    // A manual pan mid-flight stops the animation where it is.
+    harness.key(egui::Key::ArrowLeft, egui::Modifiers::NONE);
+    let interrupted = (harness.graph.pan, harness.graph.zoom);
+    harness.frame(Vec::new());
+    assert!(harness.graph_ui.view_animation.is_none());
+    harness.frame(Vec::new());
+    assert_eq!((harness.graph.pan, harness.graph.zoom), interrupted);
+}
+
+#[test]
+fn undo_and_redo_keys_step_through_header_drags() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    let node_id = harness.graph.nodes[2].id;
+    let start = harness.graph.nodes[2].pos;
+    let header = harness.header_point(node_id);
+    harness.press(header);
+    harness.move_to(header + egui::vec2(20.0, 0.0));
+    harness.move_to(header + egui::vec2(60.0, 40.0));
+    harness.release(header + egui::vec2(60.0, 40.0));
+    let moved = harness.graph.nodes[2].pos;
+    assert_ne!(moved, start);
+
+    harness.key(egui::Key::Z, egui::Modifiers::COMMAND);
+    assert_eq!(harness.graph.nodes[2].pos, start);
+    assert_eq!(
+        harness.graph_ui.take_notice().as_deref(),
+        Some("Undid the last edit")
+    );
+    harness.key(egui::Key::Z, egui::Modifiers::COMMAND);
+    assert_eq!(
+        harness.graph_ui.take_notice().as_deref(),
+        Some("Nothing to undo")
+    );
+
+    harness.key(
+        egui::Key::Z,
+        egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+    );
+    assert_eq!(harness.graph.nodes[2].pos, moved);
+    assert_eq!(
+        harness.graph_ui.take_notice().as_deref(),
+        Some("Redid the last undone edit")
+    );
+}
+
+#[test]
+fn a_wire_dropped_on_nothing_keeps_the_redo_list() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    harness.graph.push_undo_checkpoint();
+    harness.graph.nodes[0].pos.x += 50.0;
+    assert!(harness.graph.undo());
+    harness.frame(Vec::new());
+
+    let sum = harness.graph.nodes[2].id;
+    let (output, _) = harness.wire_ends(sum, 0);
+    let empty = egui::pos2(640.0, 700.0);
+    harness.press(output);
+    harness.move_to(output + egui::vec2(30.0, 0.0));
+    harness.move_to(empty);
+    assert!(harness.connection_drag_active());
+    harness.release(empty);
+
+    assert!(!harness.connection_drag_active());
+    assert!(
+        harness.graph.history.can_redo(),
+        "a drop that changed nothing is not an edit"
+    );
+}
+
+#[test]
+fn a_wire_dropped_on_a_node_body_takes_its_first_free_input() {
+    let mut graph = model::Graph::test_graph();
+    graph.nodes[2].inputs[1].connections.clear();
+    let value_a = graph.nodes[0].id;
+    let sum = graph.nodes[2].id;
+    let mut harness = GraphUiHarness::new(graph);
+    let drop_on_sum = |harness: &mut GraphUiHarness| {
+        let (output, _) = harness.wire_ends(sum, 0);
+        let body = harness.header_point(sum);
+        harness.press(output);
+        harness.move_to(output + egui::vec2(30.0, 0.0));
+        harness.move_to(body);
+        assert!(harness.connection_drag_active());
+        harness.release(body);
+    };
+
+    drop_on_sum(&mut harness);
+    let inputs = &harness.graph.nodes[2].inputs;
+    assert_eq!(
+        inputs[1].connections.len(),
+        1,
+        "the free input takes the wire"
+    );
+    assert_eq!(inputs[1].connections[0].node_id, value_a);
+    assert!(harness.graph_ui.rejected_drop.is_none());
+
+    // With every input full the drop connects nothing and outlines the node instead.
+    let snapshot = harness.graph.undo_snapshot();
+    drop_on_sum(&mut harness);
+    assert!(harness.graph.undo_snapshot() == snapshot);
+    assert_eq!(
+        harness
+            .graph_ui
+            .rejected_drop
+            .as_ref()
+            .map(|rejected| rejected.node_id),
+        Some(sum)
+    );
+}
+
+#[test]
+fn wire_labels_are_edited_from_the_wire_menu_and_hidden_at_low_zoom() {
+    let unlabelled: model::Connection = serde_json::from_value(serde_json::json!({
+        "node_id": Uuid::nil(),
+        "output_index": 0,
+    }))
+    .expect("older files have no label");
+    assert!(unlabelled.label.is_none());
+
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    harness.settle_view();
+    assert!(harness.painted_texts().contains(&"ratio".to_string()));
+
+    let sum = harness.graph.nodes[2].id;
+    let midpoint = harness
+        .graph_ui
+        .connections
+        .curves
+        .iter()
+        .find(|curve| curve.key.target_node_id == sum && curve.key.input_index == 0)
+        .map(|curve| curve.midpoint())
+        .expect("value_a feeds sum");
+    let secondary = |pressed| egui::Event::PointerButton {
+        pos: midpoint,
+        button: egui::PointerButton::Secondary,
+        pressed,
+        modifiers: egui::Modifiers::NONE,
+    };
+    harness.move_to(midpoint);
+    harness.frame(vec![secondary(true)]);
+    harness.frame(vec![secondary(false)]);
+    assert!(
+        harness.graph_ui.wire_menu.is_some(),
+        "right-click opens the wire menu"
+    );
+    harness.frame(Vec::new());
+    harness.frame(vec![egui::Event::Text("meters".to_string())]);
+    harness.frame(Vec::new());
+    assert_eq!(
+        harness.graph.nodes[2].inputs[0].connections[0]
+            .label
+            .as_deref(),
+        Some("meters")
+    );
+    harness.key(egui::Key::Escape, egui::Modifiers::NONE);
+    assert!(harness.graph_ui.wire_menu.is_none());
+    harness.frame(Vec::new());
+    assert!(
+        harness.painted_texts().contains(&"meters".to_string()),
+        "the label is drawn on the wire once the menu closes"
+    );
+
+    harness.graph.zoom = 0.4;
+    harness.frame(Vec::new());
+    let texts = harness.painted_texts();
+    assert!(!texts.contains(&"ratio".to_string()) && !texts.contains(&"meters".to_string()));
+}
+
+#[test]
+fn wires_touching_the_selection_use_the_selection_stroke_and_draw_last() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    let selected = egui::epaint::ColorMode::Solid(
+        crate::gui::style::GraphStyle::new(&egui::Visuals::dark(), 1.0)
+            .selected_connection_stroke
+            .color,
+    );
+    // Stroke colors of the painted wires, in draw order.
+    let wire_colors = |harness: &GraphUiHarness| {
+        harness
+            .shapes
+            .iter()
+            .filter_map(|clipped| match &clipped.shape {
+                egui::Shape::CubicBezier(bezier) => Some(bezier.stroke.color.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    assert!(!wire_colors(&harness).contains(&selected));
+
+    // math(sum) has two wires in and one out; value_b -> divide and divide -> output
+    // do not touch it.
+    let sum = harness.graph.nodes[2].id;
+    harness.graph.selected_nodes.insert(sum);
+    harness.frame(Vec::new());
+    let colors = wire_colors(&harness);
+    assert_eq!(colors.len(), 5);
+    assert!(colors[..2].iter().all(|color| *color != selected));
+    assert!(colors[2..].iter().all(|color| *color == selected));
+}
+
+#[test]
+fn wheel_zooms_while_shift_wheel_and_trackpad_deltas_pan() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    let canvas = egui::pos2(640.0, 700.0);
+    harness.move_to(canvas);
+    let wheel = |unit, delta, modifiers| egui::Event::MouseWheel {
+        unit,
+        delta,
+        modifiers,
+    };
+
+    let zoom = harness.graph.zoom;
+    harness.frame(vec![wheel(
+        egui::MouseWheelUnit::Line,
+        egui::vec2(0.0, 1.0),
+        egui::Modifiers::NONE,
+    )]);
+    assert!(harness.graph.zoom > zoom, "a plain wheel notch zooms in");
+
+    let (pan, zoom) = (harness.graph.pan, harness.graph.zoom);
+    harness.modifiers = egui::Modifiers::SHIFT;
+    harness.frame(vec![wheel(
+        egui::MouseWheelUnit::Line,
+        egui::vec2(0.0, 1.0),
+        egui::Modifiers::SHIFT,
+    )]);
+    harness.modifiers = egui::Modifiers::NONE;
+    assert_eq!(harness.graph.zoom, zoom, "Shift + wheel does not zoom");
+    assert_ne!(harness.graph.pan.x, pan.x, "Shift + wheel pans sideways");
+    assert_eq!(harness.graph.pan.y, pan.y);
+
+    let pan = harness.graph.pan;
+    harness.frame(vec![wheel(
+        egui::MouseWheelUnit::Point,
+        egui::vec2(-30.0, 0.0),
+        egui::Modifiers::NONE,
+    )]);
+    assert_eq!(harness.graph.zoom, zoom, "trackpad scrolling does not zoom");
+    assert!(
+        harness.graph.pan.x < pan.x,
+        "a horizontal trackpad delta pans sideways"
+    );
+    assert_eq!(harness.graph.pan.y, pan.y);
+}
+
+#[test]
+fn curve_tension_scales_every_wire_bend() {
+    let control_offsets = |tension: f32| -> Vec<f32> {
+        let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+        harness
+            .graph_ui
+            .set_style_overrides(Some(std::sync::Arc::new(move |builder| {
+                builder.curve_tension(tension)
+            })));
+        harness.frame(Vec::new());
+        harness
+            .graph_ui
+            .connections
+            .curves
+            .iter()
+            .map(|curve| match curve.shape {
+                WireShape::Bezier([start, control, ..]) => control.x - start.x,
+                WireShape::Orthogonal(_) => panic!("wires default to bezier"),
+            })
+            .collect()
+    };
+
+    let default = control_offsets(1.0);
+    let loopy = control_offsets(2.0);
+    assert!(!default.is_empty());
+    for (default, loopy) in default.iter().zip(&loopy) {
+        assert!(*default > 0.0);
+        assert!((loopy - 2.0 * default).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn canvas_and_nodes_are_keyboard_focusable_and_labelled() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    harness.ctx.enable_accesskit();
+    let canvas = egui::pos2(640.0, 700.0);
+    harness.press(canvas);
+    harness.release(canvas);
+    assert!(
+        harness
+            .accessible_labels()
+            .contains(&"Node graph, 5 nodes, no selection".to_string())
+    );
+    assert!(
+        harness
+            .accessible_labels()
+            .contains(&"Node value_a, 0 inputs, 1 outputs".to_string())
+    );
+
+    // The clicked canvas holds focus, so the arrows pan it.
+    let pan = harness.graph.pan;
+    harness.key(egui::Key::ArrowLeft, egui::Modifiers::NONE);
+    assert_eq!(harness.graph.pan, pan + egui::vec2(40.0, 0.0));
+
+    // Tab moves focus on to a node body; Enter selects the focused node.
+    let focused_node = |harness: &GraphUiHarness| {
+        harness
+            .focused_label()
+            .filter(|label| label.starts_with("Node ") && !label.starts_with("Node graph"))
+    };
+    for _ in 0..8 {
+        if focused_node(&harness).is_some() {
+            break;
+        }
+        harness.key(egui::Key::Tab, egui::Modifiers::NONE);
+        harness.frame(Vec::new());
+    }
+    assert!(focused_node(&harness).is_some(), "Tab reaches a node body");
+    assert!(harness.graph.selected_nodes.is_empty());
+    harness.key(egui::Key::Enter, egui::Modifiers::NONE);
+    harness.frame(Vec::new());
+    assert_eq!(
+        harness.graph.selected_nodes.len(),
+        1,
+        "Enter selects the node"
+    );
+    let selected = harness
+        .graph
+        .nodes
+        .iter()
+        .find(|node| harness.graph.selected_nodes.contains(&node.id))
+        .map(|node| node.name.clone())
+        .expect("one node is selected");
+    assert!(
+        harness
+            .accessible_labels()
+            .contains(&format!("Node graph, 5 nodes, selected {selected}"))
+    );
+}
+
+#[test]
+fn render_timings_are_collected_only_when_enabled() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    assert!(harness.graph_ui.render_timings().is_none());
+
+    harness.graph_ui.debug_render_timings = true;
+    harness.frame(Vec::new());
+    let timings = harness
+        .graph_ui
+        .render_timings()
+        .expect("timings are on")
+        .clone();
+    assert_eq!(
+        timings.total(),
+        RenderStage::ALL
+            .iter()
+            .map(|stage| timings.get(*stage))
+            .sum::<Duration>()
+    );
+    assert!(timings.get(RenderStage::NodeBodies) > Duration::ZERO);
+
+    harness.graph_ui.debug_render_timings = false;
+    harness.frame(Vec::new());
+    assert!(harness.graph_ui.render_timings().is_none());
+}
+
+#[test]
+fn read_only_blocks_edits_but_keeps_navigation() {
+    let mut harness = GraphUiHarness::new(model::Graph::test_graph());
+    harness.graph_ui.read_only = true;
+    let sum = harness.graph.nodes[2].id;
+    let (wire_start, port) = harness.wire_ends(sum, 0);
+    let wires = harness.graph.connections().count();
+
+    // Pressing an occupied input neither detaches nor drags; the click selects.
+    harness.press(port);
+    assert!(!harness.connection_drag_active());
+    harness.release(port);
+    assert_eq!(harness.graph.connections().count(), wires);
+    assert_eq!(harness.graph.selected_node(), Some(sum));
+
+    // A stroke across a wire pans instead of cutting.
+    let middle = wire_start + (port - wire_start) * 0.5;
+    let pan = harness.graph.pan;
+    harness.press(middle - egui::vec2(0.0, 30.0));
+    assert!(!harness.breaker_active());
+    harness.move_to(middle);
+    harness.move_to(middle + egui::vec2(0.0, 30.0));
+    harness.release(middle + egui::vec2(0.0, 30.0));
+    assert_eq!(harness.graph.connections().count(), wires);
+    assert_ne!(harness.graph.pan, pan);
+}
+
+#[test]
+fn pasting_an_image_adds_an_undoable_image_comment() {
+    let mut graph = model::Graph::default();
+    let pos = egui::pos2(10.0, 20.0);
+    assert_eq!(
+        paste_image_comment(&mut graph, pos, Ok(None)).as_deref(),
+        Some("The clipboard holds no image")
+    );
+    assert!(
+        paste_image_comment(&mut graph, pos, Err(anyhow::anyhow!("no display")))
+            .is_some_and(|notice| notice.contains("no display"))
+    );
+    assert!(graph.comments.is_empty());
+
+    let image = model::CommentImage::from_rgba(1, 1, &[1, 2, 3, 255]).expect("one pixel");
+    assert_eq!(paste_image_comment(&mut graph, pos, Ok(Some(image))), None);
+    assert_eq!(graph.comments.len(), 1);
+    assert_eq!(graph.comments[0].pos, pos);
+    assert!(graph.comments[0].image.is_some());
+    assert!(graph.undo());
+    assert!(graph.comments.is_empty());
+}
@@ -0,0 +1,318 @@
+use eframe::egui;
+use uuid::Uuid;
+
+use crate::{
+    gui::{render::RenderContext, style::GraphStyle},
+    model,
+};
+
+#[cfg(test)]
+use crate::gui::node;
+
+/// Screen-space union of the members' node rects, skipping `excluded`; `None` when no
+/// other member exists.
+fn member_bounds(
+    ctx: &RenderContext,
+    graph: &model::Graph,
+    group: &model::Group,
+    excluded: Option<Uuid>,
+) -> Option<egui::Rect> {
+    graph
+        .nodes
+        .iter()
+        .filter(|node| group.node_ids.contains(&node.id) && Some(node.id) != excluded)
+        .map(|node| ctx.node_rect(node))
+        .reduce(|bounds, rect| bounds.union(rect))
+}
+
+/// Member bounds grown by `group_padding`, with the title bar stacked on top. Works in
+/// either space as long as `style` is scaled to match `bounds`.
+pub(crate) fn frame_around(style: &GraphStyle, bounds: egui::Rect) -> egui::Rect {
+    let padded = bounds.expand(style.group_padding);
+    egui::Rect::from_min_max(
+        egui::pos2(padded.min.x, padded.min.y - style.group_title_height),
+        padded.max,
+    )
+}
+
+fn title_rect(ctx: &RenderContext, frame: egui::Rect) -> egui::Rect {
+    egui::Rect::from_min_size(
+        frame.min,
+        egui::vec2(frame.width(), ctx.style.group_title_height),
+    )
+}
+
+/// The group's screen-space frame, recomputed from its members every frame so it
+/// follows them; `None` for a group without members.
+pub(crate) fn group_rect(
+    ctx: &RenderContext,
+    graph: &model::Graph,
+    group: &model::Group,
+) -> Option<egui::Rect> {
+    member_bounds(ctx, graph, group, None).map(|bounds| frame_around(ctx.style, bounds))
+}
+
+/// The top-most group whose title bar contains `pos`; later groups draw on top.
+pub(crate) fn group_title_at(
+    ctx: &RenderContext,
+    graph: &model::Graph,
+    pos: egui::Pos2,
+) -> Option<Uuid> {
+    graph
+        .groups
+        .iter()
+        .rev()
+        .find(|group| {
+            group_rect(ctx, graph, group).is_some_and(|frame| title_rect(ctx, frame).contains(pos))
+        })
+        .map(|group| group.id)
+}
+
+/// Draws every group frame under the nodes. Only the title bar senses the pointer:
+/// dragging it moves all members (unless `read_only`), while presses on the body fall
+/// through to the members and the canvas.
+pub fn render_groups(ctx: &RenderContext, graph: &mut model::Graph, read_only: bool) {
+    for group_index in 0..graph.groups.len() {
+        let group = &graph.groups[group_index];
+        let Some(frame) = group_rect(ctx, graph, group) else {
+            continue;
+        };
+        let title = title_rect(ctx, frame);
+        let painter = ctx.painter();
+        painter.rect_filled(frame, ctx.layout.corner_radius, ctx.style.group_fill);
+        painter.rect_filled(title, ctx.layout.corner_radius, ctx.style.group_fill);
+        painter.rect_stroke(
+            frame,
+            ctx.layout.corner_radius,
+            ctx.style.group_stroke,
+            egui::StrokeKind::Inside,
+        );
+        let galley = ctx
+            .text_cache
+            .galley(painter, &group.name, &ctx.heading_font);
+        let text_pos = egui::Align2::LEFT_CENTER
+            .anchor_size(
+                egui::pos2(title.min.x + ctx.layout.padding, title.center().y),
+                galley.size(),
+            )
+            .min;
+        painter
+            .with_clip_rect(title.intersect(painter.clip_rect()))
+            .galley(text_pos, galley, ctx.text_color);
+
+        if read_only {
+            continue;
+        }
+        let title_id = ctx.ui().make_persistent_id(("group_title", group.id));
+        let response = ctx.ui().interact(title, title_id, egui::Sense::drag());
+        if response.hovered() || response.dragged() {
+            ctx.ui().ctx().set_cursor_icon(egui::CursorIcon::Grab);
+        }
+        if response.dragged() {
+            let delta = response.drag_delta();
+            let members = group.node_ids.clone();
+            for node in graph
+                .nodes
+                .iter_mut()
+                .filter(|node| members.contains(&node.id))
+            {
+                // Pinned positions are screen offsets, as in the node header drag.
+                node.pos += if node.screen_pinned {
+                    delta
+                } else {
+                    delta / ctx.scale
+                };
+            }
+        }
+    }
+}
+
+/// Called when a member drag ends: drops `node_id` from every group whose frame, built
+/// from the remaining members, its rect no longer touches. A sole member stays put.
+/// Returns whether any membership changed.
+pub(crate) fn release_dragged_member(
+    ctx: &RenderContext,
+    graph: &mut model::Graph,
+    node_id: Uuid,
+) -> bool {
+    let Some(node_rect) = graph
+        .nodes
+        .iter()
+        .find(|node| node.id == node_id)
+        .map(|node| ctx.node_rect(node))
+    else {
+        return false;
+    };
+    let left: Vec<Uuid> = graph
+        .groups
+        .iter()
+        .filter(|group| group.node_ids.contains(&node_id))
+        .filter(|group| {
+            member_bounds(ctx, graph, group, Some(node_id))
+                .is_some_and(|bounds| !frame_around(ctx.style, bounds).intersects(node_rect))
+        })
+        .map(|group| group.id)
+        .collect();
+    for group in graph
+        .groups
+        .iter_mut()
+        .filter(|group| left.contains(&group.id))
+    {
+        group.node_ids.retain(|id| *id != node_id);
+    }
+    !left.is_empty()
+}
+
+#[cfg(test)]
+fn with_render_ctx(
+    ctx: &egui::Context,
+    input: egui::RawInput,
+    graph: &mut model::Graph,
+    mut f: impl FnMut(&RenderContext, &mut model::Graph),
+) {
+    let _ = ctx.run(input, |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let painter = ui.painter().clone();
+            let style = GraphStyle::new(ui.visuals(), graph.zoom);
+            let render_ctx = RenderContext::new(
+                ui,
+                &painter,
+                ui.max_rect(),
+                graph,
+                &node::NodeLayout::default(),
+                &style,
+            );
+            f(&render_ctx, graph);
+        });
+    });
+}
+
+#[test]
+fn group_frame_pads_members_and_adds_title_bar() {
+    let mut graph = model::Graph::test_graph();
+    graph.zoom = 1.5;
+    let ctx = egui::Context::default();
+    with_render_ctx(&ctx, egui::RawInput::default(), &mut graph, |ctx, graph| {
+        let group = &graph.groups[0];
+        let frame = group_rect(ctx, graph, group).expect("group has members");
+        let bounds = member_bounds(ctx, graph, group, None).expect("group has members");
+        let padding = ctx.style.group_padding;
+        assert_eq!(padding, 16.0 * 1.5, "padding scales with zoom");
+        assert_eq!(frame.max, bounds.max + egui::vec2(padding, padding));
+        assert_eq!(frame.min.x, bounds.min.x - padding);
+        assert_eq!(
+            frame.min.y,
+            bounds.min.y - padding - ctx.style.group_title_height
+        );
+        let title_pos = frame.min + egui::vec2(4.0, 4.0);
+        assert_eq!(group_title_at(ctx, graph, title_pos), Some(group.id));
+        assert_eq!(group_title_at(ctx, graph, bounds.center()), None);
+
+        let empty = model::Group {
+            id: Uuid::new_v4(),
+            name: "empty".to_string(),
+            node_ids: Vec::new(),
+        };
+        assert_eq!(group_rect(ctx, graph, &empty), None);
+    });
+}
+
+#[test]
+fn title_bar_drag_moves_every_member() {
+    let mut graph = model::Graph::test_graph();
+    graph.zoom = 2.0;
+    let members = graph.groups[0].node_ids.clone();
+    let before: Vec<egui::Pos2> = graph.nodes.iter().map(|node| node.pos).collect();
+    let ctx = egui::Context::default();
+    let mut title_pos = egui::Pos2::ZERO;
+    with_render_ctx(&ctx, egui::RawInput::default(), &mut graph, |ctx, graph| {
+        let frame = group_rect(ctx, graph, &graph.groups[0]).expect("group has members");
+        title_pos = title_rect(ctx, frame).center();
+    });
+
+    let delta = egui::vec2(30.0, -12.0);
+    let pointer = |pos: egui::Pos2, pressed: Option<bool>| {
+        let mut events = vec![egui::Event::PointerMoved(pos)];
+        if let Some(pressed) = pressed {
+            events.push(egui::Event::PointerButton {
+                pos,
+                button: egui::PointerButton::Primary,
+                pressed,
+                modifiers: egui::Modifiers::NONE,
+            });
+        }
+        egui::RawInput {
+            events,
+            ..Default::default()
+        }
+    };
+    for input in [
+        pointer(title_pos, None),
+        pointer(title_pos, Some(true)),
+        pointer(title_pos + delta * 0.5, None),
+        pointer(title_pos + delta, None),
+        pointer(title_pos + delta, Some(false)),
+    ] {
+        with_render_ctx(&ctx, input, &mut graph, |ctx, graph| {
+            render_groups(ctx, graph, false)
+        });
+    }
+
+    for (node, before) in graph.nodes.iter().zip(before) {
+        let expected = if members.contains(&node.id) {
+            before + delta / 2.0
+        } else {
+            before
+        };
+        assert!(
+            (node.pos - expected).length() < 1e-3,
+            "{}: {:?} != {expected:?}",
+            node.name,
+            node.pos
+        );
+    }
+}
+
+#[test]
+fn dragging_a_member_clear_of_the_frame_leaves_the_group() {
+    let mut graph = model::Graph::test_graph();
+    let [sum_id, divide_id] = graph.groups[0].node_ids[..] else {
+        panic!("test group has two members");
+    };
+    let ctx = egui::Context::default();
+    with_render_ctx(&ctx, egui::RawInput::default(), &mut graph, |ctx, graph| {
+        let divide_pos = graph
+            .nodes
+            .iter()
+            .find(|node| node.id == divide_id)
+            .expect("divide node exists")
+            .pos;
+        let sum = graph
+            .nodes
+            .iter_mut()
+            .find(|node| node.id == sum_id)
+            .expect("sum node exists");
+        // Still overlapping the frame around the other member: membership is kept.
+        sum.pos = divide_pos + egui::vec2(30.0, 30.0);
+        assert!(!release_dragged_member(ctx, graph, sum_id));
+
+        let sum = graph
+            .nodes
+            .iter_mut()
+            .find(|node| node.id == sum_id)
+            .expect("sum node exists");
+        sum.pos += egui::vec2(0.0, 2000.0);
+        assert!(release_dragged_member(ctx, graph, sum_id));
+        assert_eq!(graph.groups[0].node_ids, vec![divide_id]);
+
+        // The last member keeps its group wherever it goes.
+        let divide = graph
+            .nodes
+            .iter_mut()
+            .find(|node| node.id == divide_id)
+            .expect("divide node exists");
+        divide.pos += egui::vec2(-3000.0, 0.0);
+        assert!(!release_dragged_member(ctx, graph, divide_id));
+        assert_eq!(graph.groups[0].node_ids, vec![divide_id]);
+    });
+}
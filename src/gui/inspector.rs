@@ -0,0 +1,43 @@
+use eframe::egui;
+
+use crate::model;
+
+/// Edits metadata of the selected node; with `read_only` it only shows it.
+pub fn render(ui: &mut egui::Ui, graph: &mut model::Graph, read_only: bool) {
+    ui.heading("Inspector");
+
+    let Some(selected_id) = graph.selected_node() else {
+        ui.label(match graph.selected_nodes.len() {
+            0 => "No node selected".to_string(),
+            count => format!("{count} nodes selected"),
+        });
+        return;
+    };
+    let node = graph
+        .nodes
+        .iter_mut()
+        .find(|node| node.id == selected_id)
+        .expect("selected node must exist in graph");
+
+    ui.label(&node.name);
+    ui.label("Description");
+    ui.add(
+        egui::TextEdit::multiline(&mut node.description)
+            .desired_rows(4)
+            .desired_width(f32::INFINITY)
+            .hint_text("What this node does")
+            .interactive(!read_only),
+    );
+
+    let mut pinned = node.screen_pinned;
+    if ui
+        .add_enabled(
+            !read_only,
+            egui::Checkbox::new(&mut pinned, "Pin to screen"),
+        )
+        .on_hover_text("Keep the node fixed in the view while panning and zooming")
+        .changed()
+    {
+        graph.set_screen_pinned(selected_id, pinned);
+    }
+}
@@ -1,4 +1,11 @@
+pub mod comment;
+pub mod diagnostics;
 pub mod graph;
+pub mod group;
+pub mod inspector;
 pub mod node;
+pub mod playback;
+pub mod preferences;
 pub mod render;
+pub mod search;
 pub mod style;
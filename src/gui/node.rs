@@ -1,13 +1,43 @@
 use eframe::egui;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
-use crate::{gui::render::RenderContext, model};
+use crate::{
+    gui::render::{self, RenderContext, TextLayoutCache},
+    model,
+};
 
 #[derive(Debug, Default)]
 pub struct NodeInteraction {
     pub selection_request: Option<Uuid>,
+    /// Shift was held: `selection_request` joins the selection instead of replacing it.
+    pub extend_selection: bool,
     pub remove_request: Option<Uuid>,
+    pub double_click_request: Option<Uuid>,
+    /// Graph-space node being dragged by its header this frame (pinned nodes excluded).
+    pub dragged_node: Option<Uuid>,
+    /// A selected node dragged by its header this frame and the screen-space delta, so
+    /// the rest of the selection can follow it.
+    pub selection_drag: Option<(Uuid, egui::Vec2)>,
+}
+
+/// Fill and title alpha factor for bypassed nodes.
+const BYPASS_DIM: f32 = 0.45;
+
+/// Where the shorter port column sits against the node's rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum PortAlign {
+    #[default]
+    Top,
+    Center,
+    Bottom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PortSide {
+    Input,
+    Output,
 }
 
 #[derive(Debug)]
@@ -18,6 +48,19 @@ pub struct NodeLayout {
     pub row_height: f32,
     pub padding: f32,
     pub corner_radius: f32,
+    /// When false, nodes show at most `max_visible_rows` port rows and scroll the rest.
+    pub auto_height: bool,
+    pub max_visible_rows: usize,
+    /// Port rows reserved even when a node has fewer ports. With 0, a node without ports
+    /// collapses to a header-only card.
+    pub min_rows: usize,
+    /// Title font size in points at zoom 1; `None` uses the egui `Heading` text style.
+    /// Independent of `header_height`: the title is vertically centered in the header and
+    /// clipped to it when taller.
+    pub header_font_size: Option<f32>,
+    /// Vertical placement of the shorter port column, e.g. `Bottom` puts a single output
+    /// opposite the last input.
+    pub port_align: PortAlign,
 }
 
 impl Default for NodeLayout {
@@ -29,6 +72,11 @@ impl Default for NodeLayout {
             row_height: 18.0,
             padding: 8.0,
             corner_radius: 6.0,
+            auto_height: true,
+            max_visible_rows: 8,
+            min_rows: 1,
+            header_font_size: None,
+            port_align: PortAlign::Top,
         }
     }
 }
@@ -50,6 +98,14 @@ impl NodeLayout {
             self.corner_radius >= 0.0,
             "corner radius must be non-negative"
         );
+        assert!(
+            self.max_visible_rows >= 1,
+            "max visible rows must be at least one"
+        );
+        if let Some(size) = self.header_font_size {
+            assert!(size.is_finite(), "header font size must be finite");
+            assert!(size > 0.0, "header font size must be positive");
+        }
     }
 
     pub(crate) fn scaled(&self, scale: f32) -> Self {
@@ -63,68 +119,328 @@ impl NodeLayout {
             row_height: self.row_height * scale,
             padding: self.padding * scale,
             corner_radius: self.corner_radius * scale,
+            auto_height: self.auto_height,
+            max_visible_rows: self.max_visible_rows,
+            min_rows: self.min_rows,
+            header_font_size: self.header_font_size.map(|size| size * scale),
+            port_align: self.port_align,
+        }
+    }
+
+    /// Port rows shown for `node`: every row with auto height, otherwise a window of
+    /// at most `max_visible_rows` starting at the node's scroll offset.
+    pub(crate) fn visible_rows(&self, node: &model::Node) -> std::ops::Range<usize> {
+        let row_count = self.row_count(node);
+        if self.auto_height || row_count <= self.max_visible_rows {
+            return 0..row_count;
+        }
+        let start = node.row_scroll.min(row_count - self.max_visible_rows);
+        start..start + self.max_visible_rows
+    }
+
+    fn row_count(&self, node: &model::Node) -> usize {
+        node.inputs.len().max(node.outputs.len()).max(self.min_rows)
+    }
+
+    /// Whether `node` draws as a header-only card: it has no ports and `min_rows` is 0.
+    pub(crate) fn is_compact(&self, node: &model::Node) -> bool {
+        self.row_count(node) == 0
+    }
+
+    /// Height of the node's cache row; compact cards have none.
+    pub(crate) fn cache_row_height(&self, node: &model::Node) -> f32 {
+        if self.is_compact(node) {
+            0.0
+        } else {
+            self.cache_height
+        }
+    }
+
+    fn rows_top(&self, node: &model::Node) -> f32 {
+        if self.is_compact(node) {
+            self.header_height
+        } else {
+            self.header_height + self.cache_height + self.padding
+        }
+    }
+
+    /// Row a port sits on after `port_align` shifts the shorter column; fractional for
+    /// `Center` when the columns differ by an odd count.
+    pub(crate) fn port_row(&self, node: &model::Node, side: PortSide, index: usize) -> f32 {
+        let column_len = match side {
+            PortSide::Input => node.inputs.len(),
+            PortSide::Output => node.outputs.len(),
+        };
+        let row_count = self.row_count(node);
+        let spare = (row_count - column_len.min(row_count)) as f32;
+        let shift = match self.port_align {
+            PortAlign::Top => 0.0,
+            PortAlign::Center => spare * 0.5,
+            PortAlign::Bottom => spare,
+        };
+        index as f32 + shift
+    }
+
+    /// Whether the port's row lies inside the visible row window.
+    pub(crate) fn port_visible(&self, node: &model::Node, side: PortSide, index: usize) -> bool {
+        let rows = self.visible_rows(node);
+        let row = self.port_row(node, side, index);
+        row >= rows.start as f32 && row < rows.end as f32
+    }
+
+    /// Indices of `side` ports whose rows are visible, in order.
+    pub(crate) fn visible_ports(&self, node: &model::Node, side: PortSide) -> Vec<usize> {
+        let count = match side {
+            PortSide::Input => node.inputs.len(),
+            PortSide::Output => node.outputs.len(),
+        };
+        (0..count)
+            .filter(|index| self.port_visible(node, side, *index))
+            .collect()
+    }
+
+    /// Vertical offset of a port row center from the node top. Rows scrolled out of
+    /// view are pinned to the top or bottom edge of the row area.
+    fn row_center_offset(&self, node: &model::Node, side: PortSide, index: usize) -> f32 {
+        let rows = self.visible_rows(node);
+        let row = self.port_row(node, side, index);
+        let rows_top = self.rows_top(node);
+        if row < rows.start as f32 {
+            rows_top
+        } else if row >= rows.end as f32 {
+            rows_top + self.row_height * rows.len() as f32
+        } else {
+            rows_top + self.row_height * (row - rows.start as f32) + self.row_height * 0.5
         }
     }
 }
 
 pub fn node_rect_for_graph(
-    origin: egui::Pos2,
+    view: render::ViewTransform,
     node: &model::Node,
-    scale: f32,
     layout: &NodeLayout,
     node_width: f32,
 ) -> egui::Rect {
-    assert!(scale > 0.0, "graph scale must be positive");
-    assert!(scale.is_finite(), "graph scale must be finite");
-    layout.assert_valid();
+    debug_assert!(view.scale > 0.0, "graph scale must be positive");
+    debug_assert!(view.scale.is_finite(), "graph scale must be finite");
+    if cfg!(debug_assertions) {
+        layout.assert_valid();
+    }
     let node_size = node_size(node, layout, node_width);
-    egui::Rect::from_min_size(origin + node.pos.to_vec2() * scale, node_size)
+    egui::Rect::from_min_size(view.node_min(node), node_size)
+}
+
+impl model::Graph {
+    /// Graph-space union (zoom 1) of every unpinned node's rect under `layout` and
+    /// `node_widths`; `None` when there is no such node. Pinned nodes live in screen space
+    /// and are skipped. Lives beside `node_rect_for_graph` so `model` stays GUI-free.
+    pub fn bounds(
+        &self,
+        layout: &NodeLayout,
+        node_widths: &HashMap<Uuid, f32>,
+    ) -> Option<egui::Rect> {
+        self.bounds_where(layout, node_widths, |_| true)
+    }
+
+    /// `bounds` of the selected nodes only.
+    pub fn selection_bounds(
+        &self,
+        layout: &NodeLayout,
+        node_widths: &HashMap<Uuid, f32>,
+    ) -> Option<egui::Rect> {
+        self.bounds_where(layout, node_widths, |node| {
+            self.selected_nodes.contains(&node.id)
+        })
+    }
+
+    /// Graph-space rect (zoom 1) of every unpinned node, keyed by id.
+    pub fn node_rects(
+        &self,
+        layout: &NodeLayout,
+        node_widths: &HashMap<Uuid, f32>,
+    ) -> HashMap<Uuid, egui::Rect> {
+        self.nodes
+            .iter()
+            .filter(|node| !node.screen_pinned)
+            .map(|node| {
+                let node_width = node_widths
+                    .get(&node.id)
+                    .copied()
+                    .expect("node width must be precomputed");
+                let rect =
+                    node_rect_for_graph(render::ViewTransform::unit(), node, layout, node_width);
+                (node.id, rect)
+            })
+            .collect()
+    }
+
+    /// `bounds` of `group`'s unpinned members.
+    pub fn group_bounds(
+        &self,
+        group: &model::Group,
+        layout: &NodeLayout,
+        node_widths: &HashMap<Uuid, f32>,
+    ) -> Option<egui::Rect> {
+        self.bounds_where(layout, node_widths, |node| {
+            group.node_ids.contains(&node.id)
+        })
+    }
+
+    fn bounds_where(
+        &self,
+        layout: &NodeLayout,
+        node_widths: &HashMap<Uuid, f32>,
+        include: impl Fn(&model::Node) -> bool,
+    ) -> Option<egui::Rect> {
+        self.nodes
+            .iter()
+            .filter(|node| !node.screen_pinned && include(node))
+            .map(|node| {
+                let node_width = node_widths
+                    .get(&node.id)
+                    .copied()
+                    .expect("node width must be precomputed");
+                node_rect_for_graph(render::ViewTransform::unit(), node, layout, node_width)
+            })
+            .reduce(|bounds, rect| bounds.union(rect))
+    }
 }
 
 pub(crate) fn port_radius_for_scale(scale: f32) -> f32 {
-    assert!(scale.is_finite(), "port scale must be finite");
-    assert!(scale > 0.0, "port scale must be positive");
+    debug_assert!(scale.is_finite(), "port scale must be finite");
+    debug_assert!(scale > 0.0, "port scale must be positive");
     let radius = (5.5 * scale).clamp(3.0, 7.5);
-    assert!(radius.is_finite(), "port radius must be finite");
-    assert!(radius > 0.0, "port radius must be positive");
+    debug_assert!(radius.is_finite(), "port radius must be finite");
+    debug_assert!(radius > 0.0, "port radius must be positive");
     radius
 }
 
-pub fn render_node_bodies(ctx: &RenderContext, graph: &mut model::Graph) -> NodeInteraction {
+/// Close and cache button rects for `node` at `node_rect`; the cache button has zero height
+/// when the node has no cache row (see `NodeLayout::cache_row_height`). Shared by drawing
+/// and `hit_test`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct NodeButtons {
+    pub close: egui::Rect,
+    pub cache: egui::Rect,
+}
+
+pub(crate) fn node_buttons(
+    ctx: &RenderContext,
+    node: &model::Node,
+    node_rect: egui::Rect,
+) -> NodeButtons {
+    let cache_height = ctx.layout.cache_row_height(node);
+    let cache_rect = egui::Rect::from_min_size(
+        node_rect.min + egui::vec2(0.0, ctx.layout.header_height),
+        egui::vec2(node_rect.width(), cache_height),
+    );
+    let button_size = (ctx.layout.header_height - ctx.layout.padding)
+        .max(12.0 * ctx.scale)
+        .min(ctx.layout.header_height);
+    debug_assert!(button_size.is_finite(), "close button size must be finite");
+    debug_assert!(button_size > 0.0, "close button size must be positive");
+    let button_pos = egui::pos2(
+        node_rect.max.x - ctx.layout.padding - button_size,
+        node_rect.min.y + (ctx.layout.header_height - button_size) * 0.5,
+    );
+    let close_rect = egui::Rect::from_min_size(button_pos, egui::vec2(button_size, button_size));
+    let cache_button_height = if cache_height > 0.0 {
+        let vertical_padding = ctx.layout.padding * ctx.style.cache_button_vertical_pad_factor;
+        let size = (cache_height - vertical_padding * 2.0)
+            .max(10.0 * ctx.scale)
+            .min(cache_height);
+        debug_assert!(size.is_finite(), "cache button height must be finite");
+        debug_assert!(size > 0.0, "cache button height must be positive");
+        size
+    } else {
+        0.0
+    };
+    let cache_button_padding = ctx.layout.padding * ctx.style.cache_button_text_pad_factor;
+    debug_assert!(
+        cache_button_padding.is_finite(),
+        "cache button padding must be finite"
+    );
+    debug_assert!(
+        cache_button_padding >= 0.0,
+        "cache button padding must be non-negative"
+    );
+    let cache_text_width = if cache_height > 0.0 {
+        let cached_width = ctx
+            .text_cache
+            .galley(ctx.painter(), "cached", &ctx.body_font)
+            .size()
+            .x;
+        let cache_width = ctx
+            .text_cache
+            .galley(ctx.painter(), "cache", &ctx.body_font)
+            .size()
+            .x;
+        cached_width.max(cache_width)
+    } else {
+        0.0
+    };
+    let cache_button_width = (cache_button_height * ctx.style.cache_button_width_factor)
+        .max(cache_button_height)
+        .max(cache_text_width + cache_button_padding * 2.0);
+    debug_assert!(
+        cache_button_width.is_finite(),
+        "cache button width must be finite"
+    );
+    debug_assert!(
+        cache_button_width > 0.0,
+        "cache button width must be positive"
+    );
+    let cache_button_pos = egui::pos2(
+        cache_rect.min.x + ctx.layout.padding,
+        cache_rect.min.y + (cache_height - cache_button_height) * 0.5,
+    );
+    let cache_button_rect = egui::Rect::from_min_size(
+        cache_button_pos,
+        egui::vec2(cache_button_width, cache_button_height),
+    );
+    NodeButtons {
+        close: close_rect,
+        cache: cache_button_rect,
+    }
+}
+
+/// Draws and interacts with the nodes at `node_order` (indices into `graph.nodes`),
+/// in that order. A header drag moves its node only after the pointer has travelled
+/// `drag_threshold` screen points from the press.
+pub fn render_node_bodies(
+    ctx: &RenderContext,
+    graph: &mut model::Graph,
+    node_order: &[usize],
+    highlighted_nodes: &HashSet<Uuid>,
+    health: Option<&HashMap<Uuid, model::NodeHealth>>,
+    read_only: bool,
+    drag_threshold: f32,
+) -> NodeInteraction {
     let visuals = ctx.ui().visuals();
     let node_fill = ctx.style.node_fill;
     let node_stroke = ctx.style.node_stroke;
     let selected_stroke = ctx.style.selected_stroke;
+    let selected_nodes = &graph.selected_nodes;
     let mut interaction = NodeInteraction::default();
 
-    for node in &mut graph.nodes {
+    for &index in node_order {
+        let node = &mut graph.nodes[index];
         let node_width = ctx.node_width(node.id);
         let node_size = node_size(node, &ctx.layout, node_width);
-        let node_rect =
-            egui::Rect::from_min_size(ctx.origin + node.pos.to_vec2() * ctx.scale, node_size);
+        let node_rect = egui::Rect::from_min_size(ctx.view.node_min(node), node_size);
         let header_rect = egui::Rect::from_min_size(
             node_rect.min,
             egui::vec2(node_size.x, ctx.layout.header_height),
         );
-        let cache_rect = egui::Rect::from_min_size(
-            node_rect.min + egui::vec2(0.0, ctx.layout.header_height),
-            egui::vec2(node_size.x, ctx.layout.cache_height),
-        );
-        let button_size = (ctx.layout.header_height - ctx.layout.padding)
-            .max(12.0 * ctx.scale)
-            .min(ctx.layout.header_height);
-        assert!(button_size.is_finite(), "close button size must be finite");
-        assert!(button_size > 0.0, "close button size must be positive");
-        let button_pos = egui::pos2(
-            node_rect.max.x - ctx.layout.padding - button_size,
-            node_rect.min.y + (ctx.layout.header_height - button_size) * 0.5,
-        );
-        let close_rect =
-            egui::Rect::from_min_size(button_pos, egui::vec2(button_size, button_size));
+        let buttons = node_buttons(ctx, node, node_rect);
+        let has_cache_row = ctx.layout.cache_row_height(node) > 0.0;
+        let close_rect = buttons.close;
+        let cache_button_rect = buttons.cache;
+        let button_size = close_rect.width();
         let mut header_drag_right = close_rect.min.x - ctx.layout.padding;
         let dot_radius = ctx.style.status_dot_radius;
-        assert!(dot_radius.is_finite(), "status dot radius must be finite");
-        assert!(dot_radius >= 0.0, "status dot radius must be non-negative");
+        debug_assert!(dot_radius.is_finite(), "status dot radius must be finite");
+        debug_assert!(dot_radius >= 0.0, "status dot radius must be non-negative");
         let mut dot_centers = Vec::new();
         if node.has_cached_output || node.terminal {
             let dot_diameter = dot_radius * 2.0;
@@ -144,107 +460,225 @@ pub fn render_node_bodies(ctx: &RenderContext, graph: &mut model::Graph) -> Node
             header_rect.min,
             egui::pos2(header_drag_right, header_rect.max.y),
         );
-        let cache_button_height = if ctx.layout.cache_height > 0.0 {
-            let vertical_padding = ctx.layout.padding * ctx.style.cache_button_vertical_pad_factor;
-            let size = (ctx.layout.cache_height - vertical_padding * 2.0)
-                .max(10.0 * ctx.scale)
-                .min(ctx.layout.cache_height);
-            assert!(size.is_finite(), "cache button height must be finite");
-            assert!(size > 0.0, "cache button height must be positive");
-            size
-        } else {
-            0.0
-        };
-        let cache_button_padding = ctx.layout.padding * ctx.style.cache_button_text_pad_factor;
-        assert!(
-            cache_button_padding.is_finite(),
-            "cache button padding must be finite"
-        );
-        assert!(
-            cache_button_padding >= 0.0,
-            "cache button padding must be non-negative"
-        );
-        let cache_text_width = if ctx.layout.cache_height > 0.0 {
-            let cached_width = text_width(ctx.painter(), &ctx.body_font, "cached", ctx.text_color);
-            let cache_width = text_width(ctx.painter(), &ctx.body_font, "cache", ctx.text_color);
-            cached_width.max(cache_width)
-        } else {
-            0.0
-        };
-        let cache_button_width = (cache_button_height * ctx.style.cache_button_width_factor)
-            .max(cache_button_height)
-            .max(cache_text_width + cache_button_padding * 2.0);
-        assert!(
-            cache_button_width.is_finite(),
-            "cache button width must be finite"
-        );
-        assert!(
-            cache_button_width > 0.0,
-            "cache button width must be positive"
-        );
-        let cache_button_pos = egui::pos2(
-            cache_rect.min.x + ctx.layout.padding,
-            cache_rect.min.y + (ctx.layout.cache_height - cache_button_height) * 0.5,
-        );
-        let cache_button_rect = egui::Rect::from_min_size(
-            cache_button_pos,
-            egui::vec2(cache_button_width, cache_button_height),
-        );
 
         let node_id = ctx.ui().make_persistent_id(("node_body", node.id));
+        // The body is the node's only focusable widget, so Tab cycles node to node and
+        // Enter/Space selects the focused node via egui's synthetic click.
         let body_response = ctx.ui().interact(node_rect, node_id, egui::Sense::click());
+        body_response.widget_info(|| {
+            egui::WidgetInfo::selected(
+                egui::WidgetType::Other,
+                true,
+                selected_nodes.contains(&node.id),
+                node_accessible_label(node),
+            )
+        });
 
+        // Read-only nodes keep hover (tooltips) but none of the editing senses; header
+        // clicks then fall through to the body, which still selects.
+        let edit_sense = |sense: egui::Sense| {
+            if read_only {
+                egui::Sense::hover()
+            } else {
+                sense
+            }
+        };
         let close_id = ctx.ui().make_persistent_id(("node_close", node.id));
-        let close_response = ctx
-            .ui()
-            .interact(close_rect, close_id, egui::Sense::click());
+        let close_response =
+            ctx.ui()
+                .interact(close_rect, close_id, edit_sense(egui::Sense::CLICK));
         let cache_id = ctx.ui().make_persistent_id(("node_cache", node.id));
-        let cache_response = ctx
-            .ui()
-            .interact(cache_button_rect, cache_id, egui::Sense::click());
+        let cache_response =
+            ctx.ui()
+                .interact(cache_button_rect, cache_id, edit_sense(egui::Sense::CLICK));
 
         let header_id = ctx.ui().make_persistent_id(("node_header", node.id));
-        let response = ctx
-            .ui()
-            .interact(header_drag_rect, header_id, egui::Sense::drag());
+        let response =
+            ctx.ui()
+                .interact(header_drag_rect, header_id, edit_sense(egui::Sense::DRAG));
 
-        if response.dragged() {
-            node.pos += response.drag_delta() / ctx.scale;
+        if let Some(delta) = header_move_delta(ctx, &response, header_id, drag_threshold) {
+            if selected_nodes.contains(&node.id) {
+                interaction.selection_drag = Some((node.id, delta));
+            }
+            // Pinned positions are screen offsets, so they move 1:1 with the pointer.
+            node.pos += if node.screen_pinned {
+                delta
+            } else {
+                interaction.dragged_node = Some(node.id);
+                delta / ctx.scale
+            };
+        }
+
+        for menu_response in [&body_response, &response]
+            .into_iter()
+            .filter(|_| !read_only)
+        {
+            menu_response.context_menu(|ui| {
+                if ui
+                    .checkbox(&mut node.bypass, "Bypass")
+                    .on_hover_text("Mute the node; its first input passes straight through")
+                    .changed()
+                {
+                    ui.close();
+                }
+            });
         }
 
-        if ctx.layout.cache_height > 0.0 && cache_response.clicked() {
+        if has_cache_row && cache_response.clicked() {
             node.cache_output = !node.cache_output;
         }
 
-        if close_response.hovered() {
+        if close_response.hovered() && !read_only {
             close_response.show_tooltip_text("Remove node");
         }
 
+        if response.hovered() && !response.dragged() && !node.description.is_empty() {
+            response.show_tooltip_text(&node.description);
+        }
+
         if close_response.clicked() {
             interaction.remove_request = Some(node.id);
             continue;
         }
 
-        if response.clicked() || response.dragged() || body_response.clicked() {
+        // The resize grip sits beside the cache row, or along the header of a compact
+        // card, which has nothing below it.
+        let resize_width = 6.0 * ctx.scale;
+        let resize_top = if ctx.layout.is_compact(node) {
+            header_rect.min.y
+        } else {
+            header_rect.max.y
+        };
+        let resize_rect = egui::Rect::from_min_max(
+            egui::pos2(node_rect.max.x - resize_width, resize_top),
+            egui::pos2(
+                node_rect.max.x + resize_width * 0.5,
+                node_rect.min.y + ctx.layout.rows_top(node),
+            ),
+        );
+        let resize_id = ctx.ui().make_persistent_id(("node_resize", node.id));
+        let resize_response = ctx.ui().interact(
+            resize_rect,
+            resize_id,
+            edit_sense(egui::Sense::CLICK | egui::Sense::DRAG),
+        );
+        if !read_only && (resize_response.hovered() || resize_response.dragged()) {
+            ctx.ui()
+                .ctx()
+                .set_cursor_icon(egui::CursorIcon::ResizeHorizontal);
+        }
+        if resize_response.double_clicked() {
+            node.width_override = None;
+        } else if resize_response.dragged() {
+            let min_width = auto_node_width(
+                ctx.painter(),
+                &ctx.text_cache,
+                node,
+                &ctx.layout,
+                &ctx.heading_font,
+                &ctx.body_font,
+                ctx.style,
+            );
+            let width = (node_width + resize_response.drag_delta().x).max(min_width);
+            node.width_override = Some(width / ctx.scale);
+        }
+
+        let rows = ctx.layout.visible_rows(node);
+        let row_count = ctx.layout.row_count(node);
+        let mut scroll_arrows = Vec::new();
+        if rows.len() < row_count {
+            let arrow_size = egui::vec2(ctx.layout.row_height, ctx.layout.padding);
+            let up_center = egui::pos2(
+                node_rect.center().x,
+                node_rect.min.y + ctx.layout.rows_top(node) - ctx.layout.padding * 0.5,
+            );
+            let down_center = egui::pos2(
+                node_rect.center().x,
+                node_rect.max.y - ctx.layout.padding * 0.5,
+            );
+            let can_scroll_up = rows.start > 0;
+            let can_scroll_down = rows.end < row_count;
+            for (center, up, enabled) in [
+                (up_center, true, can_scroll_up),
+                (down_center, false, can_scroll_down),
+            ] {
+                let arrow_rect = egui::Rect::from_center_size(center, arrow_size);
+                let arrow_id = ctx
+                    .ui()
+                    .make_persistent_id(("node_row_scroll", node.id, up));
+                let arrow_response = ctx.ui().interact(arrow_rect, arrow_id, egui::Sense::CLICK);
+                if enabled && arrow_response.clicked() {
+                    node.row_scroll = if up { rows.start - 1 } else { rows.start + 1 };
+                }
+                scroll_arrows.push((arrow_rect, up, enabled, arrow_response.hovered()));
+            }
+        }
+
+        // Dragging a selected node keeps the selection so all of it moves together.
+        let drag_selects = response.dragged() && !selected_nodes.contains(&node.id);
+        if response.clicked() || drag_selects || body_response.clicked() {
             interaction.selection_request = Some(node.id);
+            interaction.extend_selection = ctx.ui().input(|input| input.modifiers.shift);
+        }
+
+        if body_response.double_clicked() || response.double_clicked() {
+            interaction.double_click_request = Some(node.id);
         }
 
-        let selected_id = interaction.selection_request.or(graph.selected_node_id);
-        let is_selected = selected_id.is_some_and(|id| id == node.id);
+        // Reflect this frame's click right away; a plain click replaces the selection.
+        let is_selected = interaction.selection_request == Some(node.id)
+            || (selected_nodes.contains(&node.id)
+                && (interaction.selection_request.is_none() || interaction.extend_selection));
 
-        ctx.painter().rect(
+        let dim = |color: egui::Color32| {
+            if node.bypass {
+                color.gamma_multiply(BYPASS_DIM)
+            } else {
+                color
+            }
+        };
+        ctx.painter()
+            .rect_filled(node_rect, ctx.layout.corner_radius, dim(node_fill));
+        if let Some(header_fill) = ctx.style.header_fill(node) {
+            // Compact cards are all header, so they keep every corner rounded.
+            let radius = egui::CornerRadius::from(ctx.layout.corner_radius);
+            let corners = if header_rect.height() < node_rect.height() {
+                egui::CornerRadius {
+                    sw: 0,
+                    se: 0,
+                    ..radius
+                }
+            } else {
+                radius
+            };
+            ctx.painter()
+                .rect_filled(header_rect, corners, dim(header_fill));
+        }
+        ctx.painter().rect_stroke(
             node_rect,
             ctx.layout.corner_radius,
-            node_fill,
             if is_selected {
                 selected_stroke
+            } else if let Some(level) = health.and_then(|health| health.get(&node.id)) {
+                ctx.style.health_stroke(*level)
+            } else if highlighted_nodes.contains(&node.id) {
+                ctx.style.flow_highlight_stroke
             } else {
                 node_stroke
             },
             egui::StrokeKind::Inside,
         );
+        if body_response.has_focus() {
+            ctx.painter().rect_stroke(
+                node_rect.expand(3.0 * ctx.scale),
+                ctx.layout.corner_radius,
+                ctx.style.focus_stroke,
+                egui::StrokeKind::Outside,
+            );
+        }
 
-        if ctx.layout.cache_height > 0.0 {
+        if has_cache_row && !read_only {
             let button_fill = if node.cache_output {
                 ctx.style.cache_active_color
             } else if cache_response.is_pointer_button_down_on() {
@@ -278,6 +712,49 @@ pub fn render_node_bodies(ctx: &RenderContext, graph: &mut model::Graph) -> Node
             );
         }
 
+        for (arrow_rect, up, enabled, hovered) in scroll_arrows {
+            let color = if !enabled {
+                visuals
+                    .widgets
+                    .noninteractive
+                    .fg_stroke
+                    .color
+                    .gamma_multiply(0.4)
+            } else if hovered {
+                visuals.widgets.hovered.fg_stroke.color
+            } else {
+                visuals.text_color()
+            };
+            let half_width = arrow_rect.height() * 0.5;
+            let (tip_y, base_y) = if up {
+                (arrow_rect.min.y, arrow_rect.max.y)
+            } else {
+                (arrow_rect.max.y, arrow_rect.min.y)
+            };
+            let center_x = arrow_rect.center().x;
+            ctx.painter().add(egui::Shape::convex_polygon(
+                vec![
+                    egui::pos2(center_x, tip_y),
+                    egui::pos2(center_x + half_width, base_y),
+                    egui::pos2(center_x - half_width, base_y),
+                ],
+                color,
+                egui::Stroke::NONE,
+            ));
+        }
+
+        if !read_only && (resize_response.hovered() || resize_response.dragged()) {
+            let grip_x = node_rect.max.x - resize_width * 0.5;
+            let grip_stroke = egui::Stroke::new(1.4 * ctx.scale, visuals.text_color());
+            ctx.painter().line_segment(
+                [
+                    egui::pos2(grip_x, resize_rect.min.y + ctx.layout.padding * 0.5),
+                    egui::pos2(grip_x, resize_rect.max.y - ctx.layout.padding * 0.5),
+                ],
+                grip_stroke,
+            );
+        }
+
         let dot_center_y = header_rect.center().y;
         for (index, (center_x, tooltip, color)) in dot_centers.iter().enumerate() {
             let dot_center = egui::pos2(*center_x, dot_center_y);
@@ -293,6 +770,9 @@ pub fn render_node_bodies(ctx: &RenderContext, graph: &mut model::Graph) -> Node
             }
         }
 
+        if read_only {
+            continue;
+        }
         let close_fill = if close_response.is_pointer_button_down_on() {
             visuals.widgets.active.bg_fill
         } else if close_response.hovered() {
@@ -334,12 +814,12 @@ pub fn render_node_bodies(ctx: &RenderContext, graph: &mut model::Graph) -> Node
     interaction
 }
 
-pub fn render_ports(ctx: &RenderContext, graph: &model::Graph) {
-    for node in &graph.nodes {
+pub fn render_ports(ctx: &RenderContext, graph: &model::Graph, node_order: &[usize]) {
+    for node in node_order.iter().map(|index| &graph.nodes[*index]) {
         let node_width = ctx.node_width(node.id);
 
-        for (index, _input) in node.inputs.iter().enumerate() {
-            let center = node_input_pos(ctx.origin, node, index, &ctx.layout, ctx.scale);
+        for index in ctx.layout.visible_ports(node, PortSide::Input) {
+            let center = node_input_pos(ctx.view, node, index, &ctx.layout);
 
             let port_rect = egui::Rect::from_center_size(
                 center,
@@ -348,14 +828,14 @@ pub fn render_ports(ctx: &RenderContext, graph: &model::Graph) {
             let color = if ctx.ui().rect_contains_pointer(port_rect) {
                 ctx.style.input_hover_color
             } else {
-                ctx.style.input_port_color
+                ctx.style
+                    .port_color(&node.inputs[index].data_type, ctx.style.input_port_color)
             };
             ctx.painter().circle_filled(center, ctx.port_radius, color);
         }
 
-        for (index, _output) in node.outputs.iter().enumerate() {
-            let center =
-                node_output_pos(ctx.origin, node, index, &ctx.layout, ctx.scale, node_width);
+        for index in ctx.layout.visible_ports(node, PortSide::Output) {
+            let center = node_output_pos(ctx.view, node, index, &ctx.layout, node_width);
 
             let port_rect = egui::Rect::from_center_size(
                 center,
@@ -364,70 +844,211 @@ pub fn render_ports(ctx: &RenderContext, graph: &model::Graph) {
             let color = if ctx.ui().rect_contains_pointer(port_rect) {
                 ctx.style.output_hover_color
             } else {
-                ctx.style.output_port_color
+                ctx.style.port_color(
+                    &node.outputs[index].data_type,
+                    ctx.style.output_color(index),
+                )
             };
             ctx.painter().circle_filled(center, ctx.port_radius, color);
         }
     }
 }
 
-pub fn render_node_labels(ctx: &RenderContext, graph: &model::Graph) {
-    let header_text_offset = ctx.style.header_text_offset;
-
-    for node in &graph.nodes {
+pub fn render_node_labels(ctx: &RenderContext, graph: &model::Graph, node_order: &[usize]) {
+    for node in node_order.iter().map(|index| &graph.nodes[*index]) {
         let node_rect = ctx.node_rect(node);
         let node_width = ctx.node_width(node.id);
 
-        ctx.painter().text(
+        let header_rect = egui::Rect::from_min_size(
+            node_rect.min,
+            egui::vec2(node_width, ctx.layout.header_height),
+        );
+        let title = ctx
+            .text_cache
+            .galley(ctx.painter(), &node.name, &ctx.heading_font);
+        let header_text_offset = ((ctx.layout.header_height - title.size().y) * 0.5).max(0.0);
+        let title_rect = egui::Rect::from_min_size(
             node_rect.min + egui::vec2(ctx.layout.padding, header_text_offset),
-            egui::Align2::LEFT_TOP,
-            &node.name,
-            ctx.heading_font.clone(),
-            ctx.text_color,
+            title.size(),
         );
+        let header_painter = ctx
+            .painter()
+            .with_clip_rect(header_rect.intersect(ctx.painter().clip_rect()));
+        let title_color = if node.bypass {
+            ctx.text_color.gamma_multiply(BYPASS_DIM)
+        } else {
+            ctx.text_color
+        };
+        header_painter.galley(title_rect.min, title, title_color);
+        if node.bypass {
+            header_painter.hline(
+                title_rect.x_range(),
+                title_rect.center().y,
+                egui::Stroke::new(1.4 * ctx.scale, title_color),
+            );
+        }
 
-        for (index, input) in node.inputs.iter().enumerate() {
-            let text_pos = node_rect.min
-                + egui::vec2(
-                    ctx.layout.padding,
-                    ctx.layout.header_height
-                        + ctx.layout.cache_height
-                        + ctx.layout.padding
-                        + ctx.layout.row_height * index as f32,
-                );
-            ctx.painter().text(
+        if ctx.scale < ctx.style.label_lod_zoom {
+            draw_port_count_badges(ctx, node, node_width);
+            continue;
+        }
+
+        let row_top = |side, index| {
+            ctx.layout.row_center_offset(node, side, index) - ctx.layout.row_height * 0.5
+        };
+        for index in ctx.layout.visible_ports(node, PortSide::Input) {
+            let text_pos =
+                node_rect.min + egui::vec2(ctx.layout.padding, row_top(PortSide::Input, index));
+            ctx.text(
                 text_pos,
                 egui::Align2::LEFT_TOP,
-                &input.name,
-                ctx.body_font.clone(),
-                ctx.text_color,
+                &node.inputs[index].name,
+                &ctx.body_font,
             );
         }
 
-        for (index, output) in node.outputs.iter().enumerate() {
+        for index in ctx.layout.visible_ports(node, PortSide::Output) {
+            let output = &node.outputs[index];
             let text_pos = node_rect.min
                 + egui::vec2(
                     node_width - ctx.layout.padding,
-                    ctx.layout.header_height
-                        + ctx.layout.cache_height
-                        + ctx.layout.padding
-                        + ctx.layout.row_height * index as f32,
+                    row_top(PortSide::Output, index),
                 );
-            ctx.painter().text(
+            let label = ctx.text(
                 text_pos,
                 egui::Align2::RIGHT_TOP,
                 &output.name,
-                ctx.body_font.clone(),
-                ctx.text_color,
+                &ctx.body_font,
             );
+            if let Some(value) = &output.last_value {
+                let galley =
+                    ctx.text_cache
+                        .galley(ctx.painter(), &value_preview(value), &ctx.body_font);
+                let preview = egui::Align2::RIGHT_TOP.anchor_size(
+                    egui::pos2(label.min.x - ctx.layout.padding, label.min.y),
+                    galley.size(),
+                );
+                ctx.painter().rect_filled(
+                    preview.expand(ctx.layout.padding * 0.25),
+                    ctx.layout.corner_radius * 0.5,
+                    ctx.style.badge_fill,
+                );
+                ctx.painter().galley(preview.min, galley, ctx.text_color);
+            }
         }
     }
 }
 
+/// Longest output value preview, in characters, before it is cut short with "…".
+const VALUE_PREVIEW_CHARS: usize = 12;
+
+/// Preview text for an output's last value: its JSON form (`42`, `true`, `"hi"`), cut
+/// after `VALUE_PREVIEW_CHARS`.
+pub(crate) fn value_preview(value: &serde_json::Value) -> String {
+    let text = value.to_string();
+    match text.char_indices().nth(VALUE_PREVIEW_CHARS) {
+        Some((cut, _)) => format!("{}…", &text[..cut]),
+        None => text,
+    }
+}
+
+/// Port count badges beside the input and output edges, centered on the visible port
+/// rows, drawn in place of culled port labels at low zoom.
+fn draw_port_count_badges(ctx: &RenderContext, node: &model::Node, node_width: f32) {
+    // Fixed screen size so the count stays legible however far out the view is.
+    let font = egui::FontId::proportional(10.0);
+    let radius = 7.0;
+    let gap = ctx.port_radius + radius + 2.0;
+
+    let inputs = ctx.layout.visible_ports(node, PortSide::Input);
+    if let (Some(&first), Some(&last)) = (inputs.first(), inputs.last()) {
+        let input_pos = |index| node_input_pos(ctx.view, node, index, &ctx.layout);
+        let center = input_pos(first).lerp(input_pos(last), 0.5) - egui::vec2(gap, 0.0);
+        draw_count_badge(ctx, center, radius, node.inputs.len(), &font);
+    }
+    let outputs = ctx.layout.visible_ports(node, PortSide::Output);
+    if let (Some(&first), Some(&last)) = (outputs.first(), outputs.last()) {
+        let output_pos = |index| node_output_pos(ctx.view, node, index, &ctx.layout, node_width);
+        let center = output_pos(first).lerp(output_pos(last), 0.5) + egui::vec2(gap, 0.0);
+        draw_count_badge(ctx, center, radius, node.outputs.len(), &font);
+    }
+}
+
+fn draw_count_badge(
+    ctx: &RenderContext,
+    center: egui::Pos2,
+    radius: f32,
+    count: usize,
+    font: &egui::FontId,
+) {
+    ctx.painter()
+        .circle_filled(center, radius, ctx.style.badge_fill);
+    ctx.text(
+        center,
+        egui::Align2::CENTER_CENTER,
+        &count.to_string(),
+        font,
+    );
+}
+
+/// Screen delta a header drag moves its node by this frame. Nothing moves until the
+/// pointer is `threshold` away from the press; the frame that crosses it catches up on
+/// the whole offset so the grab point stays under the pointer, and the drag stays
+/// latched (in egui temp data under `header_id`) even if the pointer comes back.
+fn header_move_delta(
+    ctx: &RenderContext,
+    response: &egui::Response,
+    header_id: egui::Id,
+    threshold: f32,
+) -> Option<egui::Vec2> {
+    let moving = ctx
+        .ui()
+        .data(|data| data.get_temp::<bool>(header_id))
+        .unwrap_or(false);
+    if !response.dragged() {
+        if moving {
+            ctx.ui().data_mut(|data| data.remove::<bool>(header_id));
+        }
+        return None;
+    }
+    if moving {
+        return Some(response.drag_delta());
+    }
+    let offset = ctx.ui().input(|input| {
+        input
+            .pointer
+            .press_origin()
+            .zip(input.pointer.interact_pos())
+            .map(|(origin, pos)| pos - origin)
+    })?;
+    if offset.length() < threshold {
+        return None;
+    }
+    ctx.ui().data_mut(|data| data.insert_temp(header_id, true));
+    Some(offset)
+}
+
+fn node_accessible_label(node: &model::Node) -> String {
+    let mut label = format!(
+        "Node {}, {} inputs, {} outputs",
+        node.name,
+        node.inputs.len(),
+        node.outputs.len()
+    );
+    if !node.description.is_empty() {
+        label.push_str(". ");
+        label.push_str(&node.description);
+    }
+    label
+}
+
 fn node_size(node: &model::Node, layout: &NodeLayout, node_width: f32) -> egui::Vec2 {
-    assert!(node_width.is_finite(), "node width must be finite");
-    assert!(node_width > 0.0, "node width must be positive");
-    let row_count = node.inputs.len().max(node.outputs.len()).max(1);
+    debug_assert!(node_width.is_finite(), "node width must be finite");
+    debug_assert!(node_width > 0.0, "node width must be positive");
+    if layout.is_compact(node) {
+        return egui::vec2(node_width, layout.header_height);
+    }
+    let row_count = layout.visible_rows(node).len();
     let height = layout.header_height
         + layout.cache_height
         + layout.padding
@@ -437,134 +1058,82 @@ fn node_size(node: &model::Node, layout: &NodeLayout, node_width: f32) -> egui::
 }
 
 pub(crate) fn node_input_pos(
-    origin: egui::Pos2,
+    view: render::ViewTransform,
     node: &model::Node,
     index: usize,
     layout: &NodeLayout,
-    scale: f32,
 ) -> egui::Pos2 {
     assert!(
         index < node.inputs.len(),
         "input index must be within node inputs"
     );
-    assert!(scale > 0.0, "graph scale must be positive");
-    let y = origin.y
-        + node.pos.y * scale
-        + layout.header_height
-        + layout.cache_height
-        + layout.padding
-        + layout.row_height * index as f32
-        + layout.row_height * 0.5;
-    egui::pos2(origin.x + node.pos.x * scale, y)
+    debug_assert!(view.scale > 0.0, "graph scale must be positive");
+    view.node_min(node) + egui::vec2(0.0, layout.row_center_offset(node, PortSide::Input, index))
 }
 
 pub(crate) fn node_output_pos(
-    origin: egui::Pos2,
+    view: render::ViewTransform,
     node: &model::Node,
     index: usize,
     layout: &NodeLayout,
-    scale: f32,
     node_width: f32,
 ) -> egui::Pos2 {
     assert!(
         index < node.outputs.len(),
         "output index must be within node outputs"
     );
-    assert!(scale > 0.0, "graph scale must be positive");
-    assert!(node_width.is_finite(), "node width must be finite");
-    assert!(node_width > 0.0, "node width must be positive");
-    let y = origin.y
-        + node.pos.y * scale
-        + layout.header_height
-        + layout.cache_height
-        + layout.padding
-        + layout.row_height * index as f32
-        + layout.row_height * 0.5;
-    egui::pos2(origin.x + node.pos.x * scale + node_width, y)
+    debug_assert!(view.scale > 0.0, "graph scale must be positive");
+    debug_assert!(node_width.is_finite(), "node width must be finite");
+    debug_assert!(node_width > 0.0, "node width must be positive");
+    view.node_min(node)
+        + egui::vec2(
+            node_width,
+            layout.row_center_offset(node, PortSide::Output, index),
+        )
 }
 
-pub(crate) fn bezier_control_offset(start: egui::Pos2, end: egui::Pos2, scale: f32) -> f32 {
-    assert!(scale > 0.0, "graph scale must be positive");
+pub(crate) fn bezier_control_offset(
+    start: egui::Pos2,
+    end: egui::Pos2,
+    scale: f32,
+    tension: f32,
+) -> f32 {
+    debug_assert!(scale > 0.0, "graph scale must be positive");
+    debug_assert!(tension > 0.0, "curve tension must be positive");
     let dx = (end.x - start.x).abs();
-    let offset = (dx * 0.5).max(40.0 * scale);
-    assert!(offset.is_finite(), "bezier control offset must be finite");
+    let offset = (dx * 0.5).max(40.0 * scale) * tension;
+    debug_assert!(offset.is_finite(), "bezier control offset must be finite");
     offset
 }
 
 pub(crate) fn compute_node_widths(
     painter: &egui::Painter,
+    text_cache: &TextLayoutCache,
     graph: &model::Graph,
     layout: &NodeLayout,
     heading_font: &egui::FontId,
     body_font: &egui::FontId,
-    text_color: egui::Color32,
     style: &crate::gui::style::GraphStyle,
 ) -> HashMap<Uuid, f32> {
     layout.assert_valid();
-    let scale_guess = layout.row_height / 18.0;
-    assert!(scale_guess.is_finite(), "layout scale guess must be finite");
-    assert!(scale_guess > 0.0, "layout scale guess must be positive");
     let mut widths = HashMap::with_capacity(graph.nodes.len());
 
     for node in &graph.nodes {
-        let header_width =
-            text_width(painter, heading_font, &node.name, text_color) + layout.padding * 2.0;
-        let vertical_padding = layout.padding * style.cache_button_vertical_pad_factor;
-        let cache_button_height = (layout.cache_height - vertical_padding * 2.0)
-            .max(10.0 * scale_guess)
-            .min(layout.cache_height);
-        let cache_text_width = text_width(painter, body_font, "cached", text_color)
-            .max(text_width(painter, body_font, "cache", text_color));
-        let cache_button_width = (cache_button_height * style.cache_button_width_factor)
-            .max(cache_button_height)
-            .max(cache_text_width + layout.padding * style.cache_button_text_pad_factor * 2.0);
-        let cache_row_width = if layout.cache_height > 0.0 {
-            layout.padding + cache_button_width + layout.padding
-        } else {
-            0.0
-        };
-        let status_row_width = {
-            let dot_diameter = style.status_dot_radius * 2.0;
-            let count = 2usize;
-            let gaps = (count - 1) as f32;
-            let total = count as f32 * dot_diameter + gaps * style.status_item_gap;
-            layout.padding + total + layout.padding
-        };
-
-        let input_widths: Vec<f32> = node
-            .inputs
-            .iter()
-            .map(|input| text_width(painter, body_font, &input.name, text_color))
-            .collect();
-        let output_widths: Vec<f32> = node
-            .outputs
-            .iter()
-            .map(|output| text_width(painter, body_font, &output.name, text_color))
-            .collect();
-
-        let row_count = node.inputs.len().max(node.outputs.len()).max(1);
-        let mut max_row_width: f32 = 0.0;
-
-        let inter_side_padding = 0.0;
-        for row in 0..row_count {
-            let left = input_widths.get(row).copied().unwrap_or(0.0);
-            let right = output_widths.get(row).copied().unwrap_or(0.0);
-            let mut row_width = layout.padding * 2.0 + left + right;
-            if left > 0.0 && right > 0.0 {
-                row_width += inter_side_padding;
-            }
-            max_row_width = max_row_width.max(row_width);
-        }
-
-        let computed = layout.node_width.max(
-            header_width
-                .max(max_row_width)
-                .max(cache_row_width)
-                .max(status_row_width),
+        let auto_width = auto_node_width(
+            painter,
+            text_cache,
+            node,
+            layout,
+            heading_font,
+            body_font,
+            style,
         );
-        assert!(computed.is_finite(), "node width must be finite");
-        assert!(computed > 0.0, "node width must be positive");
-        let prior = widths.insert(node.id, computed);
+        let width = node
+            .width_override
+            .map(|width| (width * style.scale).max(auto_width))
+            .unwrap_or(auto_width);
+        debug_assert!(width.is_finite(), "node width must be finite");
+        let prior = widths.insert(node.id, width);
         assert!(
             prior.is_none(),
             "node width map must not contain duplicate ids"
@@ -574,9 +1143,95 @@ pub(crate) fn compute_node_widths(
     widths
 }
 
+/// Minimum width that fits the node's title, port labels, cache button, and status dots.
+fn auto_node_width(
+    painter: &egui::Painter,
+    text_cache: &TextLayoutCache,
+    node: &model::Node,
+    layout: &NodeLayout,
+    heading_font: &egui::FontId,
+    body_font: &egui::FontId,
+    style: &crate::gui::style::GraphStyle,
+) -> f32 {
+    let scale_guess = layout.row_height / 18.0;
+    debug_assert!(scale_guess.is_finite(), "layout scale guess must be finite");
+    debug_assert!(scale_guess > 0.0, "layout scale guess must be positive");
+
+    let header_width =
+        text_width(painter, text_cache, heading_font, &node.name) + layout.padding * 2.0;
+    let vertical_padding = layout.padding * style.cache_button_vertical_pad_factor;
+    let cache_button_height = (layout.cache_height - vertical_padding * 2.0)
+        .max(10.0 * scale_guess)
+        .min(layout.cache_height);
+    let cache_text_width = text_width(painter, text_cache, body_font, "cached")
+        .max(text_width(painter, text_cache, body_font, "cache"));
+    let cache_button_width = (cache_button_height * style.cache_button_width_factor)
+        .max(cache_button_height)
+        .max(cache_text_width + layout.padding * style.cache_button_text_pad_factor * 2.0);
+    let cache_row_width = if layout.cache_row_height(node) > 0.0 {
+        layout.padding + cache_button_width + layout.padding
+    } else {
+        0.0
+    };
+    let status_row_width = {
+        let dot_diameter = style.status_dot_radius * 2.0;
+        let count = 2usize;
+        let gaps = (count - 1) as f32;
+        let total = count as f32 * dot_diameter + gaps * style.status_item_gap;
+        layout.padding + total + layout.padding
+    };
+
+    let input_widths: Vec<f32> = node
+        .inputs
+        .iter()
+        .map(|input| text_width(painter, text_cache, body_font, &input.name))
+        .collect();
+    let output_widths: Vec<f32> = node
+        .outputs
+        .iter()
+        .map(|output| {
+            let label = text_width(painter, text_cache, body_font, &output.name);
+            match &output.last_value {
+                Some(value) => {
+                    let preview = text_width(painter, text_cache, body_font, &value_preview(value));
+                    label + layout.padding + preview
+                }
+                None => label,
+            }
+        })
+        .collect();
+
+    // Labels share a line when their rows overlap; with `PortAlign::Center` an output
+    // can straddle two input rows.
+    let lone_width = input_widths
+        .iter()
+        .chain(&output_widths)
+        .fold(0.0_f32, |max, width| max.max(*width));
+    let mut max_row_width = layout.padding * 2.0 + lone_width;
+    for (input_index, left) in input_widths.iter().enumerate() {
+        let input_row = layout.port_row(node, PortSide::Input, input_index);
+        for (output_index, right) in output_widths.iter().enumerate() {
+            let output_row = layout.port_row(node, PortSide::Output, output_index);
+            if (input_row - output_row).abs() < 1.0 {
+                max_row_width = max_row_width.max(layout.padding * 2.0 + left + right);
+            }
+        }
+    }
+
+    let computed = layout.node_width.max(
+        header_width
+            .max(max_row_width)
+            .max(cache_row_width)
+            .max(status_row_width),
+    );
+    debug_assert!(computed.is_finite(), "node width must be finite");
+    debug_assert!(computed > 0.0, "node width must be positive");
+    computed
+}
+
 pub(crate) fn scaled_font(ui: &egui::Ui, style: egui::TextStyle, scale: f32) -> egui::FontId {
-    assert!(scale.is_finite(), "font scale must be finite");
-    assert!(scale > 0.0, "font scale must be positive");
+    debug_assert!(scale.is_finite(), "font scale must be finite");
+    debug_assert!(scale > 0.0, "font scale must be positive");
     let base = style.resolve(ui.style());
     egui::FontId {
         size: base.size * scale,
@@ -584,15 +1239,322 @@ pub(crate) fn scaled_font(ui: &egui::Ui, style: egui::TextStyle, scale: f32) ->
     }
 }
 
+/// Title font for an already scaled layout.
+pub(crate) fn header_font(ui: &egui::Ui, layout: &NodeLayout, scale: f32) -> egui::FontId {
+    match layout.header_font_size {
+        Some(size) => egui::FontId {
+            size,
+            family: egui::TextStyle::Heading.resolve(ui.style()).family,
+        },
+        None => scaled_font(ui, egui::TextStyle::Heading, scale),
+    }
+}
+
 fn text_width(
     painter: &egui::Painter,
+    text_cache: &TextLayoutCache,
     font: &egui::FontId,
     text: &str,
-    color: egui::Color32,
 ) -> f32 {
-    let galley = painter.layout_no_wrap(text.to_string(), font.clone(), color);
+    let galley = text_cache.galley(painter, text, font);
     let width = galley.size().x;
-    assert!(width.is_finite(), "text width must be finite");
-    assert!(width >= 0.0, "text width must be non-negative");
+    debug_assert!(width.is_finite(), "text width must be finite");
+    debug_assert!(width >= 0.0, "text width must be non-negative");
     width
 }
+
+#[test]
+fn port_align_shifts_shorter_column() {
+    let graph = model::Graph::test_graph();
+    let sum = graph
+        .nodes
+        .iter()
+        .find(|node| node.name == "math(sum)")
+        .expect("test graph should contain sum");
+    assert_eq!((sum.inputs.len(), sum.outputs.len()), (2, 1));
+
+    let mut layout = NodeLayout::default();
+    let view = render::ViewTransform::unit();
+    let input_y = |layout: &NodeLayout, index| node_input_pos(view, sum, index, layout).y;
+    let output_y = |layout: &NodeLayout| node_output_pos(view, sum, 0, layout, 100.0).y;
+
+    assert_eq!(
+        output_y(&layout),
+        input_y(&layout, 0),
+        "top-aligned by default"
+    );
+    layout.port_align = PortAlign::Bottom;
+    assert_eq!(output_y(&layout), input_y(&layout, 1));
+    layout.port_align = PortAlign::Center;
+    assert_eq!(
+        output_y(&layout),
+        (input_y(&layout, 0) + input_y(&layout, 1)) * 0.5
+    );
+    // The longer column never moves.
+    assert_eq!(input_y(&layout, 0), {
+        layout.port_align = PortAlign::Top;
+        input_y(&layout, 0)
+    });
+
+    // With scrolling, a bottom-aligned output stays hidden until its row is in view.
+    layout.port_align = PortAlign::Bottom;
+    layout.auto_height = false;
+    layout.max_visible_rows = 1;
+    assert!(layout.visible_ports(sum, PortSide::Output).is_empty());
+    assert_eq!(layout.visible_ports(sum, PortSide::Input), vec![0]);
+}
+
+#[test]
+fn portless_node_collapses_to_header_card_without_min_rows() {
+    let portless = model::Node {
+        name: "note".to_string(),
+        ..model::Node::default()
+    };
+    assert!(portless.inputs.is_empty() && portless.outputs.is_empty());
+    let mut layout = NodeLayout::default();
+    let view = render::ViewTransform::unit();
+    let height = |layout: &NodeLayout| node_rect_for_graph(view, &portless, layout, 100.0).height();
+
+    assert!(
+        !layout.is_compact(&portless),
+        "one row is reserved by default"
+    );
+    assert_eq!(
+        height(&layout),
+        layout.header_height + layout.cache_height + layout.padding * 2.0 + layout.row_height
+    );
+
+    layout.min_rows = 0;
+    assert!(layout.is_compact(&portless));
+    assert_eq!(height(&layout), layout.header_height);
+    assert_eq!(layout.cache_row_height(&portless), 0.0);
+    assert!(layout.visible_rows(&portless).is_empty());
+
+    // Nodes with ports keep their rows, and a larger minimum pads them.
+    let graph = model::Graph::test_graph();
+    let sum = &graph.nodes[2];
+    assert!(!layout.is_compact(sum));
+    assert_eq!(layout.visible_rows(sum), 0..2);
+    layout.min_rows = 4;
+    assert_eq!(layout.visible_rows(sum), 0..4);
+}
+
+#[test]
+fn graph_bounds_enclose_every_node() {
+    let mut graph = model::Graph::test_graph();
+    let layout = NodeLayout::default();
+    let widths: HashMap<Uuid, f32> = graph.nodes.iter().map(|node| (node.id, 180.0)).collect();
+    let bounds = graph
+        .bounds(&layout, &widths)
+        .expect("test graph has nodes");
+
+    assert_eq!(graph.nodes.len(), 5);
+    let rects: Vec<egui::Rect> = graph
+        .nodes
+        .iter()
+        .map(|node| node_rect_for_graph(render::ViewTransform::unit(), node, &layout, 180.0))
+        .collect();
+    assert!(rects.iter().all(|rect| bounds.contains_rect(*rect)));
+    // Tight: every edge is touched by some node.
+    assert_eq!(
+        bounds.min.x,
+        rects.iter().map(|rect| rect.min.x).fold(f32::MAX, f32::min)
+    );
+    assert_eq!(
+        bounds.max.y,
+        rects.iter().map(|rect| rect.max.y).fold(f32::MIN, f32::max)
+    );
+
+    // Graph bounds do not depend on the current view.
+    graph.zoom = 3.0;
+    graph.pan = egui::vec2(-500.0, 250.0);
+    assert_eq!(graph.bounds(&layout, &widths), Some(bounds));
+
+    for node in &mut graph.nodes {
+        node.screen_pinned = true;
+    }
+    assert_eq!(graph.bounds(&layout, &widths), None);
+    assert_eq!(
+        model::Graph::default().bounds(&layout, &HashMap::new()),
+        None
+    );
+}
+
+#[test]
+fn output_value_previews_are_cut_short_and_widen_the_node() {
+    assert_eq!(value_preview(&serde_json::json!(42)), "42");
+    assert_eq!(value_preview(&serde_json::json!(true)), "true");
+    assert_eq!(value_preview(&serde_json::json!("hi")), "\"hi\"");
+    assert_eq!(
+        value_preview(&serde_json::json!("a long string value")),
+        "\"a long stri…"
+    );
+
+    let mut graph = model::Graph::test_graph();
+    let ctx = egui::Context::default();
+    let _ = ctx.run(egui::RawInput::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            // A narrow minimum so the port row, not the default width, decides.
+            let layout = NodeLayout {
+                node_width: 10.0,
+                ..NodeLayout::default()
+            };
+            let style = crate::gui::style::GraphStyle::new(ui.visuals(), 1.0);
+            let text_cache = TextLayoutCache::new(ui.visuals().text_color());
+            let body_font = scaled_font(ui, egui::TextStyle::Body, 1.0);
+            let heading_font = header_font(ui, &layout, 1.0);
+            let width = |graph: &model::Graph| {
+                compute_node_widths(
+                    ui.painter(),
+                    &text_cache,
+                    graph,
+                    &layout,
+                    &heading_font,
+                    &body_font,
+                    &style,
+                )[&graph.nodes[0].id]
+            };
+            let plain = width(&graph);
+            let value_a = graph.nodes[0].id;
+            assert!(graph.record_output_value(value_a, 0, serde_json::json!("a long string")));
+            assert!(width(&graph) > plain);
+        });
+    });
+}
+
+#[test]
+fn width_override_widens_the_node_but_never_below_the_floor() {
+    let mut graph = model::Graph::test_graph();
+    let ctx = egui::Context::default();
+    let _ = ctx.run(egui::RawInput::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let scale = 1.5;
+            let layout = NodeLayout::default().scaled(scale);
+            let style = crate::gui::style::GraphStyle::new(ui.visuals(), scale);
+            let text_cache = TextLayoutCache::new(ui.visuals().text_color());
+            let body_font = scaled_font(ui, egui::TextStyle::Body, scale);
+            let heading_font = header_font(ui, &layout, scale);
+            let width = |graph: &model::Graph| {
+                compute_node_widths(
+                    ui.painter(),
+                    &text_cache,
+                    graph,
+                    &layout,
+                    &heading_font,
+                    &body_font,
+                    &style,
+                )[&graph.nodes[0].id]
+            };
+            let auto = width(&graph);
+            assert!(auto >= layout.node_width, "the layout width is the floor");
+
+            // The override is in graph units, so it scales with zoom like the layout.
+            graph.nodes[0].width_override = Some(600.0);
+            assert_eq!(width(&graph), 600.0 * scale);
+
+            graph.nodes[0].width_override = Some(1.0);
+            assert_eq!(
+                width(&graph),
+                auto,
+                "a narrow override keeps the auto width"
+            );
+        });
+    });
+}
+
+#[test]
+fn header_title_keeps_its_font_size_and_stays_centered_in_the_header() {
+    let graph = model::Graph::test_graph();
+    let ctx = egui::Context::default();
+    // The title of node 0 for a layout: its clip rect, position and galley height.
+    let title = |layout: &NodeLayout| {
+        let output = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let painter = ui.painter().clone();
+                let style = crate::gui::style::GraphStyle::new(ui.visuals(), graph.zoom);
+                let render_ctx =
+                    RenderContext::new(ui, &painter, ui.max_rect(), &graph, layout, &style);
+                render_node_labels(&render_ctx, &graph, &[0]);
+            });
+        });
+        output
+            .shapes
+            .iter()
+            .find_map(|clipped| match &clipped.shape {
+                egui::Shape::Text(text) if text.galley.text() == graph.nodes[0].name => {
+                    Some((clipped.clip_rect, text.pos, text.galley.size().y))
+                }
+                _ => None,
+            })
+            .expect("the title is drawn")
+    };
+    let with_header = |header_height, header_font_size| NodeLayout {
+        header_height,
+        header_font_size: Some(header_font_size),
+        ..NodeLayout::default()
+    };
+
+    let (_, tall_pos, tall_height) = title(&with_header(60.0, 14.0));
+    let (_, short_pos, short_height) = title(&with_header(24.0, 14.0));
+    assert_eq!(
+        tall_height, short_height,
+        "the header height leaves the font alone"
+    );
+    assert!(tall_height < 24.0);
+    assert_eq!(
+        tall_pos.y - short_pos.y,
+        (60.0 - 24.0) * 0.5,
+        "centered in the header"
+    );
+
+    // A title taller than its header is clipped to the header instead of spilling over.
+    let (clip, pos, height) = title(&with_header(12.0, 40.0));
+    assert!(height > 12.0);
+    assert!(clip.height() <= 12.0);
+    assert!(clip.min.y >= pos.y);
+}
+
+#[test]
+fn low_zoom_swaps_port_labels_for_port_count_badges() {
+    let mut graph = model::Graph::test_graph();
+    let ctx = egui::Context::default();
+    // Every text drawn for the labels of `math(sum)`, which has inputs a, b and output sum.
+    let texts = |graph: &model::Graph| {
+        let output = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let painter = ui.painter().clone();
+                let style = crate::gui::style::GraphStyle::new(ui.visuals(), graph.zoom);
+                let layout = NodeLayout::default();
+                let render_ctx =
+                    RenderContext::new(ui, &painter, ui.max_rect(), graph, &layout, &style);
+                assert!(1.0 > style.label_lod_zoom && 0.4 < style.label_lod_zoom);
+                render_node_labels(&render_ctx, graph, &[2]);
+            });
+        });
+        output
+            .shapes
+            .iter()
+            .filter_map(|clipped| match &clipped.shape {
+                egui::Shape::Text(text) => Some(text.galley.text().to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let labels = texts(&graph);
+    for label in ["a", "b", "sum"] {
+        assert!(
+            labels.iter().any(|text| text == label),
+            "{label} is labelled"
+        );
+    }
+    assert!(!labels.iter().any(|text| text == "2"), "no badges up close");
+
+    graph.zoom = 0.4;
+    let badges = texts(&graph);
+    assert_eq!(
+        badges,
+        ["math(sum)", "2", "1"],
+        "title plus input and output counts"
+    );
+}
@@ -0,0 +1,161 @@
+use eframe::egui;
+use uuid::Uuid;
+
+use crate::model;
+
+/// Seconds between automatic steps while playing.
+const STEP_SECONDS: f64 = 0.8;
+
+/// Walks `Graph::topological_order` one node at a time to demonstrate evaluation order;
+/// nothing is computed.
+#[derive(Debug, Default)]
+pub struct PlaybackState {
+    order: Vec<Uuid>,
+    index: usize,
+    playing: bool,
+    next_step_at: f64,
+    error: Option<String>,
+}
+
+impl PlaybackState {
+    pub fn reset(&mut self) {
+        self.order.clear();
+        self.index = 0;
+        self.playing = false;
+        self.error = None;
+    }
+
+    /// The node currently being "evaluated", if playback has started.
+    pub fn current(&self) -> Option<Uuid> {
+        self.order.get(self.index).copied()
+    }
+
+    /// Advances one node, starting from the first node when idle. Stops at the last node.
+    pub fn step(&mut self, graph: &model::Graph) {
+        if self.order.is_empty() {
+            self.start(graph);
+        } else if self.index + 1 < self.order.len() {
+            self.index += 1;
+        } else {
+            self.playing = false;
+        }
+    }
+
+    pub fn toggle_play(&mut self, graph: &model::Graph, now: f64) {
+        if self.playing {
+            self.playing = false;
+            return;
+        }
+        if self.order.is_empty() || self.index + 1 >= self.order.len() {
+            self.order.clear();
+            if !self.start(graph) {
+                return;
+            }
+        }
+        self.playing = true;
+        self.next_step_at = now + STEP_SECONDS;
+    }
+
+    /// Drops playback if the graph changed underneath it and advances on the timer.
+    /// Returns true while playing so the caller keeps repainting.
+    pub fn update(&mut self, graph: &model::Graph, now: f64) -> bool {
+        let stale = self.order.len() != graph.nodes.len()
+            || self
+                .order
+                .iter()
+                .any(|id| !graph.nodes.iter().any(|node| node.id == *id));
+        if !self.order.is_empty() && stale {
+            self.reset();
+        }
+
+        if self.playing && now >= self.next_step_at {
+            self.step(graph);
+            self.next_step_at = now + STEP_SECONDS;
+        }
+        self.playing
+    }
+
+    pub fn render_controls(&mut self, ui: &mut egui::Ui, graph: &model::Graph) {
+        let now = ui.input(|input| input.time);
+        ui.separator();
+        if ui
+            .button(if self.playing { "Pause" } else { "Play" })
+            .clicked()
+        {
+            self.toggle_play(graph, now);
+        }
+        if ui.button("Step").clicked() {
+            self.playing = false;
+            self.step(graph);
+        }
+        if ui
+            .add_enabled(!self.order.is_empty(), egui::Button::new("Stop"))
+            .clicked()
+        {
+            self.reset();
+        }
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::from_rgb(235, 80, 80), error);
+        } else if !self.order.is_empty() {
+            ui.label(format!("Step {}/{}", self.index + 1, self.order.len()));
+        }
+    }
+
+    fn start(&mut self, graph: &model::Graph) -> bool {
+        self.index = 0;
+        match graph.topological_order() {
+            Ok(order) if !order.is_empty() => {
+                self.order = order;
+                self.error = None;
+                true
+            }
+            Ok(_) => {
+                self.error = Some("Graph is empty".to_string());
+                false
+            }
+            Err(_) => {
+                self.playing = false;
+                self.error = Some("Graph has a cycle; playback needs an acyclic graph".to_string());
+                false
+            }
+        }
+    }
+}
+
+#[test]
+fn playback_steps_through_order_and_refuses_cycles() {
+    let mut graph = model::Graph::test_graph();
+    let order = graph
+        .topological_order()
+        .expect("test graph should be acyclic");
+
+    let mut playback = PlaybackState::default();
+    assert_eq!(playback.current(), None);
+    for expected in &order {
+        playback.step(&graph);
+        assert_eq!(playback.current(), Some(*expected));
+    }
+    playback.step(&graph);
+    assert_eq!(playback.current(), order.last().copied());
+
+    let last = *order.last().expect("order should not be empty");
+    graph.remove_node(last);
+    playback.update(&graph, 0.0);
+    assert_eq!(playback.current(), None);
+
+    let sum_id = graph.nodes[2].id;
+    graph.nodes[0].inputs.push(model::Input {
+        name: "loop".to_string(),
+        data_type: String::new(),
+        connections: vec![model::Connection {
+            node_id: sum_id,
+            output_index: 0,
+            label: None,
+            output_name: None,
+        }],
+        max_connections: None,
+    });
+    playback.toggle_play(&graph, 0.0);
+    assert_eq!(playback.current(), None);
+    assert!(!playback.update(&graph, 10.0));
+}
@@ -0,0 +1,232 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::gui::{
+    graph::{ConnectionStyle, DoubleClickAction, GraphUi, OccupiedInputDrop},
+    node::PortAlign,
+};
+
+pub const STORAGE_KEY: &str = "ui_preferences";
+
+const MIN_ZOOM_SENSITIVITY: f32 = 0.1;
+const MAX_ZOOM_SENSITIVITY: f32 = 5.0;
+const MAX_VISIBLE_ROWS_LIMIT: usize = 64;
+const MAX_MIN_ROWS: usize = 8;
+const MAX_NODE_DRAG_THRESHOLD: f32 = 20.0;
+
+/// Editor settings persisted in eframe storage, independent of any graph file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiPreferences {
+    pub double_click_action: DoubleClickAction,
+    pub auto_height: bool,
+    pub max_visible_rows: usize,
+    pub min_rows: usize,
+    pub port_align: PortAlign,
+    pub zoom_sensitivity: f32,
+    pub node_drag_threshold: f32,
+    pub connections_on_top: bool,
+    pub connection_style: ConnectionStyle,
+    pub connection_arrows: bool,
+    pub debug_node_preview: bool,
+    pub debug_render_timings: bool,
+    pub ungroup_on_drag_out: bool,
+    pub occupied_input_drop: OccupiedInputDrop,
+}
+
+impl Default for UiPreferences {
+    fn default() -> Self {
+        let graph_ui = GraphUi::default();
+        Self::from_graph_ui(&graph_ui)
+    }
+}
+
+impl UiPreferences {
+    pub fn from_graph_ui(graph_ui: &GraphUi) -> Self {
+        Self {
+            double_click_action: graph_ui.double_click_action,
+            auto_height: graph_ui.node_layout.auto_height,
+            max_visible_rows: graph_ui.node_layout.max_visible_rows,
+            min_rows: graph_ui.node_layout.min_rows,
+            port_align: graph_ui.node_layout.port_align,
+            zoom_sensitivity: graph_ui.zoom_sensitivity,
+            node_drag_threshold: graph_ui.node_drag_threshold,
+            connections_on_top: graph_ui.connections_on_top,
+            connection_style: graph_ui.connection_style,
+            connection_arrows: graph_ui.connection_arrows,
+            debug_node_preview: graph_ui.debug_node_preview,
+            debug_render_timings: graph_ui.debug_render_timings,
+            ungroup_on_drag_out: graph_ui.ungroup_on_drag_out,
+            occupied_input_drop: graph_ui.occupied_input_drop,
+        }
+    }
+
+    /// Applies the preferences, clamping values that may come from an edited storage file.
+    pub fn apply(&self, graph_ui: &mut GraphUi) {
+        graph_ui.double_click_action = self.double_click_action;
+        graph_ui.node_layout.auto_height = self.auto_height;
+        graph_ui.node_layout.max_visible_rows =
+            self.max_visible_rows.clamp(1, MAX_VISIBLE_ROWS_LIMIT);
+        graph_ui.node_layout.min_rows = self.min_rows.min(MAX_MIN_ROWS);
+        graph_ui.node_layout.port_align = self.port_align;
+        graph_ui.connections_on_top = self.connections_on_top;
+        graph_ui.connection_style = self.connection_style;
+        graph_ui.connection_arrows = self.connection_arrows;
+        graph_ui.debug_node_preview = self.debug_node_preview;
+        graph_ui.debug_render_timings = self.debug_render_timings;
+        graph_ui.ungroup_on_drag_out = self.ungroup_on_drag_out;
+        graph_ui.occupied_input_drop = self.occupied_input_drop;
+        graph_ui.zoom_sensitivity = if self.zoom_sensitivity.is_finite() {
+            self.zoom_sensitivity
+                .clamp(MIN_ZOOM_SENSITIVITY, MAX_ZOOM_SENSITIVITY)
+        } else {
+            1.0
+        };
+        graph_ui.node_drag_threshold = if self.node_drag_threshold.is_finite() {
+            self.node_drag_threshold.clamp(0.0, MAX_NODE_DRAG_THRESHOLD)
+        } else {
+            GraphUi::default().node_drag_threshold
+        };
+    }
+}
+
+pub fn render_menu(ui: &mut egui::Ui, graph_ui: &mut GraphUi) {
+    let mut preferences = UiPreferences::from_graph_ui(graph_ui);
+
+    egui::ComboBox::from_label("Double-click")
+        .selected_text(format!("{:?}", preferences.double_click_action))
+        .show_ui(ui, |ui| {
+            for action in [
+                DoubleClickAction::Focus,
+                DoubleClickAction::Rename,
+                DoubleClickAction::OpenSubgraph,
+                DoubleClickAction::None,
+            ] {
+                ui.selectable_value(
+                    &mut preferences.double_click_action,
+                    action,
+                    format!("{action:?}"),
+                );
+            }
+        });
+    ui.checkbox(&mut preferences.auto_height, "Auto node height");
+    ui.add_enabled(
+        !preferences.auto_height,
+        egui::Slider::new(
+            &mut preferences.max_visible_rows,
+            1..=MAX_VISIBLE_ROWS_LIMIT,
+        )
+        .text("Visible rows"),
+    );
+    ui.add(egui::Slider::new(&mut preferences.min_rows, 0..=MAX_MIN_ROWS).text("Minimum rows"))
+        .on_hover_text("At 0, nodes without ports shrink to just their title bar");
+    egui::ComboBox::from_label("Port alignment")
+        .selected_text(format!("{:?}", preferences.port_align))
+        .show_ui(ui, |ui| {
+            for align in [PortAlign::Top, PortAlign::Center, PortAlign::Bottom] {
+                ui.selectable_value(&mut preferences.port_align, align, format!("{align:?}"));
+            }
+        });
+    ui.add(
+        egui::Slider::new(
+            &mut preferences.zoom_sensitivity,
+            MIN_ZOOM_SENSITIVITY..=MAX_ZOOM_SENSITIVITY,
+        )
+        .text("Zoom sensitivity"),
+    );
+    ui.add(
+        egui::Slider::new(
+            &mut preferences.node_drag_threshold,
+            0.0..=MAX_NODE_DRAG_THRESHOLD,
+        )
+        .text("Node drag threshold (px)"),
+    )
+    .on_hover_text("How far a title bar press must move before the node follows");
+    egui::ComboBox::from_label("Drop on connected input")
+        .selected_text(format!("{:?}", preferences.occupied_input_drop))
+        .show_ui(ui, |ui| {
+            for mode in [OccupiedInputDrop::Replace, OccupiedInputDrop::Swap] {
+                ui.selectable_value(
+                    &mut preferences.occupied_input_drop,
+                    mode,
+                    format!("{mode:?}"),
+                );
+            }
+        });
+    ui.checkbox(&mut preferences.connections_on_top, "Wires over nodes");
+    ui.checkbox(&mut preferences.connection_arrows, "Arrowheads on wires");
+    ui.checkbox(
+        &mut preferences.ungroup_on_drag_out,
+        "Dragging a node out leaves its group",
+    );
+    ui.checkbox(
+        &mut preferences.debug_node_preview,
+        "Debug: Shift-hover shows node JSON",
+    );
+    ui.checkbox(
+        &mut preferences.debug_render_timings,
+        "Debug: render timings",
+    );
+    ui.separator();
+    if ui.button("Reset preferences").clicked() {
+        preferences = UiPreferences::default();
+    }
+
+    preferences.apply(graph_ui);
+}
+
+#[test]
+fn wire_style_is_remembered() {
+    let mut graph_ui = GraphUi::default();
+    graph_ui.connection_style = ConnectionStyle::Orthogonal;
+    let stored = serde_json::to_string(&UiPreferences::from_graph_ui(&graph_ui))
+        .expect("preferences serialize");
+
+    let mut restored = GraphUi::default();
+    serde_json::from_str::<UiPreferences>(&stored)
+        .expect("preferences deserialize")
+        .apply(&mut restored);
+    assert_eq!(restored.connection_style, ConnectionStyle::Orthogonal);
+
+    let older: UiPreferences = serde_json::from_str("{}").expect("missing fields default");
+    assert_eq!(older.connection_style, ConnectionStyle::Bezier);
+}
+
+#[test]
+fn preferences_restore_clamp_and_reset() {
+    let mut graph_ui = GraphUi::default();
+    graph_ui.double_click_action = DoubleClickAction::Rename;
+    graph_ui.node_layout.port_align = PortAlign::Bottom;
+    graph_ui.zoom_sensitivity = 2.5;
+    graph_ui.connections_on_top = true;
+    graph_ui.occupied_input_drop = OccupiedInputDrop::Swap;
+    let saved = UiPreferences::from_graph_ui(&graph_ui);
+    let stored = serde_json::to_string(&saved).expect("preferences serialize");
+
+    let mut relaunched = GraphUi::default();
+    serde_json::from_str::<UiPreferences>(&stored)
+        .expect("preferences deserialize")
+        .apply(&mut relaunched);
+    assert_eq!(UiPreferences::from_graph_ui(&relaunched), saved);
+
+    // A hand-edited storage file cannot push settings out of range.
+    let edited = UiPreferences {
+        max_visible_rows: 0,
+        min_rows: 100,
+        zoom_sensitivity: f32::NAN,
+        node_drag_threshold: 1000.0,
+        ..saved
+    };
+    edited.apply(&mut relaunched);
+    assert_eq!(relaunched.node_layout.max_visible_rows, 1);
+    assert_eq!(relaunched.node_layout.min_rows, MAX_MIN_ROWS);
+    assert_eq!(relaunched.zoom_sensitivity, 1.0);
+    assert_eq!(relaunched.node_drag_threshold, MAX_NODE_DRAG_THRESHOLD);
+
+    // "Reset preferences" applies the defaults.
+    UiPreferences::default().apply(&mut relaunched);
+    assert_eq!(
+        UiPreferences::from_graph_ui(&relaunched),
+        UiPreferences::from_graph_ui(&GraphUi::default())
+    );
+}
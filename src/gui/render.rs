@@ -1,6 +1,8 @@
 use eframe::egui;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::marker::PhantomData;
+use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::{
@@ -48,18 +50,110 @@ impl<'a> PainterRef<'a> {
     }
 }
 
+/// Maps a graph-space position to screen space, for a view whose graph origin is drawn at
+/// `origin` (the view rect's min corner plus pan) and scaled by `scale` (zoom).
+pub fn graph_to_screen(origin: egui::Pos2, scale: f32, pos: egui::Pos2) -> egui::Pos2 {
+    assert!(scale > 0.0, "graph scale must be positive");
+    origin + pos.to_vec2() * scale
+}
+
+/// Inverse of [`graph_to_screen`].
+pub fn screen_to_graph(origin: egui::Pos2, scale: f32, pos: egui::Pos2) -> egui::Pos2 {
+    assert!(scale > 0.0, "graph scale must be positive");
+    egui::Pos2::ZERO + (pos - origin) / scale
+}
+
+/// Where nodes land on screen: graph space maps through `origin` and `scale`, while
+/// screen-pinned nodes sit at a fixed offset from `view_min` (the view rect's top-left),
+/// unaffected by pan and zoom.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewTransform {
+    pub origin: egui::Pos2,
+    pub view_min: egui::Pos2,
+    pub scale: f32,
+}
+
+impl ViewTransform {
+    pub fn new(view_min: egui::Pos2, pan: egui::Vec2, scale: f32) -> Self {
+        Self {
+            origin: view_min + pan,
+            view_min,
+            scale,
+        }
+    }
+
+    /// Zoom 1 with both origins at zero, for measuring node sizes in graph units.
+    pub fn unit() -> Self {
+        Self::new(egui::Pos2::ZERO, egui::Vec2::ZERO, 1.0)
+    }
+
+    /// Screen position of the node's top-left corner.
+    pub fn node_min(&self, node: &model::Node) -> egui::Pos2 {
+        if node.screen_pinned {
+            self.view_min + node.pos.to_vec2()
+        } else {
+            graph_to_screen(self.origin, self.scale, node.pos)
+        }
+    }
+}
+
+type TextKey = (String, egui::FontFamily, u32);
+
+/// Per-frame cache of unwrapped text layouts, shared by width measurement and label drawing.
+/// All text in a frame uses one color, so it is fixed per cache rather than part of the key.
+#[derive(Debug)]
+pub struct TextLayoutCache {
+    color: egui::Color32,
+    galleys: RefCell<HashMap<TextKey, Arc<egui::Galley>>>,
+    layout_count: Cell<usize>,
+}
+
+impl TextLayoutCache {
+    pub fn new(color: egui::Color32) -> Self {
+        Self {
+            color,
+            galleys: RefCell::new(HashMap::new()),
+            layout_count: Cell::new(0),
+        }
+    }
+
+    pub fn galley(
+        &self,
+        painter: &egui::Painter,
+        text: &str,
+        font: &egui::FontId,
+    ) -> Arc<egui::Galley> {
+        assert!(font.size.is_finite(), "font size must be finite");
+        let key = (text.to_string(), font.family.clone(), font.size.to_bits());
+        if let Some(galley) = self.galleys.borrow().get(&key) {
+            return Arc::clone(galley);
+        }
+
+        let galley = painter.layout_no_wrap(text.to_string(), font.clone(), self.color);
+        self.layout_count.set(self.layout_count.get() + 1);
+        self.galleys.borrow_mut().insert(key, Arc::clone(&galley));
+        galley
+    }
+
+    /// Number of layouts performed, i.e. cache misses.
+    pub fn layout_count(&self) -> usize {
+        self.layout_count.get()
+    }
+}
+
 #[derive(Debug)]
 pub struct RenderContext<'a> {
     ui: UiRef<'a>,
     painter: PainterRef<'a>,
     pub rect: egui::Rect,
-    pub origin: egui::Pos2,
+    pub view: ViewTransform,
     pub layout: node::NodeLayout,
     pub heading_font: egui::FontId,
     pub body_font: egui::FontId,
     pub text_color: egui::Color32,
-    pub style: GraphStyle,
+    pub style: &'a GraphStyle,
     pub node_widths: HashMap<Uuid, f32>,
+    pub text_cache: TextLayoutCache,
     pub port_radius: f32,
     pub scale: f32,
 }
@@ -70,43 +164,46 @@ impl<'a> RenderContext<'a> {
         painter: &'a egui::Painter,
         rect: egui::Rect,
         graph: &model::Graph,
+        base_layout: &node::NodeLayout,
+        style: &'a GraphStyle,
     ) -> Self {
         assert!(graph.zoom.is_finite(), "graph zoom must be finite");
         assert!(graph.zoom > 0.0, "graph zoom must be positive");
         assert!(graph.pan.x.is_finite(), "graph pan x must be finite");
         assert!(graph.pan.y.is_finite(), "graph pan y must be finite");
 
-        let layout = node::NodeLayout::default().scaled(graph.zoom);
+        let layout = base_layout.scaled(graph.zoom);
         layout.assert_valid();
 
-        let heading_font = node::scaled_font(ui, egui::TextStyle::Heading, graph.zoom);
+        let heading_font = node::header_font(ui, &layout, graph.zoom);
         let body_font = node::scaled_font(ui, egui::TextStyle::Body, graph.zoom);
         let text_color = ui.visuals().text_color();
-        let style = GraphStyle::new(ui, graph.zoom);
-        style.validate();
+        assert_eq!(style.scale, graph.zoom, "graph style must match graph zoom");
+        let text_cache = TextLayoutCache::new(text_color);
         let node_widths = node::compute_node_widths(
             painter,
+            &text_cache,
             graph,
             &layout,
             &heading_font,
             &body_font,
-            text_color,
-            &style,
+            style,
         );
-        let origin = rect.min + graph.pan;
+        let view = ViewTransform::new(rect.min, graph.pan, graph.zoom);
         let port_radius = node::port_radius_for_scale(graph.zoom);
 
         Self {
             ui: UiRef::new(ui),
             painter: PainterRef::new(painter),
             rect,
-            origin,
+            view,
             layout,
             heading_font,
             body_font,
             text_color,
             style,
             node_widths,
+            text_cache,
             port_radius,
             scale: graph.zoom,
         }
@@ -120,6 +217,28 @@ impl<'a> RenderContext<'a> {
         self.painter.get()
     }
 
+    /// Draws unwrapped text anchored at `pos`, reusing the layout from width measurement.
+    pub fn text(
+        &self,
+        pos: egui::Pos2,
+        anchor: egui::Align2,
+        text: &str,
+        font: &egui::FontId,
+    ) -> egui::Rect {
+        let galley = self.text_cache.galley(self.painter(), text, font);
+        let rect = anchor.anchor_size(pos, galley.size());
+        self.painter().galley(rect.min, galley, self.text_color);
+        rect
+    }
+
+    pub fn graph_to_screen(&self, pos: egui::Pos2) -> egui::Pos2 {
+        graph_to_screen(self.view.origin, self.scale, pos)
+    }
+
+    pub fn screen_to_graph(&self, pos: egui::Pos2) -> egui::Pos2 {
+        screen_to_graph(self.view.origin, self.scale, pos)
+    }
+
     pub fn node_width(&self, node_id: Uuid) -> f32 {
         self.node_widths
             .get(&node_id)
@@ -128,13 +247,7 @@ impl<'a> RenderContext<'a> {
     }
 
     pub fn node_rect(&self, node: &model::Node) -> egui::Rect {
-        node::node_rect_for_graph(
-            self.origin,
-            node,
-            self.scale,
-            &self.layout,
-            self.node_width(node.id),
-        )
+        node::node_rect_for_graph(self.view, node, &self.layout, self.node_width(node.id))
     }
 }
 
@@ -143,3 +256,68 @@ pub trait WidgetRenderer {
 
     fn render(&mut self, ctx: &RenderContext, graph: &mut model::Graph) -> Self::Output;
 }
+
+#[test]
+fn screen_graph_transform_roundtrip() {
+    let origin = egui::pos2(37.5, -12.25);
+    for scale in [0.2, 1.0, 3.7] {
+        for pos in [
+            egui::pos2(0.0, 0.0),
+            egui::pos2(-140.0, 88.5),
+            egui::pos2(1234.5, -987.25),
+        ] {
+            let screen = graph_to_screen(origin, scale, pos);
+            let back = screen_to_graph(origin, scale, screen);
+            assert!(
+                (back - pos).length() < 1e-3,
+                "{pos:?} at {scale} -> {back:?}"
+            );
+        }
+    }
+    assert_eq!(
+        graph_to_screen(origin, 2.0, egui::pos2(10.0, 5.0)),
+        egui::pos2(57.5, -2.25)
+    );
+}
+
+#[test]
+fn pinned_nodes_ignore_pan_and_zoom() {
+    let mut node = model::Node {
+        pos: egui::pos2(40.0, 25.0),
+        ..model::Node::default()
+    };
+    let view_min = egui::pos2(100.0, 50.0);
+    let near = ViewTransform::new(view_min, egui::vec2(-300.0, 80.0), 2.5);
+    let far = ViewTransform::new(view_min, egui::vec2(900.0, -40.0), 0.4);
+    assert_ne!(near.node_min(&node), far.node_min(&node));
+
+    node.screen_pinned = true;
+    assert_eq!(near.node_min(&node), egui::pos2(140.0, 75.0));
+    assert_eq!(far.node_min(&node), near.node_min(&node));
+}
+
+#[test]
+fn labels_reuse_measured_text_layouts() {
+    let graph = model::Graph::test_graph();
+    let order: Vec<usize> = (0..graph.nodes.len()).collect();
+    let ctx = egui::Context::default();
+    let _ = ctx.run(egui::RawInput::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let painter = ui.painter().clone();
+            let style = GraphStyle::new(ui.visuals(), graph.zoom);
+            let render_ctx = RenderContext::new(
+                ui,
+                &painter,
+                ui.max_rect(),
+                &graph,
+                &node::NodeLayout::default(),
+                &style,
+            );
+            let measured = render_ctx.text_cache.layout_count();
+            assert!(measured > 0);
+
+            node::render_node_labels(&render_ctx, &graph, &order);
+            assert_eq!(render_ctx.text_cache.layout_count(), measured);
+        });
+    });
+}
@@ -0,0 +1,80 @@
+use eframe::egui;
+use uuid::Uuid;
+
+use crate::{gui::graph::GraphUi, model};
+
+#[derive(Debug, Default)]
+pub struct NodeSearch {
+    query: String,
+    replacement: String,
+    replace_ports: bool,
+    replace_options: model::ReplaceOptions,
+    last_replace_count: Option<usize>,
+}
+
+impl NodeSearch {
+    pub fn reset(&mut self) {
+        self.query.clear();
+        self.replacement.clear();
+        self.last_replace_count = None;
+    }
+
+    pub fn render(&mut self, ui: &mut egui::Ui, graph: &mut model::Graph, graph_ui: &mut GraphUi) {
+        ui.heading("Search");
+        ui.add(egui::TextEdit::singleline(&mut self.query).hint_text("Node name"));
+
+        let matches = graph.find_nodes_by_name(&self.query);
+        ui.label(format!("{} matching nodes", matches.len()));
+        ui.separator();
+
+        let mut focus_request: Option<Uuid> = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for node_id in &matches {
+                let node = graph
+                    .nodes
+                    .iter()
+                    .find(|node| node.id == *node_id)
+                    .expect("search match must reference an existing node");
+                let selected = graph.selected_nodes.contains(&node.id);
+                if ui.selectable_label(selected, &node.name).clicked() {
+                    focus_request = Some(node.id);
+                }
+            }
+        });
+
+        if let Some(node_id) = focus_request {
+            graph_ui.focus_node(graph, node_id);
+        }
+
+        ui.separator();
+        ui.add_enabled_ui(!graph_ui.read_only, |ui| self.render_replace(ui, graph));
+    }
+
+    fn render_replace(&mut self, ui: &mut egui::Ui, graph: &mut model::Graph) {
+        ui.heading("Replace");
+        ui.add(egui::TextEdit::singleline(&mut self.replacement).hint_text("Replace with"));
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.replace_ports, "Ports")
+                .on_hover_text("Replace in input and output names instead of node names");
+            ui.checkbox(&mut self.replace_options.match_case, "Match case");
+            ui.checkbox(&mut self.replace_options.whole_name, "Whole name");
+        });
+
+        let find = self.query.trim();
+        if ui
+            .add_enabled(!find.is_empty(), egui::Button::new("Replace all"))
+            .clicked()
+        {
+            let count = if self.replace_ports {
+                graph.rename_ports_matching(find, &self.replacement, self.replace_options)
+            } else {
+                graph.rename_nodes_matching(find, &self.replacement, self.replace_options)
+            };
+            self.last_replace_count = Some(count);
+        }
+        if let Some(count) = self.last_replace_count {
+            let target = if self.replace_ports { "ports" } else { "nodes" };
+            ui.label(format!("Renamed {count} {target}"));
+        }
+    }
+}
@@ -1,9 +1,12 @@
 use eframe::egui;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{gui::node, model};
 
 #[derive(Debug, Clone)]
 pub struct GraphStyle {
     pub scale: f32,
-    pub header_text_offset: f32,
     pub cache_button_width_factor: f32,
     pub cache_button_vertical_pad_factor: f32,
     pub cache_button_text_pad_factor: f32,
@@ -15,33 +18,84 @@ pub struct GraphStyle {
     pub output_port_color: egui::Color32,
     pub input_hover_color: egui::Color32,
     pub output_hover_color: egui::Color32,
+    /// When set, output ports and their wires take `palette[output_index % len]` instead
+    /// of `output_port_color` / `connection_stroke`'s color; see `OUTPUT_INDEX_PALETTE`.
+    pub output_palette: Option<Vec<egui::Color32>>,
+    /// Port fill per `data_type`; ports of other types keep the input/output colors.
+    /// Defaults to `DEFAULT_TYPE_COLORS`.
+    pub type_colors: HashMap<String, egui::Color32>,
+    /// Scales the bezier control offset: below 1 straightens wires, above 1 loops them.
+    pub curve_tension: f32,
+    /// Where the vertical leg of a step-routed wire (`ConnectionStyle::Orthogonal`) sits
+    /// between its output (0) and input (1).
+    pub orthogonal_mid_fraction: f32,
+    /// Distance from a port's center to where its wires start, along the port's exit
+    /// direction (outputs to the right, inputs to the left); 0 starts wires at the center.
+    pub wire_port_offset: f32,
+    /// Below this zoom, port labels are culled and each side shows a port count badge.
+    pub label_lod_zoom: f32,
+    pub badge_fill: egui::Color32,
     pub connection_stroke: egui::Stroke,
     pub connection_highlight_stroke: egui::Stroke,
+    pub flow_highlight_stroke: egui::Stroke,
+    /// Wires into or out of the selected node.
+    pub selected_connection_stroke: egui::Stroke,
+    /// The wire a dragged node will be spliced into when released.
+    pub splice_connection_stroke: egui::Stroke,
     pub temp_connection_stroke: egui::Stroke,
+    /// Drag preview over a full input, where the drop will replace an existing wire.
+    pub replace_connection_stroke: egui::Stroke,
+    pub invalid_connection_stroke: egui::Stroke,
+    pub invalid_port_color: egui::Color32,
     pub breaker_stroke: egui::Stroke,
+    /// Shift-drag selection rectangle.
+    pub marquee_fill: egui::Color32,
+    pub marquee_stroke: egui::Stroke,
+    /// Length and base width of the arrowhead at each wire's input end.
+    pub connection_arrow_size: f32,
+    /// Draws every wire over a wider translucent copy of itself to soften aliased edges.
+    pub feather_wires: bool,
+    /// Width of the feather stroke relative to the wire stroke; at least 1.
+    pub feather_width_factor: f32,
     pub dotted_color: egui::Color32,
     pub dotted_base_spacing: f32,
     pub dotted_radius_base: f32,
     pub dotted_radius_min: f32,
     pub dotted_radius_max: f32,
     pub node_fill: egui::Color32,
+    /// Header tint per `Node::category`; nodes without a listed category keep
+    /// `node_fill`. Defaults to `DEFAULT_CATEGORY_COLORS`.
+    pub category_colors: HashMap<String, egui::Color32>,
     pub node_stroke: egui::Stroke,
     pub selected_stroke: egui::Stroke,
+    /// Keyboard focus ring drawn around the focused node.
+    pub focus_stroke: egui::Stroke,
+    /// Gap between a group's frame and its outermost members.
+    pub group_padding: f32,
+    /// Height of the draggable title bar above a group's members.
+    pub group_title_height: f32,
+    pub group_fill: egui::Color32,
+    pub group_stroke: egui::Stroke,
+    /// Canvas comments: the box behind their text.
+    pub comment_fill: egui::Color32,
+    pub comment_stroke: egui::Stroke,
+    /// Node borders in the health view, by `model::NodeHealth`.
+    pub health_ok_color: egui::Color32,
+    pub health_warning_color: egui::Color32,
+    pub health_error_color: egui::Color32,
 }
 
 impl GraphStyle {
-    pub fn new(ui: &egui::Ui, scale: f32) -> Self {
+    pub fn new(visuals: &egui::Visuals, scale: f32) -> Self {
         assert!(scale.is_finite(), "style scale must be finite");
         assert!(scale > 0.0, "style scale must be positive");
 
-        let visuals = ui.visuals();
         let node_stroke = visuals.widgets.noninteractive.bg_stroke;
         let selected_stroke =
             egui::Stroke::new(node_stroke.width.max(2.0), visuals.selection.stroke.color);
 
         Self {
             scale,
-            header_text_offset: 4.0 * scale,
             cache_button_width_factor: 3.1,
             cache_button_vertical_pad_factor: 0.4,
             cache_button_text_pad_factor: 0.5,
@@ -53,31 +107,151 @@ impl GraphStyle {
             output_port_color: egui::Color32::from_rgb(70, 200, 200),
             input_hover_color: egui::Color32::from_rgb(120, 190, 255),
             output_hover_color: egui::Color32::from_rgb(110, 230, 210),
+            output_palette: None,
+            type_colors: Self::DEFAULT_TYPE_COLORS
+                .iter()
+                .map(|(data_type, color)| (data_type.to_string(), *color))
+                .collect(),
+            curve_tension: 1.0,
+            orthogonal_mid_fraction: 0.5,
+            wire_port_offset: node::port_radius_for_scale(scale) + scale,
+            label_lod_zoom: 0.5,
+            badge_fill: egui::Color32::from_rgb(70, 80, 100),
             connection_stroke: egui::Stroke::new(2.0, egui::Color32::from_rgb(80, 160, 255)),
             connection_highlight_stroke: egui::Stroke::new(
                 2.5,
                 egui::Color32::from_rgb(255, 90, 90),
             ),
+            flow_highlight_stroke: egui::Stroke::new(2.5, egui::Color32::from_rgb(120, 220, 140)),
+            selected_connection_stroke: egui::Stroke::new(
+                2.5,
+                egui::Color32::from_rgb(150, 200, 255),
+            ),
+            splice_connection_stroke: egui::Stroke::new(3.0, egui::Color32::from_rgb(240, 205, 90)),
             temp_connection_stroke: egui::Stroke::new(2.0, egui::Color32::from_rgb(170, 200, 255)),
+            replace_connection_stroke: egui::Stroke::new(
+                2.5,
+                egui::Color32::from_rgb(240, 150, 60),
+            ),
+            invalid_connection_stroke: egui::Stroke::new(2.0, egui::Color32::from_rgb(235, 80, 80)),
+            invalid_port_color: egui::Color32::from_rgb(235, 80, 80),
             breaker_stroke: egui::Stroke::new(2.5, egui::Color32::from_rgb(255, 120, 120)),
+            marquee_fill: visuals.selection.bg_fill.gamma_multiply(0.15),
+            marquee_stroke: egui::Stroke::new(1.0, visuals.selection.stroke.color),
+            connection_arrow_size: 7.0 * scale,
+            feather_wires: false,
+            feather_width_factor: 2.0,
             dotted_color: egui::Color32::from_rgba_unmultiplied(255, 255, 255, 28),
             dotted_base_spacing: 24.0,
             dotted_radius_base: 1.2,
             dotted_radius_min: 0.6,
             dotted_radius_max: 2.4,
             node_fill: visuals.widgets.noninteractive.bg_fill,
+            category_colors: Self::DEFAULT_CATEGORY_COLORS
+                .iter()
+                .map(|(category, color)| (category.to_string(), *color))
+                .collect(),
             node_stroke,
             selected_stroke,
+            focus_stroke: egui::Stroke::new(1.5, visuals.selection.stroke.color),
+            group_padding: 16.0 * scale,
+            group_title_height: 22.0 * scale,
+            group_fill: visuals.selection.bg_fill.gamma_multiply(0.12),
+            group_stroke: egui::Stroke::new(1.0, visuals.selection.bg_fill.gamma_multiply(0.6)),
+            comment_fill: visuals.warn_fg_color.gamma_multiply(0.1),
+            comment_stroke: egui::Stroke::new(1.0, visuals.warn_fg_color.gamma_multiply(0.5)),
+            health_ok_color: egui::Color32::from_rgb(110, 200, 120),
+            health_warning_color: egui::Color32::from_rgb(235, 190, 70),
+            health_error_color: egui::Color32::from_rgb(235, 80, 80),
+        }
+    }
+
+    /// Distinct hues for `output_palette`, ordered so neighbouring outputs contrast.
+    pub const OUTPUT_INDEX_PALETTE: [egui::Color32; 6] = [
+        egui::Color32::from_rgb(70, 200, 200),
+        egui::Color32::from_rgb(240, 170, 70),
+        egui::Color32::from_rgb(190, 120, 240),
+        egui::Color32::from_rgb(120, 210, 110),
+        egui::Color32::from_rgb(240, 110, 160),
+        egui::Color32::from_rgb(230, 220, 90),
+    ];
+
+    /// Port colors for common value types.
+    pub const DEFAULT_TYPE_COLORS: [(&str, egui::Color32); 3] = [
+        ("number", egui::Color32::from_rgb(80, 140, 255)),
+        ("string", egui::Color32::from_rgb(110, 200, 110)),
+        ("bool", egui::Color32::from_rgb(180, 110, 235)),
+    ];
+
+    /// Muted header tints for common node libraries, readable under light text.
+    pub const DEFAULT_CATEGORY_COLORS: [(&str, egui::Color32); 5] = [
+        ("math", egui::Color32::from_rgb(70, 62, 120)),
+        ("io", egui::Color32::from_rgb(38, 96, 92)),
+        ("logic", egui::Color32::from_rgb(112, 72, 40)),
+        ("text", egui::Color32::from_rgb(62, 92, 48)),
+        ("image", egui::Color32::from_rgb(112, 50, 82)),
+    ];
+
+    /// Header fill for `node`'s category, if it has a listed one.
+    pub fn header_fill(&self, node: &model::Node) -> Option<egui::Color32> {
+        node.category()
+            .and_then(|category| self.category_colors.get(category))
+            .copied()
+    }
+
+    pub fn output_color(&self, output_index: usize) -> egui::Color32 {
+        match &self.output_palette {
+            Some(palette) => palette[output_index % palette.len()],
+            None => self.output_port_color,
+        }
+    }
+
+    /// Fill for a port of `data_type`: its `type_colors` entry, else `fallback` (the
+    /// side's usual color).
+    pub fn port_color(&self, data_type: &str, fallback: egui::Color32) -> egui::Color32 {
+        self.type_colors.get(data_type).copied().unwrap_or(fallback)
+    }
+
+    /// Translucent underlay for `stroke` when `feather_wires` is on.
+    pub fn feather_stroke(&self, stroke: egui::Stroke) -> Option<egui::Stroke> {
+        self.feather_wires.then(|| {
+            egui::Stroke::new(
+                stroke.width * self.feather_width_factor,
+                stroke.color.gamma_multiply(0.25),
+            )
+        })
+    }
+
+    /// Health-view node border: `node_stroke`'s width (at least 2) in the health color.
+    pub fn health_stroke(&self, health: model::NodeHealth) -> egui::Stroke {
+        let color = match health {
+            model::NodeHealth::Ok => self.health_ok_color,
+            model::NodeHealth::Warning => self.health_warning_color,
+            model::NodeHealth::Error => self.health_error_color,
+        };
+        egui::Stroke::new(self.node_stroke.width.max(2.0), color)
+    }
+
+    /// Regular (non-highlighted) stroke for a wire leaving output `output_index`.
+    pub fn output_connection_stroke(&self, output_index: usize) -> egui::Stroke {
+        match &self.output_palette {
+            Some(_) => egui::Stroke::new(
+                self.connection_stroke.width,
+                self.output_color(output_index),
+            ),
+            None => self.connection_stroke,
         }
     }
 
     pub fn validate(&self) {
         assert!(self.scale.is_finite(), "style scale must be finite");
-        assert!(self.scale > 0.0, "style scale must be positive");
         assert!(
-            self.header_text_offset.is_finite(),
-            "header text offset must be finite"
+            self.output_palette
+                .as_ref()
+                .is_none_or(|palette| !palette.is_empty()),
+            "output palette must not be empty"
         );
+        assert!(self.scale > 0.0, "style scale must be positive");
         assert!(
             self.cache_button_width_factor.is_finite(),
             "cache button width factor must be finite"
@@ -150,6 +324,23 @@ impl GraphStyle {
             self.dotted_radius_max >= self.dotted_radius_min,
             "dot radius max must be >= min"
         );
+        assert!(
+            self.curve_tension.is_finite(),
+            "curve tension must be finite"
+        );
+        assert!(self.curve_tension > 0.0, "curve tension must be positive");
+        assert!(
+            (0.0..=1.0).contains(&self.orthogonal_mid_fraction),
+            "orthogonal mid fraction must be within 0..=1"
+        );
+        assert!(
+            self.wire_port_offset.is_finite(),
+            "wire port offset must be finite"
+        );
+        assert!(
+            self.wire_port_offset >= 0.0,
+            "wire port offset must be non-negative"
+        );
         assert!(
             self.connection_stroke.width.is_finite(),
             "connection stroke width must be finite"
@@ -166,6 +357,30 @@ impl GraphStyle {
             self.connection_highlight_stroke.width >= 0.0,
             "connection highlight stroke width must be non-negative"
         );
+        assert!(
+            self.splice_connection_stroke.width.is_finite(),
+            "splice connection stroke width must be finite"
+        );
+        assert!(
+            self.splice_connection_stroke.width >= 0.0,
+            "splice connection stroke width must be non-negative"
+        );
+        assert!(
+            self.flow_highlight_stroke.width.is_finite(),
+            "flow highlight stroke width must be finite"
+        );
+        assert!(
+            self.flow_highlight_stroke.width >= 0.0,
+            "flow highlight stroke width must be non-negative"
+        );
+        assert!(
+            self.selected_connection_stroke.width.is_finite(),
+            "selected connection stroke width must be finite"
+        );
+        assert!(
+            self.selected_connection_stroke.width >= 0.0,
+            "selected connection stroke width must be non-negative"
+        );
         assert!(
             self.temp_connection_stroke.width.is_finite(),
             "temp connection stroke width must be finite"
@@ -174,6 +389,14 @@ impl GraphStyle {
             self.temp_connection_stroke.width >= 0.0,
             "temp connection stroke width must be non-negative"
         );
+        assert!(
+            self.invalid_connection_stroke.width.is_finite(),
+            "invalid connection stroke width must be finite"
+        );
+        assert!(
+            self.invalid_connection_stroke.width >= 0.0,
+            "invalid connection stroke width must be non-negative"
+        );
         assert!(
             self.breaker_stroke.width.is_finite(),
             "breaker stroke width must be finite"
@@ -182,5 +405,307 @@ impl GraphStyle {
             self.breaker_stroke.width >= 0.0,
             "breaker stroke width must be non-negative"
         );
+        assert!(
+            self.connection_arrow_size.is_finite() && self.connection_arrow_size > 0.0,
+            "connection arrow size must be finite and positive"
+        );
+        assert!(
+            self.feather_width_factor.is_finite(),
+            "feather width factor must be finite"
+        );
+        assert!(
+            self.feather_width_factor >= 1.0,
+            "feather width factor must be at least 1"
+        );
+        assert!(
+            self.label_lod_zoom.is_finite(),
+            "label lod zoom must be finite"
+        );
+        assert!(self.label_lod_zoom > 0.0, "label lod zoom must be positive");
+        assert!(
+            self.marquee_stroke.width.is_finite(),
+            "marquee stroke width must be finite"
+        );
+        assert!(
+            self.marquee_stroke.width >= 0.0,
+            "marquee stroke width must be non-negative"
+        );
+        assert!(
+            self.focus_stroke.width.is_finite(),
+            "focus stroke width must be finite"
+        );
+        assert!(
+            self.focus_stroke.width >= 0.0,
+            "focus stroke width must be non-negative"
+        );
+        assert!(
+            self.group_padding.is_finite(),
+            "group padding must be finite"
+        );
+        assert!(
+            self.group_padding >= 0.0,
+            "group padding must be non-negative"
+        );
+        assert!(
+            self.group_title_height.is_finite(),
+            "group title height must be finite"
+        );
+        assert!(
+            self.group_title_height >= 0.0,
+            "group title height must be non-negative"
+        );
+    }
+}
+
+/// Starts from `GraphStyle::new` and overrides individual fields, e.g.
+/// `GraphStyleBuilder::new(visuals, zoom).node_fill(fill).curve_tension(0.6).build()`.
+/// Values are taken as given, so size fields that `new` scales by zoom (such as
+/// `status_dot_radius`) should be passed already scaled.
+#[derive(Debug, Clone)]
+pub struct GraphStyleBuilder {
+    style: GraphStyle,
+}
+
+impl GraphStyleBuilder {
+    pub fn new(visuals: &egui::Visuals, scale: f32) -> Self {
+        Self {
+            style: GraphStyle::new(visuals, scale),
+        }
+    }
+
+    /// Validates and returns the style; panics on invalid overrides like `validate`.
+    pub fn build(self) -> GraphStyle {
+        self.style.validate();
+        self.style
+    }
+}
+
+macro_rules! style_setters {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        impl GraphStyleBuilder {
+            $(
+                pub fn $field(mut self, value: $ty) -> Self {
+                    self.style.$field = value;
+                    self
+                }
+            )*
+        }
+    };
+}
+
+style_setters! {
+    cache_button_width_factor: f32,
+    cache_button_vertical_pad_factor: f32,
+    cache_button_text_pad_factor: f32,
+    cache_active_color: egui::Color32,
+    cache_checked_text_color: egui::Color32,
+    status_dot_radius: f32,
+    status_item_gap: f32,
+    input_port_color: egui::Color32,
+    output_port_color: egui::Color32,
+    input_hover_color: egui::Color32,
+    output_hover_color: egui::Color32,
+    output_palette: Option<Vec<egui::Color32>>,
+    type_colors: HashMap<String, egui::Color32>,
+    curve_tension: f32,
+    orthogonal_mid_fraction: f32,
+    wire_port_offset: f32,
+    label_lod_zoom: f32,
+    badge_fill: egui::Color32,
+    connection_stroke: egui::Stroke,
+    connection_highlight_stroke: egui::Stroke,
+    flow_highlight_stroke: egui::Stroke,
+    selected_connection_stroke: egui::Stroke,
+    splice_connection_stroke: egui::Stroke,
+    temp_connection_stroke: egui::Stroke,
+    replace_connection_stroke: egui::Stroke,
+    invalid_connection_stroke: egui::Stroke,
+    invalid_port_color: egui::Color32,
+    breaker_stroke: egui::Stroke,
+    marquee_fill: egui::Color32,
+    marquee_stroke: egui::Stroke,
+    connection_arrow_size: f32,
+    feather_wires: bool,
+    feather_width_factor: f32,
+    dotted_color: egui::Color32,
+    dotted_base_spacing: f32,
+    dotted_radius_base: f32,
+    dotted_radius_min: f32,
+    dotted_radius_max: f32,
+    node_fill: egui::Color32,
+    category_colors: HashMap<String, egui::Color32>,
+    node_stroke: egui::Stroke,
+    selected_stroke: egui::Stroke,
+    focus_stroke: egui::Stroke,
+    group_padding: f32,
+    group_title_height: f32,
+    group_fill: egui::Color32,
+    group_stroke: egui::Stroke,
+    comment_fill: egui::Color32,
+    comment_stroke: egui::Stroke,
+    health_ok_color: egui::Color32,
+    health_warning_color: egui::Color32,
+    health_error_color: egui::Color32,
+}
+
+/// Applied to every style the cache builds, so embedder overrides survive zoom and
+/// visuals changes. A closure, so it can capture runtime values such as a theme color.
+pub type StyleOverrides = Arc<dyn Fn(GraphStyleBuilder) -> GraphStyleBuilder + Send + Sync>;
+
+/// Keeps the last built `GraphStyle`; it is rebuilt (and validated) only when the zoom,
+/// the egui visuals, or the overrides change.
+#[derive(Default)]
+pub struct GraphStyleCache {
+    cached: Option<(egui::Visuals, GraphStyle)>,
+    overrides: Option<StyleOverrides>,
+    rebuild_count: usize,
+}
+
+impl std::fmt::Debug for GraphStyleCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GraphStyleCache")
+            .field("cached", &self.cached)
+            .field("overrides", &self.overrides.is_some())
+            .field("rebuild_count", &self.rebuild_count)
+            .finish()
+    }
+}
+
+impl GraphStyleCache {
+    pub fn set_overrides(&mut self, overrides: Option<StyleOverrides>) {
+        self.overrides = overrides;
+        self.cached = None;
+    }
+
+    pub fn get(&mut self, visuals: &egui::Visuals, scale: f32) -> &GraphStyle {
+        let stale = self.cached.as_ref().is_none_or(|(cached_visuals, style)| {
+            style.scale != scale || cached_visuals != visuals
+        });
+        if stale {
+            let mut builder = GraphStyleBuilder::new(visuals, scale);
+            if let Some(overrides) = &self.overrides {
+                builder = overrides(builder);
+            }
+            self.cached = Some((visuals.clone(), builder.build()));
+            self.rebuild_count += 1;
+        }
+
+        &self.cached.as_ref().expect("style cache must be filled").1
     }
+
+    pub fn rebuild_count(&self) -> usize {
+        self.rebuild_count
+    }
+}
+
+#[test]
+fn style_cache_rebuilds_only_on_change() {
+    let mut cache = GraphStyleCache::default();
+    let dark = egui::Visuals::dark();
+
+    cache.get(&dark, 1.0);
+    cache.get(&dark, 1.0);
+    assert_eq!(cache.rebuild_count(), 1);
+
+    assert_eq!(cache.get(&dark, 1.5).scale, 1.5);
+    cache.get(&dark, 1.5);
+    assert_eq!(cache.rebuild_count(), 2);
+
+    cache.get(&egui::Visuals::light(), 1.5);
+    assert_eq!(cache.rebuild_count(), 3);
+}
+
+#[test]
+fn output_palette_colors_ports_and_wires_by_index() {
+    let dark = egui::Visuals::dark();
+    let plain = GraphStyle::new(&dark, 1.0);
+    assert_eq!(plain.output_color(3), plain.output_port_color);
+    assert_eq!(plain.output_connection_stroke(3), plain.connection_stroke);
+
+    let palette = GraphStyle::OUTPUT_INDEX_PALETTE;
+    let style = GraphStyleBuilder::new(&dark, 1.0)
+        .output_palette(Some(palette.to_vec()))
+        .build();
+    assert_eq!(style.output_color(1), palette[1]);
+    assert_eq!(style.output_color(palette.len() + 2), palette[2]);
+    let stroke = style.output_connection_stroke(1);
+    assert_eq!(stroke.color, palette[1]);
+    assert_eq!(stroke.width, style.connection_stroke.width);
+}
+
+#[test]
+fn category_colors_tint_namespaced_headers() {
+    let dark = egui::Visuals::dark();
+    let graph = model::Graph::test_graph();
+    let (value, sum) = (&graph.nodes[0], &graph.nodes[2]);
+    let style = GraphStyle::new(&dark, 1.0);
+    assert_eq!(style.header_fill(value), None);
+    assert_eq!(
+        style.header_fill(sum),
+        Some(GraphStyle::DEFAULT_CATEGORY_COLORS[0].1)
+    );
+
+    let teal = egui::Color32::from_rgb(20, 120, 120);
+    let style = GraphStyleBuilder::new(&dark, 1.0)
+        .category_colors(HashMap::from([("math".to_string(), teal)]))
+        .build();
+    assert_eq!(style.header_fill(sum), Some(teal));
+}
+
+#[test]
+fn type_colors_fill_typed_ports() {
+    let dark = egui::Visuals::dark();
+    let style = GraphStyle::new(&dark, 1.0);
+    let fallback = style.input_port_color;
+    for (data_type, color) in GraphStyle::DEFAULT_TYPE_COLORS {
+        assert_eq!(style.port_color(data_type, fallback), color);
+    }
+    assert_eq!(style.port_color("", fallback), fallback, "untyped");
+    assert_eq!(style.port_color("image", fallback), fallback, "unlisted");
+
+    let orange = egui::Color32::from_rgb(240, 150, 60);
+    let style = GraphStyleBuilder::new(&dark, 1.0)
+        .type_colors(HashMap::from([("image".to_string(), orange)]))
+        .build();
+    assert_eq!(style.port_color("image", fallback), orange);
+    assert_eq!(style.port_color("number", fallback), fallback);
+}
+
+#[test]
+fn feather_stroke_widens_and_fades_the_wire() {
+    let dark = egui::Visuals::dark();
+    let stroke = egui::Stroke::new(2.0, egui::Color32::from_rgb(200, 100, 50));
+    assert_eq!(GraphStyle::new(&dark, 1.0).feather_stroke(stroke), None);
+
+    let style = GraphStyleBuilder::new(&dark, 1.0)
+        .feather_wires(true)
+        .feather_width_factor(3.0)
+        .build();
+    let feather = style.feather_stroke(stroke).expect("feathering is enabled");
+    assert_eq!(feather.width, 6.0);
+    assert!(feather.color.a() < stroke.color.a());
+}
+
+#[test]
+fn style_overrides_survive_rebuilds() {
+    let fill = egui::Color32::from_rgb(10, 20, 30);
+    let dark = egui::Visuals::dark();
+    let built = GraphStyleBuilder::new(&dark, 2.0)
+        .node_fill(fill)
+        .curve_tension(0.5)
+        .build();
+    assert_eq!(built.node_fill, fill);
+    assert_eq!(built.curve_tension, 0.5);
+    assert_eq!(built.scale, 2.0);
+    assert_eq!(
+        built.input_port_color,
+        GraphStyle::new(&dark, 2.0).input_port_color
+    );
+
+    let mut cache = GraphStyleCache::default();
+    cache.get(&dark, 1.0);
+    cache.set_overrides(Some(Arc::new(move |builder| builder.node_fill(fill))));
+    assert_eq!(cache.get(&dark, 1.0).node_fill, fill);
+    assert_eq!(cache.get(&dark, 3.0).node_fill, fill);
+    assert_eq!(cache.rebuild_count(), 3);
 }
@@ -7,19 +7,36 @@ mod model;
 
 use anyhow::Result;
 use eframe::{NativeOptions, egui};
+use serde::{Deserialize, Serialize};
 use std::ffi::OsStr;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+/// eframe storage key of `WindowState`.
+const WINDOW_STORAGE_KEY: &str = "window_state";
+
+/// Window state saved by `App::save` next to eframe's own window geometry. eframe's
+/// `persist_window` restores size and position, but a maximized flag applied before the
+/// window is mapped is dropped on some platforms (e.g. Wayland), so it is re-sent as a
+/// viewport command once the app starts.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+struct WindowState {
+    maximized: bool,
+}
+
 fn main() -> Result<()> {
     init::init()?;
 
     let app_icon = load_window_icon();
     let options = NativeOptions {
         renderer: eframe::Renderer::Wgpu,
+        // Initial size for the first launch; afterwards eframe restores the persisted
+        // window size and position, and `WindowState` the maximized state.
         viewport: egui::ViewportBuilder::default()
             .with_icon(app_icon)
-            .with_app_id("scenarium-egui"),
+            .with_app_id("scenarium-egui")
+            .with_inner_size([1280.0, 800.0]),
+        persist_window: true,
         ..Default::default()
     };
 
@@ -29,7 +46,26 @@ fn main() -> Result<()> {
         Box::new(|cc| {
             configure_fonts(&cc.egui_ctx);
             configure_visuals(&cc.egui_ctx);
-            Ok(Box::new(ScenariumApp::default()))
+            let mut app = ScenariumApp::default();
+            if let Some(preferences) = cc.storage.and_then(|storage| {
+                eframe::get_value::<gui::preferences::UiPreferences>(
+                    storage,
+                    gui::preferences::STORAGE_KEY,
+                )
+            }) {
+                preferences.apply(&mut app.graph_ui);
+            }
+            if let Some(window) = cc
+                .storage
+                .and_then(|storage| eframe::get_value::<WindowState>(storage, WINDOW_STORAGE_KEY))
+            {
+                app.window = window;
+                if window.maximized {
+                    cc.egui_ctx
+                        .send_viewport_cmd(egui::ViewportCommand::Maximized(true));
+                }
+            }
+            Ok(Box::new(app))
         }),
     )?;
 
@@ -70,6 +106,11 @@ struct ScenariumApp {
     graph_path: PathBuf,
     last_status: Option<String>,
     graph_ui: gui::graph::GraphUi,
+    node_search: gui::search::NodeSearch,
+    graph_check: gui::diagnostics::GraphCheckReport,
+    validation_status: gui::diagnostics::ValidationStatus,
+    /// Tracked every frame so `App::save` records the state the window closed in.
+    window: WindowState,
 }
 
 impl Default for ScenariumApp {
@@ -85,6 +126,10 @@ impl Default for ScenariumApp {
             graph_path,
             last_status: None,
             graph_ui: gui::graph::GraphUi::default(),
+            node_search: gui::search::NodeSearch::default(),
+            graph_check: gui::diagnostics::GraphCheckReport::default(),
+            validation_status: gui::diagnostics::ValidationStatus::default(),
+            window: WindowState::default(),
         }
     }
 }
@@ -109,6 +154,7 @@ impl ScenariumApp {
             .expect("graph should be valid before storing in app state");
         self.graph = graph;
         self.graph_ui.reset();
+        self.graph_check.close();
         self.set_status(status);
     }
 
@@ -122,7 +168,10 @@ impl ScenariumApp {
             self.graph_path.extension().is_some(),
             "graph save path must include a file extension"
         );
-        match self.graph.serialize_to_file(&self.graph_path) {
+        match self
+            .graph
+            .serialize_to_file(&self.graph_path, model::JsonOptions::default())
+        {
             Ok(()) => self.set_status(format!("Saved graph to {}", self.graph_path.display())),
             Err(err) => self.set_status(format!("Save failed: {err}")),
         }
@@ -134,14 +183,26 @@ impl ScenariumApp {
             "graph load path must include a file extension"
         );
         match model::Graph::deserialize_from_file(&self.graph_path) {
-            Ok(graph) => self.set_graph(
-                graph,
-                format!("Loaded graph from {}", self.graph_path.display()),
-            ),
+            Ok(mut graph) => {
+                let mut status = format!("Loaded graph from {}", self.graph_path.display());
+                if graph.exceeds_coord_limit() {
+                    graph.recenter();
+                    status.push_str(" (recentered far-off nodes)");
+                }
+                self.set_graph(graph, status);
+            }
             Err(err) => self.set_status(format!("Load failed: {err}")),
         }
     }
 
+    fn new_from_template(&mut self, template: model::GraphTemplate) {
+        let graph = template.build();
+        self.set_graph(
+            graph,
+            format!("Created graph from template \"{}\"", template.label()),
+        );
+    }
+
     fn test_graph(&mut self) {
         let graph = model::Graph::test_graph();
         self.set_graph(graph, "Loaded sample test graph");
@@ -149,7 +210,16 @@ impl ScenariumApp {
 }
 
 impl eframe::App for ScenariumApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        let preferences = gui::preferences::UiPreferences::from_graph_ui(&self.graph_ui);
+        eframe::set_value(storage, gui::preferences::STORAGE_KEY, &preferences);
+        eframe::set_value(storage, WINDOW_STORAGE_KEY, &self.window);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if let Some(maximized) = ctx.input(|input| input.viewport().maximized) {
+            self.window.maximized = maximized;
+        }
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::MenuBar::new().ui(ui, |ui| {
                 {
@@ -175,6 +245,15 @@ impl eframe::App for ScenariumApp {
                         self.new_graph();
                         ui.close();
                     }
+                    ui.menu_button("New from template", |ui| {
+                        for template in model::GraphTemplate::ALL {
+                            let button = ui.button(template.label());
+                            if button.on_hover_text(template.description()).clicked() {
+                                self.new_from_template(template);
+                                ui.close();
+                            }
+                        }
+                    });
                     if ui.button("Save").clicked() {
                         self.save_graph();
                         ui.close();
@@ -188,6 +267,45 @@ impl eframe::App for ScenariumApp {
                         ui.close();
                     }
                 });
+                ui.menu_button("Graph", |ui| {
+                    {
+                        let style = ui.style_mut();
+                        style.spacing.button_padding = egui::vec2(16.0, 5.0);
+                        style.spacing.item_spacing = egui::vec2(10.0, 5.0);
+                        style
+                            .text_styles
+                            .entry(egui::TextStyle::Button)
+                            .and_modify(|font| font.size = 18.0);
+                    }
+                    if ui.button("Check graph").clicked() {
+                        self.graph_check.run(ui, &self.graph, &self.graph_ui);
+                        ui.close();
+                    }
+                    ui.checkbox(&mut self.graph_ui.read_only, "Read-only")
+                        .on_hover_text(
+                            "Browse without editing; pan, zoom, and selection still work",
+                        );
+                    if ui
+                        .add_enabled(
+                            !self.graph_ui.read_only,
+                            egui::Button::new("Recenter graph"),
+                        )
+                        .clicked()
+                    {
+                        self.graph.push_undo_checkpoint();
+                        self.graph.recenter();
+                        self.set_status("Recentered graph");
+                        ui.close();
+                    }
+                });
+                ui.menu_button("Preferences", |ui| {
+                    gui::preferences::render_menu(ui, &mut self.graph_ui);
+                });
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if self.validation_status.render_chip(ui, &self.graph) {
+                        self.graph_check.run(ui, &self.graph, &self.graph_ui);
+                    }
+                });
             });
         });
 
@@ -197,8 +315,24 @@ impl eframe::App for ScenariumApp {
             }
         });
 
+        egui::SidePanel::right("search_panel")
+            .resizable(true)
+            .show(ctx, |ui| {
+                gui::inspector::render(ui, &mut self.graph, self.graph_ui.read_only);
+                ui.separator();
+                self.node_search
+                    .render(ui, &mut self.graph, &mut self.graph_ui);
+            });
+
         egui::CentralPanel::default().show(ctx, |ui| {
             self.graph_ui.render(ui, &mut self.graph);
         });
+        if let Some(notice) = self.graph_ui.take_notice() {
+            self.set_status(notice);
+            ctx.request_repaint();
+        }
+
+        self.graph_check
+            .render(ctx, &mut self.graph, &mut self.graph_ui);
     }
 }
@@ -1,6 +1,7 @@
 use anyhow::{Result, anyhow, bail};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::path::Path;
 use uuid::Uuid;
 
@@ -10,43 +11,524 @@ pub enum GraphFormat {
     Yaml,
     Json,
 }
+/// Output format of `Graph::export_connections`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionExportFormat {
+    Csv,
+    Json,
+}
+
+/// What `Graph::serialize_compact` does with node UUIDs once they are replaced by
+/// their index in `nodes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompactIds {
+    /// Write an index → UUID table so `deserialize_compact` restores the original ids.
+    KeepTable,
+    /// Drop the UUIDs; `deserialize_compact` generates fresh ones.
+    Regenerate,
+}
+
+/// Schema version named in the header comment of TOML/YAML files.
+pub const GRAPH_SCHEMA_VERSION: u32 = 1;
+
+/// Top-level key of the id table in compact files.
+const COMPACT_ID_TABLE: &str = "node_id_table";
+
+/// One exported wire; node columns hold names, suffixed with the id when the name is shared.
+#[derive(Debug, Serialize)]
+struct ConnectionRow {
+    source_node: String,
+    source_output: String,
+    target_node: String,
+    target_input: String,
+}
+
+/// Prefix of the clipboard line carrying the native JSON node fragment.
+const CLIPBOARD_MARKER: &str = "scenarium-nodes:";
+
+/// Native clipboard payload and `Graph::copy_nodes` output: copies of the copied nodes.
+#[derive(Debug, Serialize, Deserialize)]
+struct ClipboardFragment {
+    nodes: Vec<Node>,
+}
+
+impl ClipboardFragment {
+    /// Rejects payloads that would corrupt the graph: a node id listed twice, an input
+    /// wired to the same output twice, or a wire to an output a payload node lacks. With
+    /// `internal_only`, wires to nodes outside the payload are rejected too.
+    fn validate(&self, internal_only: bool) -> Result<()> {
+        let mut output_counts = HashMap::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            if output_counts.insert(node.id, node.outputs.len()).is_some() {
+                bail!("node payload lists node {} twice", node.id);
+            }
+        }
+        for node in &self.nodes {
+            for input in &node.inputs {
+                let mut sources = HashSet::new();
+                for connection in &input.connections {
+                    if !sources.insert((connection.node_id, connection.output_index)) {
+                        bail!(
+                            "node payload wires input {} of {} twice",
+                            input.name,
+                            node.id
+                        );
+                    }
+                    match output_counts.get(&connection.node_id) {
+                        Some(count) if connection.output_index >= *count => bail!(
+                            "node payload wires missing output {} of {}",
+                            connection.output_index,
+                            connection.node_id
+                        ),
+                        None if internal_only => bail!(
+                            "node payload wires node {}, which it does not contain",
+                            connection.node_id
+                        ),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How far `Graph::duplicate_node` moves the copy from its source, in the source's units.
+pub const DUPLICATE_OFFSET: egui::Vec2 = egui::vec2(24.0, 24.0);
+
+/// Node coordinates are kept within ±`RECOMMENDED_COORD_LIMIT` graph units; beyond that
+/// f32 precision visibly degrades wire and text placement. `Graph::recenter` fixes graphs
+/// that drift past it.
+pub const RECOMMENDED_COORD_LIMIT: f32 = 100_000.0;
+
+/// Where `Graph::add_node` places a new node; node order drives draw order and serialization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InsertOrder {
+    #[default]
+    Append,
+    /// Before the first node with a greater name (after equal names).
+    SortedByName,
+    /// Before the first node with a greater id.
+    SortedById,
+}
+
+/// Matching rules shared by the graph's find-and-replace operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReplaceOptions {
+    pub match_case: bool,
+    /// Only replace names equal to the search text instead of every occurrence inside them.
+    pub whole_name: bool,
+}
+
+/// JSON layout for `Graph::serialize`; other formats ignore it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct JsonOptions {
+    /// Single-line output for storage or the network instead of the indented default.
+    pub compact: bool,
+}
+
+/// Built-in starter graphs offered by File → "New from template".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphTemplate {
+    Sample,
+    Chain,
+    FanOut,
+}
+
+/// A creatable node kind: name, ports, and whether it is a terminal node.
+/// `NodeTemplateRegistry::instantiate` builds disconnected nodes from it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeTemplate {
+    pub name: String,
+    pub inputs: Vec<PortTemplate>,
+    pub outputs: Vec<PortTemplate>,
+    pub terminal: bool,
+}
+
+/// A port of a `NodeTemplate` or node spec. In a spec it is either a bare name or
+/// `{"name": ..., "data_type": ...}`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "SpecPort")]
+pub struct PortTemplate {
+    pub name: String,
+    /// Becomes the port's `data_type`; empty means untyped.
+    pub data_type: String,
+}
+
+impl PortTemplate {
+    pub fn new(name: &str, data_type: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+        }
+    }
+}
+
+impl From<&str> for PortTemplate {
+    fn from(name: &str) -> Self {
+        Self::new(name, "")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SpecPort {
+    Name(String),
+    Typed(TypedSpecPort),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TypedSpecPort {
+    name: String,
+    #[serde(default)]
+    data_type: String,
+}
+
+impl From<SpecPort> for PortTemplate {
+    fn from(port: SpecPort) -> Self {
+        match port {
+            SpecPort::Name(name) => Self {
+                name,
+                data_type: String::new(),
+            },
+            SpecPort::Typed(port) => Self {
+                name: port.name,
+                data_type: port.data_type,
+            },
+        }
+    }
+}
+
+/// The node kinds the canvas "Add node" menu offers, in menu order. `Default` mirrors
+/// the nodes of `Graph::test_graph`; embedders `register` their own.
+#[derive(Debug, Clone)]
+pub struct NodeTemplateRegistry {
+    templates: Vec<NodeTemplate>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Graph {
     pub id: Uuid,
     pub nodes: Vec<Node>,
     pub pan: egui::Vec2,
     pub zoom: f32,
-    pub selected_node_id: Option<Uuid>,
+    /// Saved in id order, so equal selections serialize (and snapshot) identically.
+    #[serde(default, serialize_with = "serialize_sorted_ids")]
+    pub selected_nodes: HashSet<Uuid>,
+    /// Free-standing text boxes on the canvas.
+    #[serde(default)]
+    pub comments: Vec<Comment>,
+    /// Named sets of nodes.
+    #[serde(default)]
+    pub groups: Vec<Group>,
+    /// Undo/redo checkpoints; never saved.
+    #[serde(skip)]
+    pub history: UndoStack,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Most checkpoints `UndoStack` keeps; pushing more drops the oldest.
+pub const UNDO_DEPTH: usize = 100;
+
+/// Whole-graph snapshots (`Graph::undo_snapshot`) to step back and forth through.
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    undo: VecDeque<String>,
+    redo: Vec<String>,
+}
+
+impl UndoStack {
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// A new edit branches the history, so anything undone can no longer be redone. A
+    /// repeat of the newest checkpoint is no new edit and keeps the redo list.
+    fn push(&mut self, snapshot: String) {
+        if self.undo.back() == Some(&snapshot) {
+            return;
+        }
+        self.redo.clear();
+        self.undo.push_back(snapshot);
+        if self.undo.len() > UNDO_DEPTH {
+            self.undo.pop_front();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: Uuid,
+    pub text: String,
+    pub pos: egui::Pos2,
+    pub size: egui::Vec2,
+    /// Picture shown in the box, e.g. pasted from the clipboard.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<CommentImage>,
+}
+
+/// Widest or tallest a pasted image comment starts out, in graph units.
+pub const COMMENT_IMAGE_MAX_SIDE: f32 = 480.0;
+
+/// An RGBA picture kept in the graph file as base64 PNG. Images are never edited in
+/// place, so `id` names the pixels and lets the GUI cache the decoded texture.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommentImage {
+    pub id: Uuid,
+    pub width: u32,
+    pub height: u32,
+    pub png_base64: String,
+}
+
+impl CommentImage {
+    /// Encodes `width * height` unmultiplied RGBA pixels.
+    pub fn from_rgba(width: usize, height: usize, rgba: &[u8]) -> Result<Self> {
+        use image::ImageEncoder;
+
+        if width == 0 || height == 0 {
+            bail!("image is empty");
+        }
+        if rgba.len() != width * height * 4 {
+            bail!("image has {} bytes, not {width}x{height} RGBA", rgba.len());
+        }
+        let (width, height) = (u32::try_from(width)?, u32::try_from(height)?);
+        let mut png = Vec::new();
+        image::codecs::png::PngEncoder::new(&mut png).write_image(
+            rgba,
+            width,
+            height,
+            image::ExtendedColorType::Rgba8,
+        )?;
+        Ok(Self {
+            id: Uuid::new_v4(),
+            width,
+            height,
+            png_base64: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, png),
+        })
+    }
+
+    /// Decodes the pixels back to unmultiplied RGBA, `width * height * 4` bytes.
+    pub fn to_rgba(&self) -> Result<Vec<u8>> {
+        let png =
+            base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &self.png_base64)?;
+        let decoded = image::load_from_memory_with_format(&png, image::ImageFormat::Png)?;
+        if (decoded.width(), decoded.height()) != (self.width, self.height) {
+            bail!(
+                "image is {}x{}, not the recorded {}x{}",
+                decoded.width(),
+                decoded.height(),
+                self.width,
+                self.height
+            );
+        }
+        Ok(decoded.to_rgba8().into_raw())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Group {
+    pub id: Uuid,
+    pub name: String,
+    pub node_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Node {
     pub id: Uuid,
     pub name: String,
     pub pos: egui::Pos2,
     pub inputs: Vec<Input>,
     pub outputs: Vec<Output>,
+    #[serde(default)]
     pub cache_output: bool,
+    #[serde(default)]
     pub has_cached_output: bool,
     // node has side effects, besides calculation it's output. e.g. saving re
+    #[serde(default)]
     pub terminal: bool,
+    /// First visible port row when the node layout clamps its height. View state like
+    /// the scroll of a list, so it is never saved (nor part of undo snapshots).
+    #[serde(skip)]
+    pub row_scroll: usize,
+    /// Manual width in graph units; never narrower than the auto-computed width.
+    #[serde(default)]
+    pub width_override: Option<f32>,
+    /// Free-form notes about what the node does; not part of layout.
+    #[serde(default)]
+    pub description: String,
+    /// HUD node: `pos` is a screen offset from the view's top-left corner instead of a
+    /// graph position, so the node ignores pan and zoom placement (its size still
+    /// follows zoom). Toggle with `Graph::set_screen_pinned` to keep it in place.
+    #[serde(default)]
+    pub screen_pinned: bool,
+    /// Muted node: drawn dimmed, and when resolving sources its first output stands in
+    /// for whatever feeds its first input (see `Graph::resolve_output`).
+    #[serde(default)]
+    pub bypass: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Connection {
     pub node_id: Uuid,
     pub output_index: usize,
+    /// Optional annotation drawn at the wire midpoint (e.g. units).
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Source output name recorded when the wire was made, so `diagnostics` can catch an
+    /// `output_index` that drifted to a different output after port edits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_name: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "InputFile")]
 pub struct Input {
     pub name: String,
-    pub connection: Option<Connection>,
+    /// Type of value this input accepts; empty means untyped. See `port_types_compatible`.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub data_type: String,
+    /// Every source merged into this input, in the order they were wired (fan-in).
+    pub connections: Vec<Connection>,
+    /// Caps `connections`; `Some(1)` keeps single-connection semantics, `None` allows any
+    /// number of sources.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_connections: Option<usize>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Input {
+    pub fn is_connected(&self) -> bool {
+        !self.connections.is_empty()
+    }
+
+    /// Whether another source would exceed `max_connections`.
+    pub fn is_full(&self) -> bool {
+        self.max_connections
+            .is_some_and(|max| self.connections.len() >= max)
+    }
+}
+
+/// On-disk shape of `Input`; files written before fan-in hold a single optional
+/// `connection` and no `connections`, which loads as a one-element list capped at one
+/// source, the single-connection behavior those files were saved with.
+#[derive(Debug, Deserialize)]
+struct InputFile {
+    name: String,
+    #[serde(default)]
+    data_type: String,
+    #[serde(default)]
+    connections: Option<Vec<Connection>>,
+    #[serde(default)]
+    connection: Option<Connection>,
+    #[serde(default)]
+    max_connections: Option<usize>,
+}
+
+impl From<InputFile> for Input {
+    fn from(file: InputFile) -> Self {
+        let legacy = file.connections.is_none();
+        let mut connections = file.connections.unwrap_or_default();
+        connections.extend(file.connection);
+        Self {
+            name: file.name,
+            data_type: file.data_type,
+            connections,
+            max_connections: file.max_connections.or(legacy.then_some(1)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Output {
     pub name: String,
+    /// Type of value this output produces; empty means untyped.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub data_type: String,
+    /// Value from the latest evaluation, shown as a preview beside the port; never saved
+    /// and cleared by `Graph::invalidate_outputs`.
+    #[serde(skip)]
+    pub last_value: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    pub node_id: Option<Uuid>,
+    pub message: String,
+}
+
+/// A connection whose recorded `output_name` resolves to a different output than its
+/// stored `output_index`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MisorderedConnection {
+    pub node_id: Uuid,
+    pub input_index: usize,
+    /// Position in the input's `connections`.
+    pub slot: usize,
+    pub output_index: usize,
+    pub named_index: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// Per-node summary for the health view, ordered from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NodeHealth {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// One finding of `Graph::diagnostics`; new checks append to the same list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub node_id: Option<Uuid>,
+    pub message: String,
+}
+
+/// A terminal node and what feeds each of its inputs; see `Graph::terminal_summary`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TerminalInfo {
+    pub node_id: Uuid,
+    pub name: String,
+    pub inputs: Vec<TerminalInput>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TerminalInput {
+    pub name: String,
+    /// One entry per resolvable wire into the input; empty when unconnected.
+    pub sources: Vec<ResolvedSource>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResolvedSource {
+    pub node_id: Uuid,
+    pub node_name: String,
+    pub output_name: String,
+}
+
+impl ValidationIssue {
+    fn graph(message: impl Into<String>) -> Self {
+        Self {
+            node_id: None,
+            message: message.into(),
+        }
+    }
+
+    fn node(node_id: Uuid, message: impl Into<String>) -> Self {
+        Self {
+            node_id: Some(node_id),
+            message: message.into(),
+        }
+    }
 }
 
 impl Default for Node {
@@ -63,7 +545,132 @@ impl Default for Node {
             cache_output: false,
             has_cached_output: false,
             terminal: false,
+            row_scroll: 0,
+            width_override: None,
+            description: String::new(),
+            screen_pinned: false,
+            bypass: false,
+        }
+    }
+}
+
+/// Quotes a CSV field when it contains a separator, quote, or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// `text` with matches of `find` replaced, or `None` when nothing changed.
+fn replace_matching(
+    text: &str,
+    find: &str,
+    replace: &str,
+    options: ReplaceOptions,
+) -> Option<String> {
+    if find.is_empty() {
+        return None;
+    }
+    let find_lower = find.to_lowercase();
+    let matches = |candidate: &str| {
+        if options.match_case {
+            candidate == find
+        } else {
+            candidate.to_lowercase() == find_lower
+        }
+    };
+
+    let result = if options.whole_name {
+        if !matches(text) {
+            return None;
+        }
+        replace.to_string()
+    } else {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(first) = rest.chars().next() {
+            // Shortest match starting here; lowercasing can change byte lengths, so every
+            // char boundary is a candidate end.
+            let match_end = rest
+                .char_indices()
+                .map(|(index, _)| index)
+                .skip(1)
+                .chain(std::iter::once(rest.len()))
+                .find(|&end| matches(&rest[..end]));
+            match match_end {
+                Some(end) => {
+                    result.push_str(replace);
+                    rest = &rest[end..];
+                }
+                None => {
+                    result.push(first);
+                    rest = &rest[first.len_utf8()..];
+                }
+            }
+        }
+        result
+    };
+
+    (result != text).then_some(result)
+}
+
+/// Compact node description accepted by `Node::from_spec`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NodeSpec {
+    name: String,
+    #[serde(default)]
+    inputs: Vec<PortTemplate>,
+    #[serde(default)]
+    outputs: Vec<PortTemplate>,
+    #[serde(default)]
+    terminal: bool,
+}
+
+impl Node {
+    /// Builds an unconnected node at the origin with a fresh id from JSON such as
+    /// `{"name": "sum", "inputs": ["a", "b"], "outputs": ["sum"], "terminal": false}`.
+    /// A port may also be `{"name": "a", "data_type": "number"}`.
+    pub fn from_spec(json: &str) -> Result<Node> {
+        let spec: NodeSpec =
+            serde_json::from_str(json).map_err(|err| anyhow!("invalid node spec: {err}"))?;
+        if spec.name.trim().is_empty() {
+            bail!("node spec name must not be empty");
+        }
+        for (kind, ports) in [("input", &spec.inputs), ("output", &spec.outputs)] {
+            if let Some(index) = ports.iter().position(|port| port.name.trim().is_empty()) {
+                bail!("node spec {kind} {index} has an empty name");
+            }
+        }
+
+        Ok(Node {
+            terminal: spec.terminal,
+            ..node_with_ports(&spec.name, egui::Pos2::ZERO, &spec.inputs, &spec.outputs)
+        })
+    }
+
+    /// The category prefix of a namespaced name such as `math(sum)`; `None` when the
+    /// name is not of the form `category(...)`.
+    pub fn category(&self) -> Option<&str> {
+        let (category, rest) = self.name.split_once('(')?;
+        let category = category.trim();
+        (!category.is_empty() && rest.ends_with(')')).then_some(category)
+    }
+
+    /// Pretty-printed JSON of this node alone, cut after `max_lines` lines with a note
+    /// of how many were left out.
+    pub fn json_preview(&self, max_lines: usize) -> String {
+        let json = serde_json::to_string_pretty(self).expect("node must serialize");
+        let total = json.lines().count();
+        if total <= max_lines {
+            return json;
         }
+        let mut preview: Vec<&str> = json.lines().take(max_lines).collect();
+        let more = format!("… {} more lines", total - max_lines);
+        preview.push(&more);
+        preview.join("\n")
     }
 }
 
@@ -74,115 +681,678 @@ impl Default for Graph {
             nodes: Vec::new(),
             pan: egui::Vec2::ZERO,
             zoom: 1.0,
-            selected_node_id: None,
+            selected_nodes: HashSet::new(),
+            comments: Vec::new(),
+            groups: Vec::new(),
+            history: UndoStack::default(),
         }
     }
 }
 
 impl Graph {
     pub fn validate(&self) -> Result<()> {
+        match self.validate_detailed().into_iter().next() {
+            Some(issue) => Err(anyhow!(issue.message)),
+            None => Ok(()),
+        }
+    }
+
+    /// Runs every validation check and collects all issues instead of stopping at the first.
+    pub fn validate_detailed(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+
         if !self.zoom.is_finite() || self.zoom <= 0.0 {
-            return Err(anyhow!("graph zoom must be finite and positive"));
+            issues.push(ValidationIssue::graph(
+                "graph zoom must be finite and positive",
+            ));
         }
         if !self.pan.x.is_finite() || !self.pan.y.is_finite() {
-            return Err(anyhow!("graph pan must be finite"));
+            issues.push(ValidationIssue::graph("graph pan must be finite"));
         }
 
         let mut output_counts = HashMap::new();
+        let mut output_types = HashMap::new();
         for node in &self.nodes {
             if !node.pos.x.is_finite() || !node.pos.y.is_finite() {
-                return Err(anyhow!("node position must be finite"));
+                issues.push(ValidationIssue::node(
+                    node.id,
+                    "node position must be finite",
+                ));
+            }
+            if node
+                .width_override
+                .is_some_and(|width| !width.is_finite() || width <= 0.0)
+            {
+                issues.push(ValidationIssue::node(
+                    node.id,
+                    "node width override must be finite and positive",
+                ));
             }
+            output_types.insert(node.id, &node.outputs);
             let prior = output_counts.insert(node.id, node.outputs.len());
             if prior.is_some() {
-                return Err(anyhow!("duplicate node id detected"));
+                issues.push(ValidationIssue::node(node.id, "duplicate node id detected"));
             }
         }
 
-        if let Some(selected_node_id) = self.selected_node_id
-            && !output_counts.contains_key(&selected_node_id)
+        if self
+            .selected_nodes
+            .iter()
+            .any(|selected| !output_counts.contains_key(selected))
         {
-            return Err(anyhow!("selected node id must exist in graph"));
+            issues.push(ValidationIssue::graph(
+                "selected node id must exist in graph",
+            ));
+        }
+
+        let mut comment_ids = HashSet::new();
+        for comment in &self.comments {
+            if !comment_ids.insert(comment.id) {
+                issues.push(ValidationIssue::graph("duplicate comment id detected"));
+            }
+            if !comment.pos.x.is_finite() || !comment.pos.y.is_finite() {
+                issues.push(ValidationIssue::graph("comment position must be finite"));
+            }
+            let size_valid = [comment.size.x, comment.size.y]
+                .iter()
+                .all(|extent| extent.is_finite() && *extent > 0.0);
+            if !size_valid {
+                issues.push(ValidationIssue::graph(
+                    "comment size must be finite and positive",
+                ));
+            }
+        }
+
+        let mut group_ids = HashSet::new();
+        for group in &self.groups {
+            if !group_ids.insert(group.id) {
+                issues.push(ValidationIssue::graph("duplicate group id detected"));
+            }
+            if group
+                .node_ids
+                .iter()
+                .any(|node_id| !output_counts.contains_key(node_id))
+            {
+                issues.push(ValidationIssue::graph("group references a missing node"));
+            }
         }
 
         for node in &self.nodes {
             for input in &node.inputs {
-                if let Some(connection) = &input.connection {
-                    let output_count = output_counts
-                        .get(&connection.node_id)
-                        .ok_or_else(|| anyhow!("connection references a missing node"))?;
-                    if connection.output_index >= *output_count {
-                        return Err(anyhow!("connection output index out of range"));
+                if input.max_connections == Some(0) {
+                    issues.push(ValidationIssue::node(
+                        node.id,
+                        "input max connections must be at least 1",
+                    ));
+                }
+                if input
+                    .max_connections
+                    .is_some_and(|max| input.connections.len() > max)
+                {
+                    issues.push(ValidationIssue::node(
+                        node.id,
+                        "input has more connections than its maximum",
+                    ));
+                }
+                let mut sources = HashSet::new();
+                for connection in &input.connections {
+                    if !sources.insert((connection.node_id, connection.output_index)) {
+                        issues.push(ValidationIssue::node(
+                            node.id,
+                            "input is connected to the same output twice",
+                        ));
+                    }
+                    match output_counts.get(&connection.node_id) {
+                        None => issues.push(ValidationIssue::node(
+                            node.id,
+                            "connection references a missing node",
+                        )),
+                        Some(output_count) if connection.output_index >= *output_count => issues
+                            .push(ValidationIssue::node(
+                                node.id,
+                                "connection output index out of range",
+                            )),
+                        Some(_) => {
+                            let output =
+                                &output_types[&connection.node_id][connection.output_index];
+                            if !port_types_compatible(&output.data_type, &input.data_type) {
+                                issues.push(ValidationIssue::node(
+                                    node.id,
+                                    format!(
+                                        "connection links incompatible port types: {} into {}",
+                                        output.data_type, input.data_type
+                                    ),
+                                ));
+                            }
+                        }
                     }
                 }
             }
         }
 
-        Ok(())
-    }
-
-    pub fn serialize(&self, format: GraphFormat) -> Result<String> {
-        self.validate()?;
-
-        match format {
-            GraphFormat::Json => serde_json::to_string_pretty(self).map_err(anyhow::Error::from),
-            GraphFormat::Yaml => serde_yml::to_string(self).map_err(anyhow::Error::from),
-            GraphFormat::Toml => toml::to_string(self).map_err(anyhow::Error::from),
+        if let Some(cycle) = self.find_cycle() {
+            issues.push(ValidationIssue::graph(cycle_message(&cycle)));
         }
+
+        issues
     }
 
-    pub fn deserialize(format: GraphFormat, input: &str) -> Result<Self> {
-        if input.trim().is_empty() {
-            bail!("graph input is empty");
+    /// The nodes along one cycle of input → source edges, each followed by a source it
+    /// reads from, or `None` for an acyclic graph. Depth-first from every node in
+    /// `nodes` order; connections to missing nodes are skipped.
+    fn find_cycle(&self) -> Option<Vec<Uuid>> {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        enum Visit {
+            OnPath,
+            Done,
         }
 
-        let graph = match format {
-            GraphFormat::Json => {
-                serde_json::from_str::<Graph>(input).map_err(anyhow::Error::from)?
+        fn visit(
+            node_id: Uuid,
+            sources: &HashMap<Uuid, Vec<Uuid>>,
+            visits: &mut HashMap<Uuid, Visit>,
+            path: &mut Vec<Uuid>,
+        ) -> Option<Vec<Uuid>> {
+            match visits.get(&node_id) {
+                Some(Visit::Done) => return None,
+                Some(Visit::OnPath) => {
+                    let start = path
+                        .iter()
+                        .position(|id| *id == node_id)
+                        .expect("a node on the path must be in it");
+                    return Some(path[start..].to_vec());
+                }
+                None => {}
             }
-            GraphFormat::Yaml => {
-                serde_yml::from_str::<Graph>(input).map_err(anyhow::Error::from)?
+            visits.insert(node_id, Visit::OnPath);
+            path.push(node_id);
+            for source in &sources[&node_id] {
+                if sources.contains_key(source)
+                    && let Some(cycle) = visit(*source, sources, visits, path)
+                {
+                    return Some(cycle);
+                }
             }
-            GraphFormat::Toml => toml::from_str::<Graph>(input).map_err(anyhow::Error::from)?,
-        };
-        graph.validate()?;
+            path.pop();
+            visits.insert(node_id, Visit::Done);
+            None
+        }
 
-        Ok(graph)
+        let sources: HashMap<Uuid, Vec<Uuid>> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let sources = node
+                    .inputs
+                    .iter()
+                    .flat_map(|input| &input.connections)
+                    .map(|connection| connection.node_id)
+                    .collect();
+                (node.id, sources)
+            })
+            .collect();
+        let mut visits = HashMap::new();
+        let mut path = Vec::new();
+        self.nodes
+            .iter()
+            .find_map(|node| visit(node.id, &sources, &mut visits, &mut path))
     }
 
-    pub fn serialize_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        let path = path.as_ref();
-        let format = GraphFormat::from_path(path)?;
-        let payload = self.serialize(format)?;
-        std::fs::write(path, payload).map_err(anyhow::Error::from)
+    /// Whether wiring an output of `source_id` into an input of `target_id` would close
+    /// a cycle: the target already feeds the source, or they are the same node.
+    pub fn connection_creates_cycle(&self, source_id: Uuid, target_id: Uuid) -> bool {
+        source_id == target_id || self.descendants(target_id).contains(&source_id)
     }
 
-    pub fn deserialize_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let path = path.as_ref();
-        let format = GraphFormat::from_path(path)?;
-        let payload = std::fs::read_to_string(path).map_err(anyhow::Error::from)?;
+    /// Hash of every field `validate_detailed` reads, so callers can cache its result
+    /// and revalidate only after an edit. Floats hash by bit pattern.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for value in [self.zoom, self.pan.x, self.pan.y] {
+            value.to_bits().hash(&mut hasher);
+        }
+        let mut selected: Vec<&Uuid> = self.selected_nodes.iter().collect();
+        selected.sort_unstable();
+        selected.hash(&mut hasher);
+        for node in &self.nodes {
+            node.id.hash(&mut hasher);
+            (node.pos.x.to_bits(), node.pos.y.to_bits()).hash(&mut hasher);
+            node.width_override.map(f32::to_bits).hash(&mut hasher);
+            (node.inputs.len(), node.outputs.len()).hash(&mut hasher);
+            for output in &node.outputs {
+                output.data_type.hash(&mut hasher);
+            }
+            for input in &node.inputs {
+                (
+                    &input.data_type,
+                    input.max_connections,
+                    input.connections.len(),
+                )
+                    .hash(&mut hasher);
+                for connection in &input.connections {
+                    (connection.node_id, connection.output_index).hash(&mut hasher);
+                }
+            }
+        }
+        for comment in &self.comments {
+            comment.id.hash(&mut hasher);
+            for value in [comment.pos.x, comment.pos.y, comment.size.x, comment.size.y] {
+                value.to_bits().hash(&mut hasher);
+            }
+        }
+        for group in &self.groups {
+            (group.id, &group.node_ids).hash(&mut hasher);
+        }
+        hasher.finish()
+    }
 
-        Self::deserialize(format, &payload)
+    /// Runs every available check: validation errors plus warnings for unreachable
+    /// nodes, duplicate port names, unconnected inputs, and nodes overlapping in
+    /// `node_rects` (graph-space rects measured by the GUI; see `overlapping_nodes`).
+    pub fn diagnostics(&self, node_rects: &HashMap<Uuid, egui::Rect>) -> Vec<Diagnostic> {
+        let mut diagnostics = self.layout_free_diagnostics();
+        let name = |node_id: Uuid| {
+            self.nodes
+                .iter()
+                .find(|node| node.id == node_id)
+                .map_or("", |node| node.name.as_str())
+        };
+        for (below, above) in self.overlapping_nodes(node_rects) {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                node_id: Some(above),
+                message: format!("node overlaps '{}'", name(below)),
+            });
+        }
+        diagnostics
     }
 
-    pub fn test_graph() -> Self {
-        let value_a_id = Uuid::new_v4();
-        let value_b_id = Uuid::new_v4();
-        let sum_id = Uuid::new_v4();
-        let divide_id = Uuid::new_v4();
-        let output_id = Uuid::new_v4();
+    /// Pairs of nodes whose rects in `node_rects` share some area (touching edges do
+    /// not count), as `(below, above)` in drawing order. Nodes without a rect are
+    /// skipped.
+    pub fn overlapping_nodes(&self, node_rects: &HashMap<Uuid, egui::Rect>) -> Vec<(Uuid, Uuid)> {
+        let rects: Vec<(Uuid, egui::Rect)> = self
+            .nodes
+            .iter()
+            .filter_map(|node| node_rects.get(&node.id).map(|rect| (node.id, *rect)))
+            .collect();
+        let mut pairs = Vec::new();
+        for (index, (below, below_rect)) in rects.iter().enumerate() {
+            for (above, above_rect) in &rects[index + 1..] {
+                let shared = below_rect.intersect(*above_rect);
+                if shared.width() > 0.0 && shared.height() > 0.0 {
+                    pairs.push((*below, *above));
+                }
+            }
+        }
+        pairs
+    }
 
-        let value_a = Node {
-            id: value_a_id,
-            name: "value_a".to_string(),
-            pos: egui::pos2(80.0, 120.0),
-            inputs: Vec::new(),
+    /// The checks that need no node sizes; `node_health` uses these alone.
+    fn layout_free_diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = self
+            .validate_detailed()
+            .into_iter()
+            .map(|issue| Diagnostic {
+                severity: Severity::Error,
+                node_id: issue.node_id,
+                message: issue.message,
+            })
+            .collect();
+
+        for node_id in self.unreachable_nodes() {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                node_id: Some(node_id),
+                message: "node does not feed any terminal node".to_string(),
+            });
+        }
+
+        for node in &self.nodes {
+            let mut input_names = HashSet::new();
+            for input in &node.inputs {
+                if !input_names.insert(input.name.as_str()) {
+                    diagnostics.push(Diagnostic {
+                        severity: Severity::Warning,
+                        node_id: Some(node.id),
+                        message: format!("duplicate input name '{}'", input.name),
+                    });
+                }
+                if !input.is_connected() {
+                    diagnostics.push(Diagnostic {
+                        severity: Severity::Warning,
+                        node_id: Some(node.id),
+                        message: format!("input '{}' is not connected", input.name),
+                    });
+                }
+            }
+            let mut output_names = HashSet::new();
+            for output in &node.outputs {
+                if !output_names.insert(output.name.as_str()) {
+                    diagnostics.push(Diagnostic {
+                        severity: Severity::Warning,
+                        node_id: Some(node.id),
+                        message: format!("duplicate output name '{}'", output.name),
+                    });
+                }
+            }
+        }
+
+        for misordered in self.misordered_connections() {
+            let node = self
+                .nodes
+                .iter()
+                .find(|node| node.id == misordered.node_id)
+                .expect("misordered connection must reference an existing node");
+            diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                node_id: Some(misordered.node_id),
+                message: format!(
+                    "input '{}' uses output {} but its recorded output name is at index {}",
+                    node.inputs[misordered.input_index].name,
+                    misordered.output_index,
+                    misordered.named_index
+                ),
+            });
+        }
+
+        diagnostics
+    }
+
+    /// Worst finding per node: validation errors and unreachable nodes are `Error`, any
+    /// other diagnostic warning is `Warning`, and every remaining node is `Ok`.
+    pub fn node_health(&self) -> HashMap<Uuid, NodeHealth> {
+        let mut health: HashMap<Uuid, NodeHealth> = self
+            .nodes
+            .iter()
+            .map(|node| (node.id, NodeHealth::Ok))
+            .collect();
+        let mut raise = |node_id: Uuid, level: NodeHealth| {
+            if let Some(current) = health.get_mut(&node_id) {
+                *current = (*current).max(level);
+            }
+        };
+        for diagnostic in self.layout_free_diagnostics() {
+            if let Some(node_id) = diagnostic.node_id {
+                raise(
+                    node_id,
+                    match diagnostic.severity {
+                        Severity::Warning => NodeHealth::Warning,
+                        Severity::Error => NodeHealth::Error,
+                    },
+                );
+            }
+        }
+        for node_id in self.unreachable_nodes() {
+            raise(node_id, NodeHealth::Error);
+        }
+        health
+    }
+
+    /// Connections whose recorded source output name now sits at a different index.
+    /// Connections without a recorded name, with a missing source, or whose name no longer
+    /// exists on the source are skipped.
+    pub fn misordered_connections(&self) -> Vec<MisorderedConnection> {
+        let mut result = Vec::new();
+        for node in &self.nodes {
+            for (input_index, input) in node.inputs.iter().enumerate() {
+                for (slot, connection) in input.connections.iter().enumerate() {
+                    let Some(output_name) = connection.output_name.as_deref() else {
+                        continue;
+                    };
+                    let Some(source) = self.nodes.iter().find(|node| node.id == connection.node_id)
+                    else {
+                        continue;
+                    };
+                    let stored_matches = source
+                        .outputs
+                        .get(connection.output_index)
+                        .is_some_and(|output| output.name == output_name);
+                    if stored_matches {
+                        continue;
+                    }
+                    let Some(named_index) = source
+                        .outputs
+                        .iter()
+                        .position(|output| output.name == output_name)
+                    else {
+                        continue;
+                    };
+                    result.push(MisorderedConnection {
+                        node_id: node.id,
+                        input_index,
+                        slot,
+                        output_index: connection.output_index,
+                        named_index,
+                    });
+                }
+            }
+        }
+        result
+    }
+
+    /// Points every misordered connection at the output its recorded name resolves to;
+    /// returns how many connections changed.
+    pub fn fix_connection_output_indices(&mut self) -> usize {
+        let misordered = self.misordered_connections();
+        for entry in &misordered {
+            let node = self
+                .nodes
+                .iter_mut()
+                .find(|node| node.id == entry.node_id)
+                .expect("misordered connection must reference an existing node");
+            let connection = node.inputs[entry.input_index]
+                .connections
+                .get_mut(entry.slot)
+                .expect("misordered connection must still be wired");
+            connection.output_index = entry.named_index;
+        }
+        self.debug_assert_connections_consistent();
+        misordered.len()
+    }
+
+    /// Nodes that are neither terminal nor upstream of a terminal node, so their
+    /// results are never used.
+    pub fn unreachable_nodes(&self) -> Vec<Uuid> {
+        let mut reachable = HashSet::new();
+        for node in self.nodes.iter().filter(|node| node.terminal) {
+            reachable.insert(node.id);
+            reachable.extend(self.ancestors(node.id));
+        }
+
+        self.nodes
+            .iter()
+            .map(|node| node.id)
+            .filter(|id| !reachable.contains(id))
+            .collect()
+    }
+
+    /// Debug-only invariant check for mutation paths: no input holds more than its
+    /// `max_connections`, and each connection points at an existing node and an
+    /// in-range output.
+    fn debug_assert_connections_consistent(&self) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        let output_counts: HashMap<_, _> = self
+            .nodes
+            .iter()
+            .map(|node| (node.id, node.outputs.len()))
+            .collect();
+        for node in &self.nodes {
+            for input in &node.inputs {
+                assert!(
+                    input
+                        .max_connections
+                        .is_none_or(|max| input.connections.len() <= max),
+                    "input must not exceed its max connections after mutation"
+                );
+                for connection in &input.connections {
+                    let output_count = output_counts
+                        .get(&connection.node_id)
+                        .expect("connection source node must exist after mutation");
+                    assert!(
+                        connection.output_index < *output_count,
+                        "connection output index must be in range after mutation"
+                    );
+                }
+            }
+        }
+    }
+
+    /// TOML and YAML output starts with a `# scenarium graph vN` comment for hand
+    /// editors; both parsers skip it on load. JSON has no comments and gets no header.
+    pub fn serialize(&self, format: GraphFormat, json: JsonOptions) -> Result<String> {
+        self.validate()?;
+
+        let header = format!("# scenarium graph v{GRAPH_SCHEMA_VERSION}\n");
+        match format {
+            GraphFormat::Json if json.compact => {
+                serde_json::to_string(self).map_err(anyhow::Error::from)
+            }
+            GraphFormat::Json => serde_json::to_string_pretty(self).map_err(anyhow::Error::from),
+            GraphFormat::Yaml => Ok(header + &serde_yml::to_string(self)?),
+            GraphFormat::Toml => Ok(header + &toml::to_string(self)?),
+        }
+    }
+
+    pub fn deserialize(format: GraphFormat, input: &str) -> Result<Self> {
+        if input.trim().is_empty() {
+            bail!("graph input is empty");
+        }
+
+        let graph = match format {
+            GraphFormat::Json => {
+                serde_json::from_str::<Graph>(input).map_err(anyhow::Error::from)?
+            }
+            GraphFormat::Yaml => {
+                serde_yml::from_str::<Graph>(input).map_err(anyhow::Error::from)?
+            }
+            GraphFormat::Toml => toml::from_str::<Graph>(input).map_err(anyhow::Error::from)?,
+        };
+        graph.validate()?;
+
+        Ok(graph)
+    }
+
+    pub fn serialize_to_file<P: AsRef<Path>>(&self, path: P, json: JsonOptions) -> Result<()> {
+        let path = path.as_ref();
+        let format = GraphFormat::from_path(path)?;
+        let payload = self.serialize(format, json)?;
+        std::fs::write(path, payload).map_err(anyhow::Error::from)
+    }
+
+    pub fn deserialize_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let format = GraphFormat::from_path(path)?;
+        let payload = std::fs::read_to_string(path).map_err(anyhow::Error::from)?;
+
+        Self::deserialize(format, &payload)
+    }
+
+    /// Serializes with every node id (in `nodes`, connections, groups, and the selection)
+    /// replaced by the node's index, which shrinks files considerably. Only readable by
+    /// `deserialize_compact`; canonical files keep using `serialize`.
+    pub fn serialize_compact(&self, format: GraphFormat, ids: CompactIds) -> Result<String> {
+        self.validate()?;
+
+        let table: Vec<Uuid> = self.nodes.iter().map(|node| node.id).collect();
+        let index_of: HashMap<Uuid, usize> = table
+            .iter()
+            .enumerate()
+            .map(|(index, id)| (*id, index))
+            .collect();
+        let mut value = serde_json::to_value(self)?;
+        remap_node_ids(&mut value, |id| {
+            let id: Uuid = serde_json::from_value(id.clone())?;
+            let index = index_of
+                .get(&id)
+                .ok_or_else(|| anyhow!("node id {id} is not in the graph"))?;
+            Ok(serde_json::Value::from(*index))
+        })?;
+        if ids == CompactIds::KeepTable {
+            value[COMPACT_ID_TABLE] = serde_json::to_value(table)?;
+        }
+
+        match format {
+            GraphFormat::Json => serde_json::to_string_pretty(&value).map_err(anyhow::Error::from),
+            GraphFormat::Yaml => serde_yml::to_string(&value).map_err(anyhow::Error::from),
+            GraphFormat::Toml => {
+                // TOML has no null; absent optional fields deserialize the same way.
+                strip_nulls(&mut value);
+                toml::to_string(&value).map_err(anyhow::Error::from)
+            }
+        }
+    }
+
+    /// Reads `serialize_compact` output, restoring node UUIDs from the id table or
+    /// generating fresh ones when the file has none.
+    pub fn deserialize_compact(format: GraphFormat, input: &str) -> Result<Self> {
+        if input.trim().is_empty() {
+            bail!("graph input is empty");
+        }
+
+        let mut value = match format {
+            GraphFormat::Json => serde_json::from_str::<serde_json::Value>(input)?,
+            GraphFormat::Yaml => serde_yml::from_str::<serde_json::Value>(input)?,
+            GraphFormat::Toml => toml::from_str::<serde_json::Value>(input)?,
+        };
+        let node_count = value["nodes"]
+            .as_array()
+            .ok_or_else(|| anyhow!("compact graph has no node list"))?
+            .len();
+        let table: Vec<Uuid> = match value
+            .as_object_mut()
+            .and_then(|object| object.remove(COMPACT_ID_TABLE))
+        {
+            Some(table) => serde_json::from_value(table)?,
+            None => (0..node_count).map(|_| Uuid::new_v4()).collect(),
+        };
+        if table.len() != node_count {
+            bail!(
+                "compact id table has {} entries for {node_count} nodes",
+                table.len()
+            );
+        }
+        remap_node_ids(&mut value, |index| {
+            let id = index
+                .as_u64()
+                .and_then(|index| table.get(usize::try_from(index).ok()?))
+                .ok_or_else(|| anyhow!("invalid compact node id {index}"))?;
+            Ok(serde_json::to_value(id)?)
+        })?;
+
+        let graph: Graph = serde_json::from_value(value)?;
+        graph.validate()?;
+
+        Ok(graph)
+    }
+
+    pub fn test_graph() -> Self {
+        let value_a_id = Uuid::new_v4();
+        let value_b_id = Uuid::new_v4();
+        let sum_id = Uuid::new_v4();
+        let divide_id = Uuid::new_v4();
+        let output_id = Uuid::new_v4();
+
+        let value_a = Node {
+            id: value_a_id,
+            name: "value_a".to_string(),
+            pos: egui::pos2(80.0, 120.0),
+            inputs: Vec::new(),
             outputs: vec![Output {
                 name: "value".to_string(),
+                data_type: String::new(),
+                last_value: None,
             }],
             cache_output: true,
             has_cached_output: true,
             terminal: false,
+            row_scroll: 0,
+            width_override: None,
+            description: String::new(),
+            screen_pinned: false,
+            bypass: false,
         };
 
         let value_b = Node {
@@ -192,10 +1362,17 @@ impl Graph {
             inputs: Vec::new(),
             outputs: vec![Output {
                 name: "value".to_string(),
+                data_type: String::new(),
+                last_value: None,
             }],
             cache_output: true,
             has_cached_output: true,
             terminal: false,
+            row_scroll: 0,
+            width_override: None,
+            description: String::new(),
+            screen_pinned: false,
+            bypass: false,
         };
 
         let sum = Node {
@@ -205,25 +1382,40 @@ impl Graph {
             inputs: vec![
                 Input {
                     name: "a".to_string(),
-                    connection: Some(Connection {
+                    data_type: String::new(),
+                    connections: vec![Connection {
                         node_id: value_a_id,
                         output_index: 0,
-                    }),
+                        label: None,
+                        output_name: None,
+                    }],
+                    max_connections: Some(1),
                 },
                 Input {
                     name: "b".to_string(),
-                    connection: Some(Connection {
+                    data_type: String::new(),
+                    connections: vec![Connection {
                         node_id: value_b_id,
                         output_index: 0,
-                    }),
+                        label: None,
+                        output_name: None,
+                    }],
+                    max_connections: Some(1),
                 },
             ],
             outputs: vec![Output {
                 name: "sum".to_string(),
+                data_type: String::new(),
+                last_value: None,
             }],
             cache_output: false,
             has_cached_output: false,
             terminal: false,
+            row_scroll: 0,
+            width_override: None,
+            description: "Adds both inputs.\nOutputs their sum.".to_string(),
+            screen_pinned: false,
+            bypass: false,
         };
 
         let divide = Node {
@@ -233,25 +1425,40 @@ impl Graph {
             inputs: vec![
                 Input {
                     name: "sum".to_string(),
-                    connection: Some(Connection {
+                    data_type: String::new(),
+                    connections: vec![Connection {
                         node_id: sum_id,
                         output_index: 0,
-                    }),
+                        label: Some("ratio".to_string()),
+                        output_name: Some("sum".to_string()),
+                    }],
+                    max_connections: Some(1),
                 },
                 Input {
                     name: "b".to_string(),
-                    connection: Some(Connection {
+                    data_type: String::new(),
+                    connections: vec![Connection {
                         node_id: value_b_id,
                         output_index: 0,
-                    }),
+                        label: None,
+                        output_name: None,
+                    }],
+                    max_connections: Some(1),
                 },
             ],
             outputs: vec![Output {
                 name: "divide".to_string(),
+                data_type: String::new(),
+                last_value: None,
             }],
             cache_output: false,
             has_cached_output: false,
             terminal: false,
+            row_scroll: 0,
+            width_override: None,
+            description: String::new(),
+            screen_pinned: false,
+            bypass: false,
         };
 
         let output = Node {
@@ -260,15 +1467,24 @@ impl Graph {
             pos: egui::pos2(800.0, 180.0),
             inputs: vec![Input {
                 name: "value".to_string(),
-                connection: Some(Connection {
+                data_type: String::new(),
+                connections: vec![Connection {
                     node_id: divide_id,
                     output_index: 0,
-                }),
+                    label: None,
+                    output_name: None,
+                }],
+                max_connections: Some(1),
             }],
             outputs: Vec::new(),
             cache_output: false,
             has_cached_output: false,
             terminal: true,
+            row_scroll: 0,
+            width_override: None,
+            description: String::new(),
+            screen_pinned: false,
+            bypass: false,
         };
 
         let graph = Self {
@@ -276,7 +1492,20 @@ impl Graph {
             nodes: vec![value_a, value_b, sum, divide, output],
             pan: egui::Vec2::ZERO,
             zoom: 1.0,
-            selected_node_id: None,
+            selected_nodes: HashSet::new(),
+            comments: vec![Comment {
+                id: Uuid::new_v4(),
+                text: "Inputs".to_string(),
+                pos: egui::pos2(60.0, 60.0),
+                size: egui::vec2(220.0, 320.0),
+                image: None,
+            }],
+            groups: vec![Group {
+                id: Uuid::new_v4(),
+                name: "math".to_string(),
+                node_ids: vec![sum_id, divide_id],
+            }],
+            history: UndoStack::default(),
         };
 
         assert!(graph.nodes.len() == 5, "test_graph must contain 5 nodes");
@@ -284,92 +1513,2778 @@ impl Graph {
         graph
     }
 
-    pub fn select_node(&mut self, node_id: Uuid) {
+    /// All nodes that transitively feed `node_id` through input connections.
+    pub fn ancestors(&self, node_id: Uuid) -> Vec<Uuid> {
+        let node_lookup: HashMap<_, _> = self.nodes.iter().map(|node| (node.id, node)).collect();
         assert!(
-            self.nodes.iter().any(|node| node.id == node_id),
-            "selected node must exist in graph"
+            node_lookup.contains_key(&node_id),
+            "node must exist to collect ancestors"
+        );
+
+        let mut visited = HashSet::from([node_id]);
+        let mut result = Vec::new();
+        let mut stack = vec![node_id];
+        while let Some(current) = stack.pop() {
+            let Some(node) = node_lookup.get(&current) else {
+                continue;
+            };
+            for connection in node.inputs.iter().flat_map(|input| &input.connections) {
+                if visited.insert(connection.node_id) {
+                    result.push(connection.node_id);
+                    stack.push(connection.node_id);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Every wire as (target node, input index, connection), in graph order; a fan-in
+    /// input yields one item per source.
+    pub fn connections(&self) -> impl Iterator<Item = (&Node, usize, &Connection)> + '_ {
+        self.nodes.iter().flat_map(|node| {
+            node.inputs
+                .iter()
+                .enumerate()
+                .flat_map(move |(index, input)| {
+                    input
+                        .connections
+                        .iter()
+                        .map(move |connection| (node, index, connection))
+                })
+        })
+    }
+
+    /// Flat `source_node, source_output, target_node, target_input` list for spreadsheets
+    /// and scripts. Connections to missing nodes or outputs are left out.
+    pub fn export_connections(&self, format: ConnectionExportFormat) -> String {
+        let node_lookup: HashMap<_, _> = self.nodes.iter().map(|node| (node.id, node)).collect();
+        let mut name_counts: HashMap<&str, usize> = HashMap::new();
+        for node in &self.nodes {
+            *name_counts.entry(node.name.as_str()).or_default() += 1;
+        }
+        let node_label = |node: &Node| {
+            if name_counts[node.name.as_str()] > 1 {
+                format!("{} ({})", node.name, node.id)
+            } else {
+                node.name.clone()
+            }
+        };
+
+        let rows: Vec<ConnectionRow> = self
+            .connections()
+            .filter_map(|(target, input_index, connection)| {
+                let source = node_lookup.get(&connection.node_id)?;
+                let output = source.outputs.get(connection.output_index)?;
+                Some(ConnectionRow {
+                    source_node: node_label(source),
+                    source_output: output.name.clone(),
+                    target_node: node_label(target),
+                    target_input: target.inputs[input_index].name.clone(),
+                })
+            })
+            .collect();
+
+        match format {
+            ConnectionExportFormat::Json => serde_json::to_string_pretty(&rows)
+                .expect("connection rows should serialize to JSON"),
+            ConnectionExportFormat::Csv => {
+                let mut csv = String::from("source_node,source_output,target_node,target_input\n");
+                for row in &rows {
+                    let fields = [
+                        &row.source_node,
+                        &row.source_output,
+                        &row.target_node,
+                        &row.target_input,
+                    ];
+                    let line: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+                    csv.push_str(&line.join(","));
+                    csv.push('\n');
+                }
+                csv
+            }
+        }
+    }
+
+    /// Every terminal node with the node/output feeding each of its inputs.
+    pub fn terminal_summary(&self) -> Vec<TerminalInfo> {
+        let node_lookup: HashMap<_, _> = self.nodes.iter().map(|node| (node.id, node)).collect();
+
+        self.nodes
+            .iter()
+            .filter(|node| node.terminal)
+            .map(|node| TerminalInfo {
+                node_id: node.id,
+                name: node.name.clone(),
+                inputs: node
+                    .inputs
+                    .iter()
+                    .map(|input| TerminalInput {
+                        name: input.name.clone(),
+                        sources: input
+                            .connections
+                            .iter()
+                            .filter_map(|connection| {
+                                let (source_id, output_index) = self
+                                    .resolve_output(connection.node_id, connection.output_index)?;
+                                let source = node_lookup
+                                    .get(&source_id)
+                                    .expect("terminal input must reference an existing node");
+                                let output = source
+                                    .outputs
+                                    .get(output_index)
+                                    .expect("terminal input must reference an existing output");
+                                Some(ResolvedSource {
+                                    node_id: source.id,
+                                    node_name: source.name.clone(),
+                                    output_name: output.name.clone(),
+                                })
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// The output that actually provides `output_index` of `node_id` once bypassed nodes
+    /// pass through: a bypassed node forwards the first source of its first input to its
+    /// first output. `None` when a bypassed node can't forward (another output,
+    /// unconnected first input, or a bypass cycle).
+    pub fn resolve_output(&self, node_id: Uuid, output_index: usize) -> Option<(Uuid, usize)> {
+        let mut current = (node_id, output_index);
+        let mut visited = HashSet::new();
+        loop {
+            let node = self.nodes.iter().find(|node| node.id == current.0)?;
+            if !node.bypass {
+                return Some(current);
+            }
+            if current.1 != 0 || !visited.insert(node.id) {
+                return None;
+            }
+            let connection = node.inputs.first()?.connections.first()?;
+            current = (connection.node_id, connection.output_index);
+        }
+    }
+
+    /// Node ids ordered so every node comes after the sources of its inputs, starting from
+    /// nodes nothing feeds, ties broken by position in `nodes`. Errors with the same
+    /// message as `validate` if the connections form a cycle.
+    pub fn topological_order(&self) -> Result<Vec<Uuid>> {
+        if let Some(cycle) = self.find_cycle() {
+            bail!(cycle_message(&cycle));
+        }
+        let index_of: HashMap<Uuid, usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.id, index))
+            .collect();
+        let mut pending_inputs = vec![0usize; self.nodes.len()];
+        let mut consumers: Vec<Vec<usize>> = vec![Vec::new(); self.nodes.len()];
+        for (index, node) in self.nodes.iter().enumerate() {
+            for connection in node.inputs.iter().flat_map(|input| &input.connections) {
+                if let Some(source) = index_of.get(&connection.node_id) {
+                    pending_inputs[index] += 1;
+                    consumers[*source].push(index);
+                }
+            }
+        }
+
+        let mut ready: std::collections::BTreeSet<usize> = pending_inputs
+            .iter()
+            .enumerate()
+            .filter(|(_, pending)| **pending == 0)
+            .map(|(index, _)| index)
+            .collect();
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while let Some(index) = ready.pop_first() {
+            order.push(self.nodes[index].id);
+            for consumer in &consumers[index] {
+                pending_inputs[*consumer] -= 1;
+                if pending_inputs[*consumer] == 0 {
+                    ready.insert(*consumer);
+                }
+            }
+        }
+
+        assert_eq!(
+            order.len(),
+            self.nodes.len(),
+            "an acyclic graph must order every node"
         );
-        self.selected_node_id = Some(node_id);
+        Ok(order)
     }
 
-    pub fn remove_node(&mut self, node_id: Uuid) {
+    /// All nodes that transitively consume outputs of `node_id`.
+    pub fn descendants(&self, node_id: Uuid) -> Vec<Uuid> {
         assert!(
             self.nodes.iter().any(|node| node.id == node_id),
-            "node must exist to be removed"
+            "node must exist to collect descendants"
         );
+        let mut consumers: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for node in &self.nodes {
+            for connection in node.inputs.iter().flat_map(|input| &input.connections) {
+                consumers
+                    .entry(connection.node_id)
+                    .or_default()
+                    .push(node.id);
+            }
+        }
 
-        self.nodes.retain(|node| node.id != node_id);
-
-        if self
-            .selected_node_id
-            .is_some_and(|selected| selected == node_id)
-        {
-            self.selected_node_id = None;
+        let mut visited = HashSet::from([node_id]);
+        let mut result = Vec::new();
+        let mut stack = vec![node_id];
+        while let Some(current) = stack.pop() {
+            let Some(targets) = consumers.get(&current) else {
+                continue;
+            };
+            for target in targets {
+                if visited.insert(*target) {
+                    result.push(*target);
+                    stack.push(*target);
+                }
+            }
         }
 
+        result
+    }
+
+    /// Ids of nodes whose name contains `query`, case-insensitively, in graph order.
+    pub fn find_nodes_by_name(&self, query: &str) -> Vec<Uuid> {
+        let query = query.trim().to_lowercase();
+        self.nodes
+            .iter()
+            .filter(|node| node.name.to_lowercase().contains(&query))
+            .map(|node| node.id)
+            .collect()
+    }
+
+    /// Replaces `find` with `replace` in every input and output name; returns how many
+    /// port names changed.
+    pub fn rename_ports_matching(
+        &mut self,
+        find: &str,
+        replace: &str,
+        options: ReplaceOptions,
+    ) -> usize {
+        let mut changed = 0;
         for node in &mut self.nodes {
-            for input in &mut node.inputs {
-                if let Some(connection) = &input.connection
-                    && connection.node_id == node_id
-                {
-                    input.connection = None;
+            let names = node
+                .inputs
+                .iter_mut()
+                .map(|input| &mut input.name)
+                .chain(node.outputs.iter_mut().map(|output| &mut output.name));
+            for name in names {
+                if let Some(renamed) = replace_matching(name, find, replace, options) {
+                    *name = renamed;
+                    changed += 1;
                 }
             }
         }
+        changed
     }
-}
 
-impl GraphFormat {
-    pub fn from_extension(extension: &str) -> Result<Self> {
-        let normalized = extension.trim().to_ascii_lowercase();
-        if normalized.is_empty() {
-            bail!("graph file extension is empty");
+    /// Replaces `find` with `replace` in every node name; returns how many nodes changed.
+    pub fn rename_nodes_matching(
+        &mut self,
+        find: &str,
+        replace: &str,
+        options: ReplaceOptions,
+    ) -> usize {
+        let mut changed = 0;
+        for node in &mut self.nodes {
+            if let Some(renamed) = replace_matching(&node.name, find, replace, options) {
+                node.name = renamed;
+                changed += 1;
+            }
         }
+        changed
+    }
 
-        match normalized.as_str() {
-            "json" => Ok(Self::Json),
-            "yaml" | "yml" => Ok(Self::Yaml),
-            "toml" => Ok(Self::Toml),
-            _ => bail!("unsupported graph file extension: {normalized}"),
+    pub fn exceeds_coord_limit(&self) -> bool {
+        let limit = RECOMMENDED_COORD_LIMIT as f64;
+        self.camera_points()
+            .any(|(x, y)| x.abs() > limit || y.abs() > limit)
+    }
+
+    /// Graph-space points that move with the camera: unpinned node positions and both
+    /// corners of every comment. In f64, so a far corner cannot overflow.
+    fn camera_points(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        let point = |pos: egui::Pos2| (pos.x as f64, pos.y as f64);
+        let nodes = self
+            .nodes
+            .iter()
+            .filter(|node| !node.screen_pinned)
+            .map(move |node| point(node.pos));
+        let comments = self.comments.iter().flat_map(move |comment| {
+            let (x, y) = point(comment.pos);
+            [
+                (x, y),
+                (x + comment.size.x as f64, y + comment.size.y as f64),
+            ]
+        });
+        nodes.chain(comments)
+    }
+
+    /// Translates all unpinned nodes and comments so the bounding box of their positions
+    /// is centered on the origin, shifting `pan` so the view does not move. Returns the
+    /// applied translation.
+    pub fn recenter(&mut self) -> egui::Vec2 {
+        let mut points = self.camera_points();
+        let Some(first) = points.next() else {
+            return egui::Vec2::ZERO;
+        };
+        // f64 keeps the center exact for positions far beyond the recommended range.
+        let (mut min_x, mut min_y) = first;
+        let (mut max_x, mut max_y) = first;
+        for (x, y) in points {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        let center_x = (min_x + max_x) * 0.5;
+        let center_y = (min_y + max_y) * 0.5;
+
+        let shift = |pos: egui::Pos2| {
+            egui::pos2(
+                (pos.x as f64 - center_x) as f32,
+                (pos.y as f64 - center_y) as f32,
+            )
+        };
+        for node in self.nodes.iter_mut().filter(|node| !node.screen_pinned) {
+            node.pos = shift(node.pos);
+        }
+        for comment in &mut self.comments {
+            comment.pos = shift(comment.pos);
         }
+        let pan_x = self.pan.x as f64 + center_x * self.zoom as f64;
+        let pan_y = self.pan.y as f64 + center_y * self.zoom as f64;
+        let pan = egui::vec2(pan_x as f32, pan_y as f32);
+        // Content near the f32 limit needs a pan beyond it to stay put; show the
+        // recentered content at the origin instead.
+        self.pan = if pan.x.is_finite() && pan.y.is_finite() {
+            pan
+        } else {
+            egui::Vec2::ZERO
+        };
+
+        egui::vec2(-center_x as f32, -center_y as f32)
     }
 
-    pub fn from_path(path: &Path) -> Result<Self> {
-        let extension = path
-            .extension()
-            .and_then(|value| value.to_str())
-            .ok_or_else(|| anyhow!("graph file extension is missing"))?;
+    /// Pins or unpins a node, converting its position with the current `pan`/`zoom` so
+    /// it stays where it is on screen.
+    pub fn set_screen_pinned(&mut self, node_id: Uuid, pinned: bool) {
+        let (pan, zoom) = (self.pan, self.zoom);
+        let node = self
+            .nodes
+            .iter_mut()
+            .find(|node| node.id == node_id)
+            .expect("pinned node must exist");
+        if node.screen_pinned == pinned {
+            return;
+        }
+        node.pos = if pinned {
+            node.pos * zoom + pan
+        } else {
+            egui::Pos2::ZERO + (node.pos.to_vec2() - pan) / zoom
+        };
+        node.screen_pinned = pinned;
+    }
 
-        Self::from_extension(extension)
+    /// Wires each `(source_node, output_index, target_node, input_index)` edge as
+    /// `add_connection` would, so fan-in inputs keep their other sources. All edges are
+    /// checked first: if any names a missing node or port, links incompatible port types,
+    /// overfills an input, or would close a cycle, nothing is changed.
+    pub fn connect_many(&mut self, edges: &[(Uuid, usize, Uuid, usize)]) -> Result<()> {
+        let index_of: HashMap<Uuid, usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.id, index))
+            .collect();
+        let mut per_input: HashMap<(usize, usize), usize> = HashMap::new();
+        for (edge, &(source_id, output_index, target_id, input_index)) in edges.iter().enumerate() {
+            let Some(&source) = index_of.get(&source_id) else {
+                bail!("edge {edge}: source node {source_id} does not exist");
+            };
+            let Some(&target) = index_of.get(&target_id) else {
+                bail!("edge {edge}: target node {target_id} does not exist");
+            };
+            if output_index >= self.nodes[source].outputs.len() {
+                bail!("edge {edge}: source node {source_id} has no output {output_index}");
+            }
+            let Some(input) = self.nodes[target].inputs.get(input_index) else {
+                bail!("edge {edge}: target node {target_id} has no input {input_index}");
+            };
+            let wired = per_input.entry((target, input_index)).or_default();
+            *wired += 1;
+            if input.max_connections.is_some_and(|max| *wired > max) {
+                bail!("edge {edge}: input {input_index} of {target_id} is wired too many times");
+            }
+            if let Err(err) = self.check_port_types(source_id, output_index, target_id, input_index)
+            {
+                bail!("edge {edge}: {err}");
+            }
+        }
+
+        // Staged on a copy of the nodes so a cycle leaves the graph untouched.
+        let mut staged = Graph {
+            nodes: self.nodes.clone(),
+            ..Graph::default()
+        };
+        for &(source_id, output_index, target_id, input_index) in edges {
+            staged.add_connection(source_id, output_index, target_id, input_index)?;
+        }
+        if let Some(cycle) = staged.find_cycle() {
+            bail!(cycle_message(&cycle));
+        }
+        self.nodes = staged.nodes;
+
+        Ok(())
     }
-}
 
-#[test]
-fn test_graph() {
-    let graph = Graph::test_graph();
-    assert!(graph.validate().is_ok());
-}
+    /// Errors unless output `output_index` of `source_id` may feed input `input_index` of
+    /// `target_id` under `port_types_compatible`. The ports must exist.
+    pub fn check_port_types(
+        &self,
+        source_id: Uuid,
+        output_index: usize,
+        target_id: Uuid,
+        input_index: usize,
+    ) -> Result<()> {
+        let port = |node_id: Uuid| {
+            self.nodes
+                .iter()
+                .find(|node| node.id == node_id)
+                .expect("checked ports must exist")
+        };
+        let output = &port(source_id).outputs[output_index];
+        let input = &port(target_id).inputs[input_index];
+        if !port_types_compatible(&output.data_type, &input.data_type) {
+            bail!(
+                "{} output {} does not fit {} input {}",
+                output.data_type,
+                output.name,
+                input.data_type,
+                input.name
+            );
+        }
+        Ok(())
+    }
 
-#[test]
-fn graph_roundtrip() {
-    assert_roundtrip(GraphFormat::Json);
-    assert_roundtrip(GraphFormat::Yaml);
-    assert_roundtrip(GraphFormat::Toml);
+    /// Adds one source to an input, keeping the ones it has. A wire that already exists
+    /// is left alone. When the input is full (`Input::max_connections`), its most recent
+    /// connection makes room and is returned.
+    pub fn add_connection(
+        &mut self,
+        source_id: Uuid,
+        output_index: usize,
+        target_id: Uuid,
+        input_index: usize,
+    ) -> Result<Option<Connection>> {
+        let Some(source) = self.nodes.iter().find(|node| node.id == source_id) else {
+            bail!("source node {source_id} does not exist");
+        };
+        let Some(output) = source.outputs.get(output_index) else {
+            bail!("source node {source_id} has no output {output_index}");
+        };
+        let output_name = output.name.clone();
+        let Some(target) = self.nodes.iter_mut().find(|node| node.id == target_id) else {
+            bail!("target node {target_id} does not exist");
+        };
+        let Some(input) = target.inputs.get_mut(input_index) else {
+            bail!("target node {target_id} has no input {input_index}");
+        };
 
-    assert_file_roundtrip(GraphFormat::Json, "json");
-    assert_file_roundtrip(GraphFormat::Yaml, "yaml");
-    assert_file_roundtrip(GraphFormat::Toml, "toml");
-}
+        if input.connections.iter().any(|connection| {
+            connection.node_id == source_id && connection.output_index == output_index
+        }) {
+            return Ok(None);
+        }
+        let displaced = if input.is_full() {
+            input.connections.pop()
+        } else {
+            None
+        };
+        input.connections.push(Connection {
+            node_id: source_id,
+            output_index,
+            label: None,
+            output_name: Some(output_name),
+        });
+        self.invalidate_outputs(target_id);
+        self.debug_assert_connections_consistent();
 
-fn assert_roundtrip(format: GraphFormat) {
-    let graph = Graph::test_graph();
-    let serialized = graph
-        .serialize(format)
-        .expect("graph serialization should succeed for test graph");
-    assert!(
-        !serialized.trim().is_empty(),
-        "serialized graph should not be empty"
-    );
+        Ok(displaced)
+    }
+
+    /// Records an evaluated value for an output's preview; false when the port is missing.
+    pub fn record_output_value(
+        &mut self,
+        node_id: Uuid,
+        output_index: usize,
+        value: serde_json::Value,
+    ) -> bool {
+        let Some(output) = self
+            .nodes
+            .iter_mut()
+            .find(|node| node.id == node_id)
+            .and_then(|node| node.outputs.get_mut(output_index))
+        else {
+            return false;
+        };
+        output.last_value = Some(value);
+        true
+    }
+
+    /// Marks `node_id` and everything downstream stale after its inputs changed: drops
+    /// their cached outputs and value previews.
+    pub fn invalidate_outputs(&mut self, node_id: Uuid) {
+        let stale: HashSet<Uuid> = std::iter::once(node_id)
+            .chain(self.descendants(node_id))
+            .collect();
+        for node in self
+            .nodes
+            .iter_mut()
+            .filter(|node| stale.contains(&node.id))
+        {
+            node.has_cached_output = false;
+            for output in &mut node.outputs {
+                output.last_value = None;
+            }
+        }
+    }
+
+    pub fn add_node(&mut self, node: Node, order: InsertOrder) -> Uuid {
+        assert!(
+            self.nodes.iter().all(|existing| existing.id != node.id),
+            "added node id must be unique"
+        );
+        let index = match order {
+            InsertOrder::Append => None,
+            InsertOrder::SortedByName => self
+                .nodes
+                .iter()
+                .position(|existing| existing.name > node.name),
+            InsertOrder::SortedById => self.nodes.iter().position(|existing| existing.id > node.id),
+        }
+        .unwrap_or(self.nodes.len());
+        let node_id = node.id;
+        self.nodes.insert(index, node);
+        node_id
+    }
+
+    /// Clipboard text for `node_ids`: a human-readable adjacency list (one line per node,
+    /// `input <- source.output` per connection) followed by a single native line
+    /// (`CLIPBOARD_MARKER` + JSON) that `paste_clipboard_text` detects.
+    pub fn clipboard_text(&self, node_ids: &[Uuid]) -> String {
+        let node_lookup: HashMap<_, _> = self.nodes.iter().map(|node| (node.id, node)).collect();
+        let nodes: Vec<Node> = node_ids
+            .iter()
+            .map(|id| {
+                (*node_lookup
+                    .get(id)
+                    .expect("copied node must exist in graph"))
+                .clone()
+            })
+            .collect();
+
+        let mut text = String::new();
+        for node in &nodes {
+            text.push_str(&node.name);
+            let sources: Vec<String> = node
+                .inputs
+                .iter()
+                .flat_map(|input| {
+                    input.connections.iter().map(|connection| {
+                        let source = node_lookup
+                            .get(&connection.node_id)
+                            .expect("connection source must exist");
+                        let output = &source.outputs[connection.output_index];
+                        format!("{} <- {}.{}", input.name, source.name, output.name)
+                    })
+                })
+                .collect();
+            if !sources.is_empty() {
+                text.push_str(": ");
+                text.push_str(&sources.join(", "));
+            }
+            text.push('\n');
+        }
+
+        let native = serde_json::to_string(&ClipboardFragment { nodes })
+            .expect("clipboard fragment must serialize");
+        text.push_str(CLIPBOARD_MARKER);
+        text.push_str(&native);
+        text
+    }
+
+    /// Pastes nodes from `clipboard_text` output with fresh ids, shifted by `offset`.
+    /// Connections between pasted nodes are remapped, connections to nodes still in the
+    /// graph are kept, and the rest are dropped. Returns `None` for non-native text and
+    /// for payloads `ClipboardFragment::validate` or `paste_fragment` rejects, leaving the
+    /// graph unchanged.
+    pub fn paste_clipboard_text(&mut self, text: &str, offset: egui::Vec2) -> Option<Vec<Uuid>> {
+        let native = text
+            .lines()
+            .find_map(|line| line.strip_prefix(CLIPBOARD_MARKER))?;
+        let fragment: ClipboardFragment = serde_json::from_str(native).ok()?;
+        fragment.validate(false).ok()?;
+        self.paste_fragment(fragment, offset).ok()
+    }
+
+    /// JSON for duplicating `ids` as a subgraph: copies of the nodes keeping only the
+    /// connections between them, so a paste never links back to the originals.
+    pub fn copy_nodes(&self, ids: &[Uuid]) -> String {
+        let nodes: Vec<Node> = ids
+            .iter()
+            .map(|id| {
+                let mut node = self
+                    .nodes
+                    .iter()
+                    .find(|node| node.id == *id)
+                    .expect("copied node must exist in graph")
+                    .clone();
+                for input in &mut node.inputs {
+                    input
+                        .connections
+                        .retain(|connection| ids.contains(&connection.node_id));
+                }
+                node
+            })
+            .collect();
+        serde_json::to_string(&ClipboardFragment { nodes }).expect("node copy must serialize")
+    }
+
+    /// Adds the nodes of a `copy_nodes` payload with fresh ids, shifted by `offset` so
+    /// they don't cover the originals, and internal connections remapped to the new ids.
+    /// Returns the new ids in payload order. Malformed payloads, duplicate node ids, wires
+    /// to nodes outside the payload, and nodes that would leave the graph invalid are
+    /// errors and add nothing.
+    pub fn paste_nodes(&mut self, payload: &str, offset: egui::Vec2) -> Result<Vec<Uuid>> {
+        let fragment: ClipboardFragment =
+            serde_json::from_str(payload).map_err(|err| anyhow!("invalid node payload: {err}"))?;
+        fragment.validate(true)?;
+        self.paste_fragment(fragment, offset)
+    }
+
+    /// Shared by both paste paths once the fragment is validated; see
+    /// `paste_clipboard_text` for what happens to wires. Clipboard text may come from
+    /// anywhere, so the remapped nodes are first checked on a trial graph with every
+    /// `validate_detailed` check (cycles, port types, input caps, finite positions) and
+    /// refused if any fails.
+    fn paste_fragment(
+        &mut self,
+        fragment: ClipboardFragment,
+        offset: egui::Vec2,
+    ) -> Result<Vec<Uuid>> {
+        let id_map: HashMap<Uuid, Uuid> = fragment
+            .nodes
+            .iter()
+            .map(|node| (node.id, Uuid::new_v4()))
+            .collect();
+        let mut pasted = Vec::with_capacity(fragment.nodes.len());
+        let mut new_nodes = Vec::with_capacity(fragment.nodes.len());
+        for mut node in fragment.nodes {
+            node.id = id_map[&node.id];
+            node.pos += offset;
+            for connection in node
+                .inputs
+                .iter_mut()
+                .flat_map(|input| &mut input.connections)
+            {
+                if let Some(new_id) = id_map.get(&connection.node_id) {
+                    connection.node_id = *new_id;
+                }
+            }
+            pasted.push(node.id);
+            new_nodes.push(node);
+        }
+
+        let outputs: HashMap<Uuid, usize> = self
+            .nodes
+            .iter()
+            .chain(&new_nodes)
+            .map(|node| (node.id, node.outputs.len()))
+            .collect();
+        for node in &mut new_nodes {
+            for input in &mut node.inputs {
+                input.connections.retain(|connection| {
+                    outputs
+                        .get(&connection.node_id)
+                        .is_some_and(|count| connection.output_index < *count)
+                });
+            }
+        }
+        let trial = Graph {
+            nodes: self.nodes.iter().chain(&new_nodes).cloned().collect(),
+            ..Graph::default()
+        };
+        if let Some(issue) = trial.validate_detailed().into_iter().next() {
+            bail!(
+                "node payload would leave the graph invalid: {}",
+                issue.message
+            );
+        }
+        for node in new_nodes {
+            self.add_node(node, InsertOrder::Append);
+        }
+
+        Ok(pasted)
+    }
+
+    /// Deep copy with fresh ids for the graph, every node, comment, and group. Connection
+    /// sources, group members, and the selection follow their nodes; references to ids not
+    /// in the graph are left as they are, so a valid graph stays valid.
+    pub fn clone_fresh(&self) -> Graph {
+        let id_map: HashMap<Uuid, Uuid> = self
+            .nodes
+            .iter()
+            .map(|node| (node.id, Uuid::new_v4()))
+            .collect();
+        let fresh = |id: Uuid| id_map.get(&id).copied().unwrap_or(id);
+
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let mut node = node.clone();
+                node.id = id_map[&node.id];
+                for connection in node
+                    .inputs
+                    .iter_mut()
+                    .flat_map(|input| &mut input.connections)
+                {
+                    connection.node_id = fresh(connection.node_id);
+                }
+                node
+            })
+            .collect();
+        let comments = self
+            .comments
+            .iter()
+            .map(|comment| Comment {
+                id: Uuid::new_v4(),
+                ..comment.clone()
+            })
+            .collect();
+        let groups = self
+            .groups
+            .iter()
+            .map(|group| Group {
+                id: Uuid::new_v4(),
+                name: group.name.clone(),
+                node_ids: group.node_ids.iter().copied().map(fresh).collect(),
+            })
+            .collect();
+
+        Graph {
+            id: Uuid::new_v4(),
+            nodes,
+            pan: self.pan,
+            zoom: self.zoom,
+            selected_nodes: self
+                .selected_nodes
+                .iter()
+                .filter_map(|id| id_map.get(id).copied())
+                .collect(),
+            comments,
+            groups,
+            history: UndoStack::default(),
+        }
+    }
+
+    /// Compact JSON of everything but the history: nodes, connections, comments,
+    /// groups, view, and selection.
+    pub fn undo_snapshot(&self) -> String {
+        serde_json::to_string(self).expect("graph must serialize to JSON")
+    }
+
+    /// Records the current state so the next edit can be undone back to it.
+    pub fn push_undo_checkpoint(&mut self) {
+        let snapshot = self.undo_snapshot();
+        self.history.push(snapshot);
+    }
+
+    /// Records a snapshot taken before an edit that has already happened, such as a
+    /// drag that moved nodes over several frames.
+    pub fn push_undo_snapshot(&mut self, snapshot: String) {
+        self.history.push(snapshot);
+    }
+
+    /// Steps back to the latest checkpoint that differs from the current state, skipping
+    /// ones no edit followed; false when there is none.
+    pub fn undo(&mut self) -> bool {
+        let current = self.undo_snapshot();
+        let Some(snapshot) = std::iter::from_fn(|| self.history.undo.pop_back())
+            .find(|snapshot| *snapshot != current)
+        else {
+            return false;
+        };
+        self.restore_snapshot(&snapshot);
+        self.history.redo.push(current);
+        true
+    }
+
+    /// Re-applies the latest undone edit; false when there is none.
+    pub fn redo(&mut self) -> bool {
+        let Some(snapshot) = self.history.redo.pop() else {
+            return false;
+        };
+        let current = self.undo_snapshot();
+        self.restore_snapshot(&snapshot);
+        self.history.undo.push_back(current);
+        true
+    }
+
+    fn restore_snapshot(&mut self, snapshot: &str) {
+        let restored: Graph =
+            serde_json::from_str(snapshot).expect("undo snapshots must be valid graphs");
+        let history = std::mem::take(&mut self.history);
+        let row_scrolls: HashMap<Uuid, usize> = self
+            .nodes
+            .iter()
+            .map(|node| (node.id, node.row_scroll))
+            .collect();
+        *self = Graph {
+            history,
+            ..restored
+        };
+        for node in &mut self.nodes {
+            node.row_scroll = row_scrolls.get(&node.id).copied().unwrap_or_default();
+        }
+        self.debug_assert_connections_consistent();
+    }
+
+    /// Appends a disconnected copy of `node_id` under a new id, `DUPLICATE_OFFSET` away.
+    /// Ports, flags, and notes are copied; input wires and evaluated results are not.
+    pub fn duplicate_node(&mut self, node_id: Uuid) -> Uuid {
+        let source = self
+            .nodes
+            .iter()
+            .find(|node| node.id == node_id)
+            .expect("duplicated node must exist in graph");
+        let mut node = Node {
+            id: Uuid::new_v4(),
+            pos: source.pos + DUPLICATE_OFFSET,
+            has_cached_output: false,
+            ..source.clone()
+        };
+        for input in &mut node.inputs {
+            input.connections.clear();
+        }
+        for output in &mut node.outputs {
+            output.last_value = None;
+        }
+        self.add_node(node, InsertOrder::Append)
+    }
+
+    /// `base`, or `base 2`, `base 3`, … if that name is taken. For `category(name)` the
+    /// number goes inside the parentheses, so `Node::category` still finds the prefix.
+    pub fn unique_node_name(&self, base: &str) -> String {
+        let taken = |name: &str| self.nodes.iter().any(|node| node.name == name);
+        if !taken(base) {
+            return base.to_string();
+        }
+        (2..)
+            .map(|n| match base.strip_suffix(')') {
+                Some(head) => format!("{head} {n})"),
+                None => format!("{base} {n}"),
+            })
+            .find(|name| !taken(name))
+            .expect("some numeric suffix must be free")
+    }
+
+    /// Appends `node`, renamed with `unique_node_name` if its name is taken.
+    pub fn add_node_with_unique_name(&mut self, mut node: Node) -> Uuid {
+        node.name = self.unique_node_name(&node.name);
+        self.add_node(node, InsertOrder::Append)
+    }
+
+    /// Appends a comment showing `image` with its top-left corner at `pos`, sized to the
+    /// image and scaled down to fit `COMMENT_IMAGE_MAX_SIDE`. Returns the comment id.
+    pub fn add_image_comment(&mut self, pos: egui::Pos2, image: CommentImage) -> Uuid {
+        let size = egui::vec2(image.width as f32, image.height as f32);
+        let fit = (COMMENT_IMAGE_MAX_SIDE / size.max_elem()).min(1.0);
+        let comment = Comment {
+            id: Uuid::new_v4(),
+            text: String::new(),
+            pos,
+            size: size * fit,
+            image: Some(image),
+        };
+        let id = comment.id;
+        self.comments.push(comment);
+        id
+    }
+
+    /// Parses `json` with `Node::from_spec` and appends the node.
+    pub fn add_node_from_spec(&mut self, json: &str) -> Result<Uuid> {
+        let node = Node::from_spec(json)?;
+        Ok(self.add_node(node, InsertOrder::Append))
+    }
+
+    /// The primary selection: the selected node when exactly one is selected.
+    pub fn selected_node(&self) -> Option<Uuid> {
+        match self.selected_nodes.len() {
+            1 => self.selected_nodes.iter().next().copied(),
+            _ => None,
+        }
+    }
+
+    /// Makes `node_id` the only selected node. Returns false and leaves the selection
+    /// alone when `node_id` is not in the graph.
+    pub fn select_node(&mut self, node_id: Uuid) -> bool {
+        if !self.nodes.iter().any(|node| node.id == node_id) {
+            return false;
+        }
+        self.selected_nodes.clear();
+        self.selected_nodes.insert(node_id);
+        true
+    }
+
+    /// Adds `node_id` to the selection; false when it is not in the graph.
+    pub fn add_to_selection(&mut self, node_id: Uuid) -> bool {
+        if !self.nodes.iter().any(|node| node.id == node_id) {
+            return false;
+        }
+        self.selected_nodes.insert(node_id);
+        true
+    }
+
+    pub fn select_all(&mut self) {
+        self.selected_nodes = self.nodes.iter().map(|node| node.id).collect();
+    }
+
+    /// Returns false and changes nothing when `node_id` is not in the graph.
+    pub fn remove_node(&mut self, node_id: Uuid) -> bool {
+        if !self.nodes.iter().any(|node| node.id == node_id) {
+            return false;
+        }
+
+        let downstream = self.descendants(node_id);
+        self.nodes.retain(|node| node.id != node_id);
+
+        self.selected_nodes.remove(&node_id);
+
+        for node in &mut self.nodes {
+            for input in &mut node.inputs {
+                input
+                    .connections
+                    .retain(|connection| connection.node_id != node_id);
+            }
+        }
+
+        for group in &mut self.groups {
+            group.node_ids.retain(|id| *id != node_id);
+        }
+        for node_id in downstream {
+            self.invalidate_outputs(node_id);
+        }
+
+        self.debug_assert_connections_consistent();
+        true
+    }
+}
+
+impl GraphFormat {
+    pub fn from_extension(extension: &str) -> Result<Self> {
+        let normalized = extension.trim().to_ascii_lowercase();
+        if normalized.is_empty() {
+            bail!("graph file extension is empty");
+        }
+
+        match normalized.as_str() {
+            "json" => Ok(Self::Json),
+            "yaml" | "yml" => Ok(Self::Yaml),
+            "toml" => Ok(Self::Toml),
+            _ => bail!("unsupported graph file extension: {normalized}"),
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|value| value.to_str())
+            .ok_or_else(|| anyhow!("graph file extension is missing"))?;
+
+        Self::from_extension(extension)
+    }
+}
+
+impl GraphTemplate {
+    pub const ALL: [GraphTemplate; 3] = [Self::Sample, Self::Chain, Self::FanOut];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Sample => "Sample math",
+            Self::Chain => "Processing chain",
+            Self::FanOut => "Fan-out / merge",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Sample => "Two values summed and divided, with a comment and a group",
+            Self::Chain => "A value passed through a row of single-input steps",
+            Self::FanOut => "One value feeding several branches merged into one result",
+        }
+    }
+
+    /// Builds a fresh graph with new ids every call.
+    pub fn build(self) -> Graph {
+        let graph = match self {
+            Self::Sample => Graph::test_graph(),
+            Self::Chain => chain_template(),
+            Self::FanOut => fan_out_template(),
+        };
+        graph.validate().expect("built-in templates must be valid");
+        graph
+    }
+}
+
+impl Default for NodeTemplateRegistry {
+    fn default() -> Self {
+        let template = |name: &str, inputs: &[&str], outputs: &[&str], terminal| NodeTemplate {
+            name: name.to_string(),
+            inputs: inputs.iter().map(|&name| name.into()).collect(),
+            outputs: outputs.iter().map(|&name| name.into()).collect(),
+            terminal,
+        };
+        let mut registry = Self {
+            templates: Vec::new(),
+        };
+        registry.register(template("value", &[], &["value"], false));
+        registry.register(template("math(sum)", &["a", "b"], &["sum"], false));
+        registry.register(template("math(divide)", &["sum", "b"], &["divide"], false));
+        registry.register(template("output", &["value"], &[], true));
+        registry
+    }
+}
+
+impl NodeTemplateRegistry {
+    /// Adds `template` at the end of the menu, or replaces the template with the same
+    /// name in place.
+    pub fn register(&mut self, template: NodeTemplate) {
+        assert!(
+            !template.name.trim().is_empty(),
+            "node template name must not be empty"
+        );
+        match self
+            .templates
+            .iter_mut()
+            .find(|existing| existing.name == template.name)
+        {
+            Some(existing) => *existing = template,
+            None => self.templates.push(template),
+        }
+    }
+
+    pub fn templates(&self) -> &[NodeTemplate] {
+        &self.templates
+    }
+
+    /// A disconnected node named after the template, at graph position `pos`, with a
+    /// fresh id. `name` must be registered.
+    pub fn instantiate(&self, name: &str, pos: egui::Pos2) -> Node {
+        let template = self
+            .templates
+            .iter()
+            .find(|template| template.name == name)
+            .expect("instantiated node template must be registered");
+        Node {
+            terminal: template.terminal,
+            ..node_with_ports(&template.name, pos, &template.inputs, &template.outputs)
+        }
+    }
+}
+
+/// Whether an output of `output_type` may feed an input of `input_type`: the names match,
+/// or either port is untyped (empty), which keeps files from before typed ports wired.
+pub fn port_types_compatible(output_type: &str, input_type: &str) -> bool {
+    output_type.is_empty() || input_type.is_empty() || output_type == input_type
+}
+
+fn cycle_message(cycle: &[Uuid]) -> String {
+    let ids: Vec<String> = cycle.iter().map(Uuid::to_string).collect();
+    format!("graph contains a cycle: {}", ids.join(" -> "))
+}
+
+/// Rewrites every node id reference in a serialized graph: node ids, connection sources,
+/// group members, and the selection.
+fn remap_node_ids(
+    graph: &mut serde_json::Value,
+    mut remap: impl FnMut(&serde_json::Value) -> Result<serde_json::Value>,
+) -> Result<()> {
+    let mut apply = |id: &mut serde_json::Value| -> Result<()> {
+        *id = remap(id)?;
+        Ok(())
+    };
+    for node in graph["nodes"].as_array_mut().into_iter().flatten() {
+        apply(&mut node["id"])?;
+        for input in node["inputs"].as_array_mut().into_iter().flatten() {
+            for connection in input["connections"].as_array_mut().into_iter().flatten() {
+                apply(&mut connection["node_id"])?;
+            }
+            // Compact files written before fan-in.
+            if input["connection"].is_object() {
+                apply(&mut input["connection"]["node_id"])?;
+            }
+        }
+    }
+    for group in graph["groups"].as_array_mut().into_iter().flatten() {
+        for id in group["node_ids"].as_array_mut().into_iter().flatten() {
+            apply(id)?;
+        }
+    }
+    for id in graph["selected_nodes"].as_array_mut().into_iter().flatten() {
+        apply(id)?;
+    }
+    Ok(())
+}
+
+fn serialize_sorted_ids<S: serde::Serializer>(
+    ids: &HashSet<Uuid>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    let mut sorted: Vec<&Uuid> = ids.iter().collect();
+    sorted.sort_unstable();
+    serializer.collect_seq(sorted)
+}
+
+fn strip_nulls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => {
+            object.retain(|_, field| !field.is_null());
+            object.values_mut().for_each(strip_nulls);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+fn template_node(name: &str, pos: egui::Pos2, inputs: &[&str], outputs: &[&str]) -> Node {
+    let ports =
+        |names: &[&str]| -> Vec<PortTemplate> { names.iter().map(|&name| name.into()).collect() };
+    node_with_ports(name, pos, &ports(inputs), &ports(outputs))
+}
+
+/// A disconnected node with a fresh id; each input takes a single source.
+fn node_with_ports(
+    name: &str,
+    pos: egui::Pos2,
+    inputs: &[PortTemplate],
+    outputs: &[PortTemplate],
+) -> Node {
+    Node {
+        name: name.to_string(),
+        pos,
+        inputs: inputs
+            .iter()
+            .map(|port| Input {
+                name: port.name.clone(),
+                data_type: port.data_type.clone(),
+                connections: Vec::new(),
+                max_connections: Some(1),
+            })
+            .collect(),
+        outputs: outputs
+            .iter()
+            .map(|port| Output {
+                name: port.name.clone(),
+                data_type: port.data_type.clone(),
+                last_value: None,
+            })
+            .collect(),
+        ..Node::default()
+    }
+}
+
+fn template_wire(target: &mut Node, input_index: usize, source: &Node, output_index: usize) {
+    target.inputs[input_index].connections.push(Connection {
+        node_id: source.id,
+        output_index,
+        label: None,
+        output_name: Some(source.outputs[output_index].name.clone()),
+    });
+}
+
+fn chain_template() -> Graph {
+    let mut value = template_node("value", egui::pos2(80.0, 180.0), &[], &["value"]);
+    value.cache_output = true;
+    let mut nodes = vec![value];
+    for (step, name) in ["math(negate)", "math(abs)", "math(sqrt)"]
+        .into_iter()
+        .enumerate()
+    {
+        let pos = egui::pos2(300.0 + 220.0 * step as f32, 180.0);
+        let mut node = template_node(name, pos, &["value"], &["value"]);
+        template_wire(
+            &mut node,
+            0,
+            nodes.last().expect("chain starts with a value"),
+            0,
+        );
+        nodes.push(node);
+    }
+    let mut output = template_node("output", egui::pos2(960.0, 180.0), &["value"], &[]);
+    output.terminal = true;
+    template_wire(&mut output, 0, nodes.last().expect("chain has steps"), 0);
+    nodes.push(output);
+
+    Graph {
+        nodes,
+        ..Graph::default()
+    }
+}
+
+fn fan_out_template() -> Graph {
+    let mut value = template_node("value", egui::pos2(80.0, 220.0), &[], &["value"]);
+    value.cache_output = true;
+    let mut merge = template_node(
+        "math(sum)",
+        egui::pos2(540.0, 220.0),
+        &["a", "b", "c"],
+        &["sum"],
+    );
+    let mut nodes = Vec::new();
+    for (branch, name) in ["math(square)", "math(double)", "math(half)"]
+        .into_iter()
+        .enumerate()
+    {
+        let pos = egui::pos2(300.0, 80.0 + 140.0 * branch as f32);
+        let mut node = template_node(name, pos, &["value"], &["value"]);
+        template_wire(&mut node, 0, &value, 0);
+        template_wire(&mut merge, branch, &node, 0);
+        nodes.push(node);
+    }
+    let mut output = template_node("output", egui::pos2(780.0, 220.0), &["value"], &[]);
+    output.terminal = true;
+    template_wire(&mut output, 0, &merge, 0);
+
+    let branch_ids = nodes.iter().map(|node| node.id).collect();
+    nodes.insert(0, value);
+    nodes.extend([merge, output]);
+    Graph {
+        nodes,
+        groups: vec![Group {
+            id: Uuid::new_v4(),
+            name: "branches".to_string(),
+            node_ids: branch_ids,
+        }],
+        ..Graph::default()
+    }
+}
+
+#[test]
+fn test_graph() {
+    let graph = Graph::test_graph();
+    assert!(graph.validate().is_ok());
+}
+
+#[test]
+fn remove_then_validate() {
+    let mut graph = Graph::test_graph();
+    let value_b_id = graph
+        .nodes
+        .iter()
+        .find(|node| node.name == "value_b")
+        .expect("test graph should contain value_b")
+        .id;
+    let sum_id = graph
+        .nodes
+        .iter()
+        .find(|node| node.name == "math(sum)")
+        .expect("test graph should contain math(sum)")
+        .id;
+
+    assert!(graph.remove_node(value_b_id));
+    assert!(graph.validate_detailed().is_empty());
+    assert!(graph.remove_node(sum_id));
+    assert!(graph.validate_detailed().is_empty());
+
+    let dangling = graph
+        .nodes
+        .iter()
+        .flat_map(|node| node.inputs.iter())
+        .flat_map(|input| &input.connections)
+        .any(|connection| connection.node_id == value_b_id || connection.node_id == sum_id);
+    assert!(
+        !dangling,
+        "removed nodes must not be referenced by connections"
+    );
+}
+
+#[test]
+fn validate_detailed_collects_all_issues() {
+    let mut graph = Graph::test_graph();
+    graph.zoom = 0.0;
+    graph.nodes[2].inputs[0].connections = vec![Connection {
+        node_id: Uuid::new_v4(),
+        output_index: 0,
+        label: None,
+        output_name: None,
+    }];
+
+    let issues = graph.validate_detailed();
+    assert_eq!(issues.len(), 2, "both issues should be reported");
+    assert_eq!(issues[1].node_id, Some(graph.nodes[2].id));
+    assert!(graph.validate().is_err());
+}
+
+#[test]
+fn content_hash_changes_only_with_validated_content() {
+    let mut graph = Graph::test_graph();
+    let hash = graph.content_hash();
+    assert_eq!(graph.content_hash(), hash, "hashing is deterministic");
+
+    graph.nodes[0].name.push_str(" renamed");
+    assert_eq!(graph.content_hash(), hash, "names are not validated");
+
+    graph.nodes[2].inputs[0].connections.clear();
+    assert_ne!(graph.content_hash(), hash);
+}
+
+#[test]
+fn node_category_is_the_namespace_prefix() {
+    let graph = Graph::test_graph();
+    let categories: Vec<Option<&str>> = graph.nodes.iter().map(Node::category).collect();
+    assert_eq!(
+        categories,
+        vec![None, None, Some("math"), Some("math"), None]
+    );
+    for name in ["(sum)", "math(sum", "math"] {
+        let node = Node {
+            name: name.to_string(),
+            ..Node::default()
+        };
+        assert_eq!(node.category(), None, "{name}");
+    }
+}
+
+#[test]
+fn output_values_clear_downstream_when_inputs_change() {
+    let mut graph = Graph::test_graph();
+    let [value_a, value_b, sum, divide, output] = [0, 1, 2, 3, 4].map(|i| graph.nodes[i].id);
+    for node_id in [value_a, value_b, sum, divide] {
+        assert!(graph.record_output_value(node_id, 0, serde_json::json!(1)));
+    }
+    assert!(!graph.record_output_value(output, 0, serde_json::json!(1)));
+    let json = graph
+        .serialize(GraphFormat::Json, JsonOptions::default())
+        .expect("graph should serialize");
+    assert!(!json.contains("last_value"), "previews are never saved");
+
+    // Rewiring `divide.b` invalidates divide and everything after it, not its sources.
+    graph
+        .add_connection(value_a, 0, divide, 1)
+        .expect("ports exist");
+    let has_value =
+        |graph: &Graph, index: usize| graph.nodes[index].outputs[0].last_value.is_some();
+    assert!(has_value(&graph, 0) && has_value(&graph, 1) && has_value(&graph, 2));
+    assert!(!has_value(&graph, 3));
+    assert!(graph.nodes[0].has_cached_output);
+
+    assert!(graph.remove_node(value_b));
+    assert!(!has_value(&graph, 1), "sum lost an input");
+}
+
+#[test]
+fn undo_restores_the_graph_and_redo_reapplies_the_edit() {
+    let mut graph = Graph::test_graph();
+    let original = graph.undo_snapshot();
+    assert!(!graph.undo(), "nothing to undo yet");
+
+    let (value_a, divide) = (graph.nodes[0].id, graph.nodes[3].id);
+    graph.push_undo_checkpoint();
+    graph
+        .add_connection(value_a, 0, divide, 1)
+        .expect("ports exist");
+    let connected = graph.undo_snapshot();
+    assert_ne!(connected, original);
+
+    assert!(graph.undo());
+    assert_eq!(graph.undo_snapshot(), original);
+    assert!(graph.history.can_redo());
+    assert!(graph.redo());
+    assert_eq!(graph.undo_snapshot(), connected);
+    assert!(!graph.redo());
+
+    // A removal that cleared the selection brings the selection back on undo.
+    assert!(graph.select_node(divide));
+    graph.push_undo_checkpoint();
+    assert!(graph.remove_node(divide));
+    assert_eq!(graph.selected_node(), None);
+    assert!(graph.undo());
+    assert_eq!(graph.selected_node(), Some(divide));
+    assert!(graph.validate().is_ok());
+
+    // Repeating the newest checkpoint is not an edit, so redo survives it.
+    let newest = graph
+        .history
+        .undo
+        .back()
+        .cloned()
+        .expect("checkpoints remain");
+    graph.push_undo_snapshot(newest);
+    assert!(graph.history.can_redo());
+
+    // A new edit after an undo drops the redo branch.
+    graph.push_undo_checkpoint();
+    assert!(!graph.history.can_redo());
+}
+
+#[test]
+fn undo_history_keeps_the_latest_checkpoints() {
+    let mut graph = Graph::test_graph();
+    for step in 0..UNDO_DEPTH + 5 {
+        graph.pan.x = step as f32;
+        graph.push_undo_checkpoint();
+    }
+    // Repeating a checkpoint with nothing changed adds nothing, and undo skips the one
+    // matching the current state.
+    graph.push_undo_checkpoint();
+    let mut undone = 0;
+    while graph.undo() {
+        undone += 1;
+    }
+    assert_eq!(undone, UNDO_DEPTH - 1);
+    assert_eq!(graph.pan.x, 5.0, "the oldest checkpoints were dropped");
+}
+
+#[test]
+fn find_nodes_by_name_is_case_insensitive() {
+    let graph = Graph::test_graph();
+    let matches = graph.find_nodes_by_name("MATH");
+    assert_eq!(matches.len(), 2, "sum and divide should match");
+    assert_eq!(matches[0], graph.nodes[2].id);
+    assert_eq!(matches[1], graph.nodes[3].id);
+    assert_eq!(graph.find_nodes_by_name("").len(), graph.nodes.len());
+    assert!(graph.find_nodes_by_name("missing").is_empty());
+}
+
+#[test]
+fn rename_ports_matching_counts_changed_ports() {
+    let mut graph = Graph::test_graph();
+    let whole = ReplaceOptions {
+        match_case: true,
+        whole_name: true,
+    };
+
+    assert_eq!(
+        graph.rename_ports_matching("", "x", ReplaceOptions::default()),
+        0
+    );
+    assert_eq!(graph.rename_ports_matching("VALUE", "out", whole), 0);
+    // value_a, value_b, and output each have a "value" port.
+    assert_eq!(graph.rename_ports_matching("value", "out", whole), 3);
+    // Substring matches ignore case by default: "sum" input and output, "out" x3.
+    assert_eq!(
+        graph.rename_ports_matching("U", "u_", ReplaceOptions::default()),
+        5
+    );
+    assert_eq!(
+        graph.rename_ports_matching("b", "b", ReplaceOptions::default()),
+        0
+    );
+
+    let names: Vec<&str> = graph
+        .nodes
+        .iter()
+        .flat_map(|node| {
+            node.inputs
+                .iter()
+                .map(|input| input.name.as_str())
+                .chain(node.outputs.iter().map(|output| output.name.as_str()))
+        })
+        .collect();
+    assert!(names.contains(&"ou_t"));
+    assert!(names.contains(&"su_m"));
+    assert!(!names.contains(&"value"));
+    graph.validate().expect("renamed graph should stay valid");
+}
+
+#[test]
+fn export_connections_lists_every_wire() {
+    let mut graph = Graph::test_graph();
+    let wire_count = graph.connections().count();
+    assert_eq!(wire_count, 5);
+
+    let csv = graph.export_connections(ConnectionExportFormat::Csv);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "source_node,source_output,target_node,target_input"
+    );
+    assert_eq!(lines.len(), wire_count + 1);
+    assert!(lines.contains(&"math(sum),sum,math(divide),sum"));
+
+    let json = graph.export_connections(ConnectionExportFormat::Json);
+    let rows: Vec<HashMap<String, String>> =
+        serde_json::from_str(&json).expect("export should be valid JSON");
+    assert_eq!(rows.len(), wire_count);
+    assert!(rows.iter().any(|row| row["source_node"] == "math(sum)"
+        && row["target_node"] == "math(divide)"
+        && row["target_input"] == "sum"));
+
+    // Shared names get the id appended; commas are quoted.
+    for node in &mut graph.nodes {
+        if node.name.starts_with("value_") {
+            node.name = "value, raw".to_string();
+        }
+    }
+    let csv = graph.export_connections(ConnectionExportFormat::Csv);
+    let value_a_id = graph.nodes[0].id;
+    assert!(csv.contains(&format!("\"value, raw ({value_a_id})\",value,math(sum),a")));
+}
+
+#[test]
+fn ancestors_and_descendants() {
+    let graph = Graph::test_graph();
+    let value_a_id = graph.nodes[0].id;
+    let value_b_id = graph.nodes[1].id;
+    let output_id = graph.nodes[4].id;
+
+    let ancestors: HashSet<_> = graph.ancestors(output_id).into_iter().collect();
+    let expected: HashSet<_> = graph.nodes[..4].iter().map(|node| node.id).collect();
+    assert_eq!(ancestors, expected, "output is fed by every other node");
+    assert!(graph.ancestors(value_a_id).is_empty());
+
+    let descendants: HashSet<_> = graph.descendants(value_b_id).into_iter().collect();
+    let expected: HashSet<_> = graph.nodes[2..].iter().map(|node| node.id).collect();
+    assert_eq!(
+        descendants, expected,
+        "value_b feeds sum, divide and output"
+    );
+    assert!(graph.descendants(output_id).is_empty());
+}
+
+#[test]
+fn topological_order_sorts_sources_first() {
+    let mut graph = Graph::test_graph();
+    let order = graph
+        .topological_order()
+        .expect("test graph should be acyclic");
+    let names: Vec<&str> = order
+        .iter()
+        .map(|id| {
+            graph
+                .nodes
+                .iter()
+                .find(|node| node.id == *id)
+                .expect("ordered id should exist")
+                .name
+                .as_str()
+        })
+        .collect();
+    assert_eq!(
+        names,
+        ["value_a", "value_b", "math(sum)", "math(divide)", "output"]
+    );
+    let rank = |name: &str| {
+        names
+            .iter()
+            .position(|ordered| *ordered == name)
+            .expect("every node is ordered")
+    };
+    assert!(rank("value_a") < rank("math(sum)"));
+    assert!(rank("value_b") < rank("math(sum)"));
+    assert!(rank("math(sum)") < rank("math(divide)"));
+    assert!(rank("math(divide)") < rank("output"));
+
+    // Ties follow insertion order, so moving a root to the end moves it in the order.
+    let mut reordered = Graph::test_graph();
+    let value_a = reordered.nodes.remove(0);
+    reordered.nodes.push(value_a);
+    let first = reordered
+        .topological_order()
+        .expect("reordering keeps the graph acyclic")[0];
+    assert_eq!(first, reordered.nodes[0].id, "value_b now comes first");
+
+    let divide_id = graph
+        .nodes
+        .iter()
+        .find(|node| node.name == "math(divide)")
+        .expect("test graph should contain divide")
+        .id;
+    let sum = graph
+        .nodes
+        .iter_mut()
+        .find(|node| node.name == "math(sum)")
+        .expect("test graph should contain sum");
+    sum.inputs[0].connections = vec![Connection {
+        node_id: divide_id,
+        output_index: 0,
+        label: None,
+        output_name: None,
+    }];
+    let message = graph
+        .topological_order()
+        .expect_err("sum and divide feed each other")
+        .to_string();
+    assert!(message.starts_with("graph contains a cycle"), "{message}");
+    assert!(message.contains(&divide_id.to_string()), "{message}");
+}
+
+#[test]
+fn port_types_gate_connections_and_validation() {
+    let mut graph = Graph::test_graph();
+    let json = serde_json::to_string(&graph).expect("graph serializes");
+    assert!(
+        !json.contains("data_type"),
+        "untyped ports keep the old file format"
+    );
+    let (value_a, sum) = (graph.nodes[0].id, graph.nodes[2].id);
+    assert!(graph.check_port_types(value_a, 0, sum, 0).is_ok());
+
+    graph.nodes[0].outputs[0].data_type = "number".to_string();
+    graph.nodes[2].inputs[0].data_type = "number".to_string();
+    assert!(graph.check_port_types(value_a, 0, sum, 0).is_ok());
+    assert!(graph.validate().is_ok());
+    let hash = graph.content_hash();
+
+    graph.nodes[2].inputs[0].data_type = "text".to_string();
+    assert_ne!(graph.content_hash(), hash);
+    let err = graph
+        .check_port_types(value_a, 0, sum, 0)
+        .expect_err("number does not fit text");
+    assert_eq!(
+        err.to_string(),
+        "number output value does not fit text input a"
+    );
+    let message = graph
+        .validate()
+        .expect_err("the existing wire links number to text")
+        .to_string();
+    assert!(message.contains("incompatible port types"), "{message}");
+
+    let loaded: Graph =
+        serde_json::from_str(&serde_json::to_string(&graph).expect("graph serializes"))
+            .expect("typed graph loads");
+    assert_eq!(loaded.nodes[2].inputs[0].data_type, "text");
+    assert_eq!(loaded.nodes[0].outputs[0].data_type, "number");
+    assert_eq!(
+        loaded.nodes[1].outputs[0].data_type, "",
+        "untyped stays untyped"
+    );
+}
+
+#[test]
+fn validate_rejects_connection_cycles() {
+    let graph = Graph::test_graph();
+    assert!(graph.validate().is_ok(), "the test graph is acyclic");
+    let sum_id = graph.nodes[2].id;
+    let divide_id = graph.nodes[3].id;
+    assert!(graph.connection_creates_cycle(divide_id, sum_id));
+    assert!(graph.connection_creates_cycle(sum_id, sum_id));
+    assert!(!graph.connection_creates_cycle(sum_id, divide_id));
+
+    let mut looped = Graph::default();
+    let mut add = |spec: &str| {
+        looped
+            .add_node_from_spec(spec)
+            .expect("node spec should be valid")
+    };
+    let first = add(r#"{"name": "first", "inputs": ["in"], "outputs": ["out"]}"#);
+    let second = add(r#"{"name": "second", "inputs": ["in"], "outputs": ["out"]}"#);
+    looped
+        .add_connection(first, 0, second, 0)
+        .expect("ports exist");
+    assert!(looped.validate().is_ok());
+    looped
+        .add_connection(second, 0, first, 0)
+        .expect("ports exist");
+    let message = looped
+        .validate()
+        .expect_err("a feedback loop is a cycle")
+        .to_string();
+    assert!(message.starts_with("graph contains a cycle"), "{message}");
+    assert!(message.contains(&first.to_string()), "{message}");
+    assert!(message.contains(&second.to_string()), "{message}");
+}
+
+#[test]
+fn screen_pinned_nodes_keep_their_screen_position() {
+    let mut graph = Graph::test_graph();
+    graph.zoom = 2.0;
+    graph.pan = egui::vec2(30.0, -10.0);
+    let node_id = graph.nodes[0].id;
+    let screen = |graph: &Graph| graph.nodes[0].pos.to_vec2() * graph.zoom + graph.pan;
+    let before = screen(&graph);
+
+    graph.set_screen_pinned(node_id, true);
+    assert!(graph.nodes[0].screen_pinned);
+    assert_eq!(
+        graph.nodes[0].pos.to_vec2(),
+        before,
+        "pinned pos is the screen offset"
+    );
+
+    graph.nodes[1].pos += egui::vec2(1.0e6, 0.0);
+    graph.nodes[0].pos += egui::vec2(5.0e6, 0.0);
+    assert!(graph.exceeds_coord_limit());
+    let offset = graph.nodes[0].pos;
+    graph.recenter();
+    assert_eq!(
+        graph.nodes[0].pos, offset,
+        "recenter leaves pinned nodes alone"
+    );
+    graph.nodes[0].pos -= egui::vec2(5.0e6, 0.0);
+
+    graph.set_screen_pinned(node_id, false);
+    assert!(!graph.nodes[0].screen_pinned);
+    assert!((screen(&graph) - before).length() < 1e-3);
+}
+
+#[test]
+fn recenter_keeps_view_stable() {
+    let comment_at = |pos| Comment {
+        id: Uuid::new_v4(),
+        text: "far off".to_string(),
+        pos,
+        size: egui::vec2(160.0, 80.0),
+        image: None,
+    };
+    let mut graph = Graph::test_graph();
+    for node in &mut graph.nodes {
+        node.pos += egui::vec2(5.0e6, -3.0e6);
+    }
+    for comment in &mut graph.comments {
+        comment.pos += egui::vec2(5.0e6, -3.0e6);
+    }
+    graph
+        .comments
+        .push(comment_at(egui::pos2(5.0e6 + 300.0, -3.0e6 - 200.0)));
+    graph.zoom = 2.0;
+    graph.pan = egui::vec2(-1.0e7, 6.0e6);
+    assert!(graph.exceeds_coord_limit());
+
+    let screen = |graph: &Graph, pos: egui::Pos2| graph.pan + pos.to_vec2() * graph.zoom;
+    let node_before = screen(&graph, graph.nodes[2].pos);
+    let comment_before = screen(&graph, graph.comments[0].pos);
+    let translation = graph.recenter();
+
+    assert!(!graph.exceeds_coord_limit());
+    assert!(translation.x < -4.0e6 && translation.y > 2.0e6);
+    assert!((screen(&graph, graph.nodes[2].pos) - node_before).length() < 1.0);
+    assert!(
+        (screen(&graph, graph.comments[0].pos) - comment_before).length() < 1.0,
+        "comments move with the nodes"
+    );
+    assert!(graph.validate().is_ok());
+
+    // A far-off comment alone is enough to need recentering.
+    let mut graph = Graph::default();
+    graph.comments.push(comment_at(egui::pos2(-2.0e6, 0.0)));
+    assert!(graph.exceeds_coord_limit());
+    graph.recenter();
+    assert!(!graph.exceeds_coord_limit());
+}
+
+#[test]
+fn recenter_near_the_f32_limit_falls_back_to_zero_pan() {
+    let mut graph = Graph::test_graph();
+    graph.comments.clear();
+    for node in &mut graph.nodes {
+        node.pos += egui::vec2(3.0e38, 0.0);
+    }
+    graph.zoom = 2.0;
+    assert!(graph.validate().is_ok(), "positions are finite");
+
+    graph.recenter();
+    assert_eq!(
+        graph.pan,
+        egui::Vec2::ZERO,
+        "the kept-view pan would overflow"
+    );
+    assert!(!graph.exceeds_coord_limit());
+    assert!(graph.validate().is_ok());
+}
+
+#[test]
+fn templates_build_valid_graphs_with_fresh_ids() {
+    for template in GraphTemplate::ALL {
+        let first = template.build();
+        let second = template.build();
+        assert!(first.validate().is_ok(), "{template:?} must validate");
+        assert!(first.nodes.iter().any(|node| node.terminal));
+        assert!(first.connections().count() > 0);
+        assert_ne!(first.id, second.id);
+        let first_ids: HashSet<Uuid> = first.nodes.iter().map(|node| node.id).collect();
+        assert!(
+            second
+                .nodes
+                .iter()
+                .all(|node| !first_ids.contains(&node.id))
+        );
+    }
+}
+
+#[test]
+fn select_and_remove_ignore_missing_ids() {
+    let mut graph = Graph::test_graph();
+    let first = graph.nodes[0].id;
+    assert!(graph.select_node(first));
+
+    let stale = Uuid::new_v4();
+    assert!(!graph.select_node(stale));
+    assert!(!graph.add_to_selection(stale));
+    assert_eq!(graph.selected_node(), Some(first));
+    assert!(!graph.remove_node(stale));
+    assert_eq!(graph.nodes.len(), 5);
+    assert_eq!(graph.selected_node(), Some(first));
+
+    assert!(graph.remove_node(first));
+    assert!(!graph.remove_node(first));
+    assert!(graph.selected_nodes.is_empty());
+}
+
+#[test]
+fn selection_set_validates_and_round_trips() {
+    let mut graph = Graph::test_graph();
+    let (first, third) = (graph.nodes[0].id, graph.nodes[2].id);
+    assert!(graph.select_node(first));
+    assert!(graph.add_to_selection(third));
+    assert_eq!(graph.selected_node(), None, "no primary node among several");
+    assert!(graph.validate().is_ok());
+
+    for format in [GraphFormat::Json, GraphFormat::Yaml, GraphFormat::Toml] {
+        let text = graph
+            .serialize(format, JsonOptions::default())
+            .expect("graph should serialize");
+        let restored = Graph::deserialize(format, &text).expect("graph should load");
+        assert_eq!(restored.selected_nodes, graph.selected_nodes, "{format:?}");
+    }
+
+    graph.select_all();
+    assert_eq!(graph.selected_nodes.len(), graph.nodes.len());
+    graph.selected_nodes.insert(Uuid::new_v4());
+    assert!(graph.validate().is_err());
+}
+
+#[test]
+fn add_node_from_spec_validates_ports() {
+    let mut graph = Graph::default();
+    let node_id = graph
+        .add_node_from_spec(r#"{"name": "sum", "inputs": ["a", "b"], "outputs": ["sum"]}"#)
+        .expect("valid spec should add a node");
+    let node = &graph.nodes[0];
+    assert_eq!(node.id, node_id);
+    assert_eq!(node.name, "sum");
+    assert_eq!(node.inputs.len(), 2);
+    assert_eq!(node.outputs[0].name, "sum");
+    assert!(!node.terminal);
+    assert_eq!(node.pos, egui::Pos2::ZERO);
+    // New inputs take one source, so dropping another wire replaces it.
+    assert!(
+        node.inputs
+            .iter()
+            .all(|input| input.max_connections == Some(1))
+    );
+
+    let other_id = graph
+        .add_node_from_spec(r#"{"name": "sum", "inputs": ["a"], "terminal": true}"#)
+        .expect("valid spec should add a node");
+    assert_ne!(other_id, node_id);
+    assert!(graph.validate().is_ok());
+
+    let typed = Node::from_spec(
+        r#"{"name": "scale", "inputs": [{"name": "in", "data_type": "number"}, "factor"],
+            "outputs": [{"name": "out", "data_type": "number"}]}"#,
+    )
+    .expect("typed ports are valid");
+    assert_eq!(typed.inputs[0].data_type, "number");
+    assert_eq!(typed.inputs[1].data_type, "");
+    assert_eq!(typed.outputs[0].data_type, "number");
+
+    let error = |json: &str| {
+        Node::from_spec(json)
+            .expect_err("spec should be rejected")
+            .to_string()
+    };
+    assert_eq!(
+        error(r#"{"name": "x", "inputs": ["a", " "]}"#),
+        "node spec input 1 has an empty name"
+    );
+    assert_eq!(
+        error(r#"{"name": "x", "outputs": [""]}"#),
+        "node spec output 0 has an empty name"
+    );
+    assert_eq!(error(r#"{"name": ""}"#), "node spec name must not be empty");
+    assert!(error(r#"{"name": "x", "color": "red"}"#).starts_with("invalid node spec"));
+    assert_eq!(
+        error(r#"{"name": "x", "inputs": [{"name": " ", "data_type": "number"}]}"#),
+        "node spec input 0 has an empty name"
+    );
+    assert!(
+        error(r#"{"name": "x", "inputs": [{"name": "a", "type": "number"}]}"#)
+            .starts_with("invalid node spec")
+    );
+    assert_eq!(graph.nodes.len(), 2);
+}
+
+#[test]
+fn connect_many_is_all_or_nothing() {
+    let mut graph = Graph::default();
+    let mut add = |spec: &str| {
+        graph
+            .add_node_from_spec(spec)
+            .expect("node spec should be valid")
+    };
+    let source = add(r#"{"name": "source", "outputs": ["a", "b"]}"#);
+    let sum = add(r#"{"name": "sum", "inputs": ["x", "y"], "outputs": ["sum"]}"#);
+    let sink = add(r#"{"name": "sink", "inputs": ["value"], "terminal": true}"#);
+    let wired = |graph: &Graph| graph.connections().count();
+
+    let invalid = [(source, 0, sum, 0), (source, 5, sum, 1), (sum, 0, sink, 0)];
+    assert!(graph.connect_many(&invalid).is_err());
+    assert_eq!(wired(&graph), 0);
+    assert!(
+        graph
+            .connect_many(&[(source, 0, sum, 0), (source, 1, sum, 0)])
+            .is_err()
+    );
+    assert!(graph.connect_many(&[(Uuid::new_v4(), 0, sum, 0)]).is_err());
+    assert_eq!(wired(&graph), 0);
+
+    graph
+        .connect_many(&[(source, 0, sum, 0), (source, 1, sum, 1), (sum, 0, sink, 0)])
+        .expect("valid edges should connect");
+    assert_eq!(wired(&graph), 3);
+    let y = graph.nodes[1].inputs[1]
+        .connections
+        .first()
+        .expect("sum.y should be wired");
+    assert_eq!((y.node_id, y.output_index), (source, 1));
+    assert_eq!(y.output_name.as_deref(), Some("b"));
+    assert!(graph.validate().is_ok());
+}
+
+#[test]
+fn connect_many_refuses_cycles_and_keeps_fan_in_sources() {
+    let mut graph = Graph::test_graph();
+    let (sum, divide) = (graph.nodes[2].id, graph.nodes[3].id);
+    let snapshot = graph.undo_snapshot();
+    let message = graph
+        .connect_many(&[(divide, 0, sum, 0)])
+        .expect_err("divide already reads sum")
+        .to_string();
+    assert!(message.starts_with("graph contains a cycle"), "{message}");
+    assert!(
+        graph.undo_snapshot() == snapshot,
+        "a refused batch changes nothing"
+    );
+
+    let value_a = graph.nodes[0].id;
+    graph.nodes[0].outputs[0].data_type = "text".to_string();
+    graph.nodes[3].inputs[1].data_type = "number".to_string();
+    assert!(graph.connect_many(&[(value_a, 0, divide, 1)]).is_err());
+    graph.nodes[0].outputs[0].data_type.clear();
+    graph.nodes[3].inputs[1].data_type.clear();
+    graph.nodes[4].inputs[0].max_connections = None;
+    let output = graph.nodes[4].id;
+    graph
+        .connect_many(&[(value_a, 0, output, 0)])
+        .expect("fan-in takes another source");
+    assert_eq!(graph.nodes[4].inputs[0].connections.len(), 2);
+    assert!(graph.validate().is_ok());
+}
+
+#[test]
+fn add_connection_fans_in_up_to_max_connections() {
+    let mut graph = Graph::default();
+    let mut add = |spec: &str| {
+        graph
+            .add_node_from_spec(spec)
+            .expect("node spec should be valid")
+    };
+    let source = add(r#"{"name": "source", "outputs": ["a", "b", "c"]}"#);
+    let merge = add(r#"{"name": "merge", "inputs": ["values"]}"#);
+    // Spec inputs take one source; opt this one into unlimited fan-in.
+    graph.nodes[1].inputs[0].max_connections = None;
+    let sources = |graph: &Graph| -> Vec<usize> {
+        graph.nodes[1].inputs[0]
+            .connections
+            .iter()
+            .map(|connection| connection.output_index)
+            .collect()
+    };
+
+    for output_index in 0..3 {
+        let displaced = graph
+            .add_connection(source, output_index, merge, 0)
+            .expect("ports exist");
+        assert!(displaced.is_none(), "an uncapped input keeps every source");
+    }
+    assert_eq!(sources(&graph), vec![0, 1, 2]);
+    graph
+        .add_connection(source, 1, merge, 0)
+        .expect("ports exist");
+    assert_eq!(
+        sources(&graph),
+        vec![0, 1, 2],
+        "an existing wire is a no-op"
+    );
+    assert!(graph.validate().is_ok());
+
+    graph.nodes[1].inputs[0].max_connections = Some(2);
+    assert!(graph.validate().is_err(), "three sources exceed the cap");
+    graph.nodes[1].inputs[0].connections.truncate(2);
+    let displaced = graph
+        .add_connection(source, 2, merge, 0)
+        .expect("ports exist")
+        .expect("a full input displaces its latest source");
+    assert_eq!(displaced.output_index, 1);
+    assert_eq!(sources(&graph), vec![0, 2]);
+
+    let duplicate = graph.nodes[1].inputs[0].connections[0].clone();
+    graph.nodes[1].inputs[0].connections[1] = duplicate;
+    assert!(
+        graph.validate().is_err(),
+        "the same output twice is rejected"
+    );
+    assert!(graph.add_connection(source, 9, merge, 0).is_err());
+}
+
+#[test]
+fn add_node_respects_insert_order() {
+    let named = |name: &str| Node {
+        name: name.to_string(),
+        ..Node::default()
+    };
+
+    let mut graph = Graph::default();
+    for name in ["b", "c", "a"] {
+        graph.add_node(named(name), InsertOrder::Append);
+    }
+    let names: Vec<_> = graph.nodes.iter().map(|node| node.name.as_str()).collect();
+    assert_eq!(names, ["b", "c", "a"]);
+
+    let mut graph = Graph::default();
+    for name in ["b", "c", "a", "b"] {
+        graph.add_node(named(name), InsertOrder::SortedByName);
+    }
+    let names: Vec<_> = graph.nodes.iter().map(|node| node.name.as_str()).collect();
+    assert_eq!(names, ["a", "b", "b", "c"]);
+
+    let mut graph = Graph::default();
+    for _ in 0..5 {
+        graph.add_node(Node::default(), InsertOrder::SortedById);
+    }
+    assert!(graph.nodes.windows(2).all(|pair| pair[0].id < pair[1].id));
+    assert!(graph.validate().is_ok());
+}
+
+#[test]
+fn clone_fresh_keeps_topology_with_disjoint_ids() {
+    let mut graph = Graph::test_graph();
+    graph.select_node(graph.nodes[3].id);
+    graph.add_to_selection(graph.nodes[1].id);
+    let clone = graph.clone_fresh();
+    assert!(clone.validate().is_ok());
+
+    let ids = |graph: &Graph| -> HashSet<Uuid> {
+        std::iter::once(graph.id)
+            .chain(graph.nodes.iter().map(|node| node.id))
+            .chain(graph.comments.iter().map(|comment| comment.id))
+            .chain(graph.groups.iter().map(|group| group.id))
+            .collect()
+    };
+    assert!(ids(&graph).is_disjoint(&ids(&clone)));
+    assert_eq!(ids(&clone).len(), ids(&graph).len());
+
+    // Same wiring, expressed as node positions in `nodes`.
+    let topology = |graph: &Graph| -> Vec<(usize, usize, usize, usize)> {
+        let index = |id: Uuid| {
+            graph
+                .nodes
+                .iter()
+                .position(|node| node.id == id)
+                .expect("connection source should exist")
+        };
+        graph
+            .connections()
+            .map(|(target, input_index, connection)| {
+                (
+                    index(connection.node_id),
+                    connection.output_index,
+                    index(target.id),
+                    input_index,
+                )
+            })
+            .collect()
+    };
+    assert_eq!(topology(&clone), topology(&graph));
+    assert_eq!(
+        clone.selected_nodes,
+        HashSet::from([clone.nodes[1].id, clone.nodes[3].id])
+    );
+    let members = |graph: &Graph| -> Vec<Option<usize>> {
+        graph.groups[0]
+            .node_ids
+            .iter()
+            .map(|id| graph.nodes.iter().position(|node| node.id == *id))
+            .collect()
+    };
+    assert_eq!(members(&clone), members(&graph));
+
+    graph.selected_nodes.clear();
+    assert!(graph.clone_fresh().selected_nodes.is_empty());
+}
+
+#[test]
+fn clipboard_text_has_readable_and_native_parts() {
+    let graph = Graph::test_graph();
+    let sum = graph
+        .nodes
+        .iter()
+        .find(|node| node.name == "math(sum)")
+        .expect("test graph should contain sum");
+
+    let text = graph.clipboard_text(&[sum.id]);
+    let mut lines = text.lines();
+    assert_eq!(
+        lines.next(),
+        Some("math(sum): a <- value_a.value, b <- value_b.value")
+    );
+    assert!(
+        lines
+            .next()
+            .is_some_and(|line| line.starts_with(CLIPBOARD_MARKER))
+    );
+    assert_eq!(lines.next(), None);
+}
+
+#[test]
+fn paste_clipboard_text_remaps_ids() {
+    let mut graph = Graph::test_graph();
+    let ids: Vec<Uuid> = graph
+        .nodes
+        .iter()
+        .filter(|node| node.name == "math(sum)" || node.name == "math(divide)")
+        .map(|node| node.id)
+        .collect();
+    let text = graph.clipboard_text(&ids);
+    let node_count = graph.nodes.len();
+
+    let pasted = graph
+        .paste_clipboard_text(&text, egui::vec2(10.0, 20.0))
+        .expect("native clipboard text should paste");
+    assert_eq!(pasted.len(), 2);
+    assert_eq!(graph.nodes.len(), node_count + 2);
+    assert!(pasted.iter().all(|id| !ids.contains(id)));
+    assert!(graph.validate().is_ok());
+
+    // The pasted divide reads from the pasted sum, and still from the original value_b.
+    let pasted_divide = graph
+        .nodes
+        .iter()
+        .find(|node| node.id == pasted[1])
+        .expect("pasted divide should exist");
+    let first_source = pasted_divide.inputs[0]
+        .connections
+        .first()
+        .expect("internal connection should be kept");
+    assert_eq!(first_source.node_id, pasted[0]);
+    let second_source = pasted_divide.inputs[1]
+        .connections
+        .first()
+        .expect("external connection should be kept");
+    assert!(!pasted.contains(&second_source.node_id));
+
+    assert!(
+        graph
+            .paste_clipboard_text("math(sum): a <- value_a.value", egui::Vec2::ZERO)
+            .is_none()
+    );
+    assert_eq!(graph.nodes.len(), node_count + 2);
+}
+
+#[test]
+fn copy_nodes_duplicates_only_internal_wires() {
+    let mut graph = Graph::test_graph();
+    let (sum, divide) = (graph.nodes[2].id, graph.nodes[3].id);
+    let payload = graph.copy_nodes(&[sum, divide]);
+    let wires = graph.connections().count();
+
+    let pasted = graph
+        .paste_nodes(&payload, egui::vec2(30.0, 40.0))
+        .expect("copied nodes should paste");
+    assert_eq!(pasted.len(), 2);
+    assert!(pasted.iter().all(|id| *id != sum && *id != divide));
+    assert!(graph.validate().is_ok());
+    let node = |id: Uuid| {
+        graph
+            .nodes
+            .iter()
+            .find(|node| node.id == id)
+            .expect("node exists")
+    };
+    assert_eq!(node(pasted[0]).pos, node(sum).pos + egui::vec2(30.0, 40.0));
+    // Only divide's wire from sum came along, now from the pasted sum.
+    let sources: Vec<Uuid> = node(pasted[1])
+        .inputs
+        .iter()
+        .flat_map(|input| &input.connections)
+        .map(|connection| connection.node_id)
+        .collect();
+    assert_eq!(sources, vec![pasted[0]]);
+    assert!(
+        node(pasted[0])
+            .inputs
+            .iter()
+            .all(|input| input.connections.is_empty())
+    );
+    assert_eq!(graph.connections().count(), wires + 1);
+
+    assert!(graph.paste_nodes("math(sum)", egui::Vec2::ZERO).is_err());
+    assert_eq!(graph.nodes.len(), 7);
+}
+
+#[test]
+fn paste_rejects_corrupt_payloads() {
+    let mut graph = Graph::test_graph();
+    let node_count = graph.nodes.len();
+    let sum = graph.nodes[2].id;
+    let single = graph.copy_nodes(&[sum]);
+    let fragment: serde_json::Value = serde_json::from_str(&single).expect("payload is JSON");
+    let node = fragment["nodes"][0].clone();
+
+    let duplicate = serde_json::json!({ "nodes": [node.clone(), node.clone()] }).to_string();
+    let err = graph
+        .paste_nodes(&duplicate, egui::Vec2::ZERO)
+        .expect_err("duplicate ids must be rejected");
+    assert!(err.to_string().contains("twice"));
+    let text = format!("{CLIPBOARD_MARKER}{duplicate}");
+    assert!(
+        graph
+            .paste_clipboard_text(&text, egui::Vec2::ZERO)
+            .is_none()
+    );
+
+    let mut looped = node.clone();
+    looped["inputs"][0]["connections"] =
+        serde_json::json!([{ "node_id": node["id"].clone(), "output_index": 0 }]);
+    let looped = serde_json::json!({ "nodes": [looped] }).to_string();
+    let err = graph
+        .paste_nodes(&looped, egui::Vec2::ZERO)
+        .expect_err("a node wired to itself must be rejected");
+    assert!(err.to_string().contains("cycle"), "{err}");
+    let text = format!("{CLIPBOARD_MARKER}{looped}");
+    assert!(
+        graph
+            .paste_clipboard_text(&text, egui::Vec2::ZERO)
+            .is_none()
+    );
+
+    let mut dangling = node;
+    dangling["inputs"][0]["connections"] =
+        serde_json::json!([{ "node_id": Uuid::new_v4(), "output_index": 0 }]);
+    let dangling = serde_json::json!({ "nodes": [dangling] }).to_string();
+    let err = graph
+        .paste_nodes(&dangling, egui::Vec2::ZERO)
+        .expect_err("wires leaving the payload must be rejected");
+    assert!(err.to_string().contains("does not contain"));
+    assert_eq!(graph.nodes.len(), node_count);
+    assert!(graph.validate().is_ok());
+}
+
+#[test]
+fn duplicate_node_copies_ports_without_wires() {
+    let mut graph = Graph::test_graph();
+    let divide = graph.nodes[3].id;
+    graph.nodes[3].cache_output = true;
+    let copy = graph.duplicate_node(divide);
+    assert_ne!(copy, divide);
+    assert!(graph.validate().is_ok());
+
+    let source = &graph.nodes[3];
+    let duplicate = graph.nodes.last().expect("duplicate was appended");
+    assert_eq!(duplicate.id, copy);
+    assert_eq!(duplicate.name, source.name);
+    assert_eq!(duplicate.pos, source.pos + DUPLICATE_OFFSET);
+    assert!(duplicate.cache_output && duplicate.terminal == source.terminal);
+    let names = |ports: &[Output]| -> Vec<String> {
+        ports.iter().map(|output| output.name.clone()).collect()
+    };
+    assert_eq!(names(&duplicate.outputs), names(&source.outputs));
+    assert_eq!(duplicate.inputs.len(), source.inputs.len());
+    assert!(
+        duplicate
+            .inputs
+            .iter()
+            .all(|input| input.connections.is_empty())
+    );
+    assert!(
+        source
+            .inputs
+            .iter()
+            .all(|input| !input.connections.is_empty())
+    );
+}
+
+#[test]
+fn node_templates_instantiate_with_their_ports() {
+    let mut registry = NodeTemplateRegistry::default();
+    let names: Vec<&str> = registry
+        .templates()
+        .iter()
+        .map(|template| template.name.as_str())
+        .collect();
+    assert_eq!(names, ["value", "math(sum)", "math(divide)", "output"]);
+
+    let port_names = |node: &Node| -> (Vec<String>, Vec<String>) {
+        (
+            node.inputs.iter().map(|input| input.name.clone()).collect(),
+            node.outputs
+                .iter()
+                .map(|output| output.name.clone())
+                .collect(),
+        )
+    };
+    // The defaults match the nodes of the sample graph.
+    let sample = Graph::test_graph();
+    for name in ["value", "math(sum)", "math(divide)", "output"] {
+        let node = registry.instantiate(name, egui::pos2(10.0, 20.0));
+        let original = sample
+            .nodes
+            .iter()
+            .find(|node| node.name == name || (name == "value" && node.name == "value_a"))
+            .expect("sample graph has the node");
+        assert_eq!(port_names(&node), port_names(original), "{name}");
+        assert_eq!(node.terminal, original.terminal, "{name}");
+        assert_eq!(node.pos, egui::pos2(10.0, 20.0));
+        assert!(
+            node.inputs
+                .iter()
+                .all(|input| input.max_connections == Some(1)),
+            "{name}"
+        );
+    }
+
+    registry.register(NodeTemplate {
+        name: "math(sum)".to_string(),
+        inputs: vec!["x".into(), "y".into(), PortTemplate::new("z", "number")],
+        outputs: vec![PortTemplate::new("total", "number")],
+        terminal: false,
+    });
+    assert_eq!(registry.templates().len(), 4, "same name replaces in place");
+    let sum = registry.instantiate("math(sum)", egui::Pos2::ZERO);
+    assert_eq!(
+        port_names(&sum),
+        (
+            vec!["x".to_string(), "y".to_string(), "z".to_string()],
+            vec!["total".to_string()]
+        )
+    );
+    let input_types: Vec<&str> = sum
+        .inputs
+        .iter()
+        .map(|input| input.data_type.as_str())
+        .collect();
+    assert_eq!(input_types, ["", "", "number"]);
+    assert_eq!(sum.outputs[0].data_type, "number");
+}
+
+#[test]
+fn added_nodes_get_unique_names() {
+    let mut graph = Graph::test_graph();
+    let registry = NodeTemplateRegistry::default();
+    let name = |graph: &Graph, id: Uuid| {
+        let node = graph.nodes.iter().find(|node| node.id == id);
+        node.expect("node exists").name.clone()
+    };
+    let first =
+        graph.add_node_with_unique_name(registry.instantiate("math(sum)", egui::Pos2::ZERO));
+    let second =
+        graph.add_node_with_unique_name(registry.instantiate("math(sum)", egui::Pos2::ZERO));
+    assert_eq!(
+        name(&graph, first),
+        "math(sum 2)",
+        "test graph already has math(sum)"
+    );
+    assert_eq!(name(&graph, second), "math(sum 3)");
+    assert_eq!(
+        graph.nodes.last().expect("node added").category(),
+        Some("math")
+    );
+    let output = graph.add_node_with_unique_name(registry.instantiate("output", egui::Pos2::ZERO));
+    assert_eq!(name(&graph, output), "output 2");
+    assert!(graph.validate().is_ok());
+}
+
+#[test]
+fn terminal_summary_resolves_sources() {
+    let graph = Graph::test_graph();
+    let summary = graph.terminal_summary();
+    assert_eq!(summary.len(), 1);
+
+    let terminal = &summary[0];
+    assert_eq!(terminal.name, "output");
+    assert_eq!(terminal.inputs.len(), 1);
+    let [source] = &terminal.inputs[0].sources[..] else {
+        panic!("output terminal should have one source");
+    };
+    let divide = graph
+        .nodes
+        .iter()
+        .find(|node| node.name == "math(divide)")
+        .expect("test graph should contain divide");
+    assert_eq!(source.node_id, divide.id);
+    assert_eq!(source.output_name, "divide");
+}
+
+#[test]
+fn bypassed_nodes_pass_their_first_input_through() {
+    let mut graph = Graph::test_graph();
+    let id_of = |graph: &Graph, name: &str| {
+        graph
+            .nodes
+            .iter()
+            .find(|node| node.name == name)
+            .expect("test graph node should exist")
+            .id
+    };
+    let (sum, divide) = (id_of(&graph, "math(sum)"), id_of(&graph, "math(divide)"));
+    let terminal_source = |graph: &Graph| {
+        graph.terminal_summary()[0].inputs[0]
+            .sources
+            .first()
+            .map(|source| source.node_name.clone())
+    };
+
+    graph.nodes[3].bypass = true;
+    assert_eq!(graph.resolve_output(divide, 0), Some((sum, 0)));
+    assert_eq!(terminal_source(&graph).as_deref(), Some("math(sum)"));
+
+    graph.nodes[2].bypass = true;
+    assert_eq!(terminal_source(&graph).as_deref(), Some("value_a"));
+
+    graph.nodes[2].inputs[0].connections.clear();
+    assert_eq!(graph.resolve_output(divide, 0), None);
+    assert_eq!(terminal_source(&graph), None);
+}
+
+#[test]
+fn diagnostics_report_warnings() {
+    let mut graph = Graph::test_graph();
+    let no_rects = HashMap::new();
+    assert!(
+        graph.diagnostics(&no_rects).is_empty(),
+        "test graph should be clean"
+    );
+
+    let orphan = Node {
+        name: "orphan".to_string(),
+        inputs: vec![
+            Input {
+                name: "x".to_string(),
+                data_type: String::new(),
+                connections: Vec::new(),
+                max_connections: None,
+            },
+            Input {
+                name: "x".to_string(),
+                data_type: String::new(),
+                connections: Vec::new(),
+                max_connections: None,
+            },
+        ],
+        ..Node::default()
+    };
+    let orphan_id = orphan.id;
+    graph.nodes.push(orphan);
+
+    assert_eq!(graph.unreachable_nodes(), vec![orphan_id]);
+    let diagnostics = graph.diagnostics(&no_rects);
+    assert_eq!(
+        diagnostics.len(),
+        4,
+        "unreachable, duplicate, and two unconnected"
+    );
+    assert!(
+        diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.severity == Severity::Warning
+                && diagnostic.node_id == Some(orphan_id))
+    );
+}
+
+#[test]
+fn diagnostics_report_overlapping_nodes() {
+    let graph = Graph::test_graph();
+    let size = egui::vec2(100.0, 60.0);
+    let mut rects: HashMap<Uuid, egui::Rect> = graph
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(index, node)| {
+            let min = egui::pos2(index as f32 * size.x, 0.0);
+            (node.id, egui::Rect::from_min_size(min, size))
+        })
+        .collect();
+    assert!(
+        graph.overlapping_nodes(&rects).is_empty(),
+        "nodes side by side only touch"
+    );
+
+    let (value_a, divide) = (graph.nodes[0].id, graph.nodes[3].id);
+    rects.insert(
+        divide,
+        egui::Rect::from_min_size(egui::pos2(-50.0, 30.0), size),
+    );
+    assert_eq!(graph.overlapping_nodes(&rects), vec![(value_a, divide)]);
+    let overlaps: Vec<Diagnostic> = graph
+        .diagnostics(&rects)
+        .into_iter()
+        .filter(|diagnostic| diagnostic.message.contains("overlaps"))
+        .collect();
+    assert_eq!(
+        overlaps,
+        vec![Diagnostic {
+            severity: Severity::Warning,
+            node_id: Some(divide),
+            message: "node overlaps 'value_a'".to_string(),
+        }]
+    );
+    assert_eq!(
+        graph.node_health()[&divide],
+        NodeHealth::Ok,
+        "overlap is a layout nit, not a health problem"
+    );
+}
+
+#[test]
+fn text_formats_start_with_a_version_comment() {
+    let graph = Graph::test_graph();
+    let header = format!("# scenarium graph v{GRAPH_SCHEMA_VERSION}\n");
+    for format in [GraphFormat::Toml, GraphFormat::Yaml] {
+        let serialized = graph
+            .serialize(format, JsonOptions::default())
+            .expect("graph should serialize");
+        assert!(serialized.starts_with(&header), "{format:?}: {serialized}");
+        let restored = Graph::deserialize(format, &serialized).expect("header is a comment");
+        assert_eq!(restored.nodes.len(), graph.nodes.len());
+
+        // Hand-edited files may drop the header or use another version comment.
+        let without = serialized.strip_prefix(&header).expect("header is present");
+        Graph::deserialize(format, without).expect("header is optional");
+        Graph::deserialize(format, &format!("# scenarium graph v99\n\n{without}"))
+            .expect("unknown header versions are ignored");
+    }
+    let json = graph
+        .serialize(GraphFormat::Json, JsonOptions::default())
+        .expect("graph should serialize");
+    assert!(json.starts_with('{'));
+}
+
+#[test]
+fn compact_json_round_trips_like_pretty_json() {
+    let graph = Graph::test_graph();
+    let pretty = graph
+        .serialize(GraphFormat::Json, JsonOptions::default())
+        .expect("graph should serialize");
+    let compact = graph
+        .serialize(GraphFormat::Json, JsonOptions { compact: true })
+        .expect("graph should serialize");
+    assert!(pretty.contains('\n'));
+    assert!(!compact.contains('\n'));
+    assert!(compact.len() < pretty.len());
+
+    let restored = Graph::deserialize(GraphFormat::Json, &compact).expect("compact JSON parses");
+    assert_eq!(
+        restored
+            .serialize(GraphFormat::Json, JsonOptions::default())
+            .expect("graph should serialize"),
+        pretty
+    );
+}
+
+#[test]
+fn node_health_grades_each_node() {
+    let mut graph = Graph::test_graph();
+    let health = graph.node_health();
+    assert_eq!(health.len(), graph.nodes.len());
+    assert!(health.values().all(|level| *level == NodeHealth::Ok));
+
+    let orphan = Node::default();
+    let orphan_id = orphan.id;
+    graph.nodes.push(orphan);
+    let sum_id = graph.nodes[2].id;
+    let divide_id = graph.nodes[3].id;
+    graph.nodes[2].inputs[1].connections.clear();
+    graph.nodes[3].inputs[0]
+        .connections
+        .first_mut()
+        .expect("divide is wired")
+        .output_index = 7;
+
+    let health = graph.node_health();
+    assert_eq!(health[&sum_id], NodeHealth::Warning, "unconnected input");
+    assert_eq!(health[&divide_id], NodeHealth::Error, "invalid connection");
+    assert_eq!(health[&orphan_id], NodeHealth::Error, "unreachable");
+    assert_eq!(health[&graph.nodes[0].id], NodeHealth::Ok);
+}
+
+#[test]
+fn misordered_connections_are_reported_and_fixed() {
+    let mut graph = Graph::test_graph();
+    assert!(graph.misordered_connections().is_empty());
+
+    // Insert an output ahead of "sum": divide's index 0 now names the wrong output.
+    let sum = graph
+        .nodes
+        .iter_mut()
+        .find(|node| node.name == "math(sum)")
+        .expect("test graph should contain sum");
+    sum.outputs.insert(
+        0,
+        Output {
+            name: "carry".to_string(),
+            data_type: String::new(),
+            last_value: None,
+        },
+    );
+    let sum_id = sum.id;
+
+    let misordered = graph.misordered_connections();
+    assert_eq!(misordered.len(), 1);
+    assert_eq!(misordered[0].output_index, 0);
+    assert_eq!(misordered[0].named_index, 1);
+    assert!(graph.validate().is_ok(), "index is still in range");
+    assert!(
+        graph
+            .diagnostics(&HashMap::new())
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Warning
+                && diagnostic.node_id == Some(misordered[0].node_id)
+                && diagnostic.message.contains("recorded output name"))
+    );
+
+    assert_eq!(graph.fix_connection_output_indices(), 1);
+    assert!(graph.misordered_connections().is_empty());
+    let divide = graph
+        .nodes
+        .iter()
+        .find(|node| node.name == "math(divide)")
+        .expect("test graph should contain divide");
+    let connection = divide.inputs[0]
+        .connections
+        .first()
+        .expect("divide input should stay connected");
+    assert_eq!((connection.node_id, connection.output_index), (sum_id, 1));
+
+    // A name that no longer exists on the source is stale, not misordered.
+    let sum = graph
+        .nodes
+        .iter_mut()
+        .find(|node| node.id == sum_id)
+        .expect("sum should still exist");
+    sum.outputs[1].name = "total".to_string();
+    assert!(graph.misordered_connections().is_empty());
+}
+
+#[test]
+fn comments_and_groups_default_and_validate() {
+    let mut graph = Graph::test_graph();
+    let mut value: serde_json::Value = serde_json::from_str(
+        &graph
+            .serialize(GraphFormat::Json, JsonOptions::default())
+            .expect("serialize"),
+    )
+    .expect("serialized graph should be json");
+    let object = value.as_object_mut().expect("graph should be an object");
+    object.remove("comments");
+    object.remove("groups");
+    let legacy = Graph::deserialize(GraphFormat::Json, &value.to_string())
+        .expect("graphs without comments/groups should load");
+    assert!(legacy.comments.is_empty());
+    assert!(legacy.groups.is_empty());
+
+    let sum_id = graph.groups[0].node_ids[0];
+    graph.remove_node(sum_id);
+    assert!(!graph.groups[0].node_ids.contains(&sum_id));
+    assert!(graph.validate().is_ok());
+
+    graph.comments[0].size = egui::vec2(0.0, 10.0);
+    graph.groups[0].node_ids.push(Uuid::new_v4());
+    let messages: Vec<String> = graph
+        .validate_detailed()
+        .into_iter()
+        .map(|issue| issue.message)
+        .collect();
+    assert!(messages.contains(&"comment size must be finite and positive".to_string()));
+    assert!(messages.contains(&"group references a missing node".to_string()));
+}
+
+#[test]
+fn image_comments_keep_their_pixels_through_a_save() {
+    let (width, height) = (960, 2);
+    let rgba: Vec<u8> = (0..width * height * 4)
+        .map(|byte| (byte % 251) as u8)
+        .collect();
+    assert!(CommentImage::from_rgba(width, height, &rgba[4..]).is_err());
+    assert!(CommentImage::from_rgba(0, 0, &[]).is_err());
+    let image = CommentImage::from_rgba(width, height, &rgba).expect("pixels fit the size");
+
+    let mut graph = Graph::test_graph();
+    let id = graph.add_image_comment(egui::pos2(10.0, 20.0), image);
+    let comment = graph.comments.last().expect("comment was added");
+    assert_eq!(comment.id, id);
+    assert!(comment.text.is_empty());
+    // 960 wide scales down to the cap, keeping the aspect ratio.
+    assert_eq!(comment.size, egui::vec2(COMMENT_IMAGE_MAX_SIDE, 1.0));
+    assert!(graph.validate().is_ok());
+
+    for format in [GraphFormat::Json, GraphFormat::Yaml, GraphFormat::Toml] {
+        let text = graph
+            .serialize(format, JsonOptions::default())
+            .expect("graph serializes");
+        let restored = Graph::deserialize(format, &text).expect("graph loads");
+        let image = restored
+            .comments
+            .last()
+            .and_then(|comment| comment.image.as_ref());
+        let pixels = image
+            .expect("image survives")
+            .to_rgba()
+            .expect("image decodes");
+        assert_eq!(pixels, rgba, "{format:?}");
+    }
+    assert!(
+        graph.comments[0].image.is_none(),
+        "plain comments have no image"
+    );
+}
+
+#[test]
+fn graph_roundtrip() {
+    assert_roundtrip(GraphFormat::Json);
+    assert_roundtrip(GraphFormat::Yaml);
+    assert_roundtrip(GraphFormat::Toml);
+
+    assert_file_roundtrip(GraphFormat::Json, "json");
+    assert_file_roundtrip(GraphFormat::Yaml, "yaml");
+    assert_file_roundtrip(GraphFormat::Toml, "toml");
+}
+
+fn assert_roundtrip(format: GraphFormat) {
+    let mut graph = Graph::test_graph();
+    let pinned_id = graph.nodes[0].id;
+    graph.set_screen_pinned(pinned_id, true);
+    // Every per-node flag ends up non-default on at least one node.
+    graph.nodes[1].has_cached_output = false;
+    graph.nodes[3].width_override = Some(180.0);
+    graph.nodes[3].bypass = true;
+    let serialized = graph
+        .serialize(format, JsonOptions::default())
+        .expect("graph serialization should succeed for test graph");
+    assert!(
+        !serialized.trim().is_empty(),
+        "serialized graph should not be empty"
+    );
     let deserialized = Graph::deserialize(format, &serialized)
         .expect("graph deserialization should succeed for test payload");
     assert!(deserialized.validate().is_ok());
@@ -382,10 +4297,265 @@ fn assert_roundtrip(format: GraphFormat) {
         graph.nodes[0].id, deserialized.nodes[0].id,
         "node ids should round-trip"
     );
+    for (node, restored) in graph.nodes.iter().zip(&deserialized.nodes) {
+        assert_eq!(
+            node.description, restored.description,
+            "node descriptions should round-trip"
+        );
+        assert_eq!(
+            node.screen_pinned, restored.screen_pinned,
+            "screen pinning should round-trip"
+        );
+        assert_eq!(node.cache_output, restored.cache_output);
+        assert_eq!(node.has_cached_output, restored.has_cached_output);
+        assert_eq!(node.terminal, restored.terminal);
+        assert_eq!(node.width_override, restored.width_override);
+        assert_eq!(node.bypass, restored.bypass);
+        for (input, restored_input) in node.inputs.iter().zip(&restored.inputs) {
+            let wires = |input: &Input| -> Vec<_> {
+                input
+                    .connections
+                    .iter()
+                    .map(|connection| {
+                        (
+                            connection.node_id,
+                            connection.output_index,
+                            connection.label.clone(),
+                            connection.output_name.clone(),
+                        )
+                    })
+                    .collect()
+            };
+            assert_eq!(
+                wires(input),
+                wires(restored_input),
+                "connections with labels and output names should round-trip"
+            );
+            assert_eq!(input.max_connections, restored_input.max_connections);
+        }
+    }
+    assert_eq!(
+        graph.comments.len(),
+        deserialized.comments.len(),
+        "comments should round-trip"
+    );
+    for (comment, restored) in graph.comments.iter().zip(&deserialized.comments) {
+        assert_eq!(comment.id, restored.id);
+        assert_eq!(comment.text, restored.text);
+        assert_eq!(comment.pos, restored.pos);
+        assert_eq!(comment.size, restored.size);
+    }
+    assert_eq!(
+        graph.groups.len(),
+        deserialized.groups.len(),
+        "groups should round-trip"
+    );
+    for (group, restored) in graph.groups.iter().zip(&deserialized.groups) {
+        assert_eq!(group.id, restored.id);
+        assert_eq!(group.name, restored.name);
+        assert_eq!(group.node_ids, restored.node_ids);
+    }
     assert_eq!(graph.zoom, deserialized.zoom, "zoom should round-trip");
     assert_eq!(graph.pan, deserialized.pan, "pan should round-trip");
 }
 
+#[test]
+fn compact_ids_roundtrip_preserves_topology() {
+    let mut graph = Graph::test_graph();
+    graph.select_node(graph.nodes[2].id);
+    graph.add_to_selection(graph.nodes[4].id);
+    let topology = |graph: &Graph| {
+        let index_of = |id: Uuid| graph.nodes.iter().position(|node| node.id == id);
+        let wires: Vec<_> = graph
+            .connections()
+            .map(|(node, input, connection)| {
+                (
+                    index_of(node.id),
+                    input,
+                    index_of(connection.node_id),
+                    connection.output_index,
+                )
+            })
+            .collect();
+        let groups: Vec<Vec<_>> = graph
+            .groups
+            .iter()
+            .map(|group| group.node_ids.iter().map(|id| index_of(*id)).collect())
+            .collect();
+        let mut selected: Vec<_> = graph
+            .selected_nodes
+            .iter()
+            .map(|id| index_of(*id))
+            .collect();
+        selected.sort();
+        (wires, groups, selected)
+    };
+
+    for format in [GraphFormat::Json, GraphFormat::Yaml, GraphFormat::Toml] {
+        let canonical = graph
+            .serialize(format, JsonOptions::default())
+            .expect("graph should serialize");
+        let kept = graph
+            .serialize_compact(format, CompactIds::KeepTable)
+            .expect("compact serialization should succeed");
+        let regenerated = graph
+            .serialize_compact(format, CompactIds::Regenerate)
+            .expect("compact serialization should succeed");
+        // Each of the node ids, wire sources, group members, and the selection drops
+        // a 36-character UUID for a one-digit index.
+        let references = graph.nodes.len()
+            + graph.connections().count()
+            + graph
+                .groups
+                .iter()
+                .map(|group| group.node_ids.len())
+                .sum::<usize>()
+            + graph.selected_nodes.len();
+        assert!(
+            regenerated.len() + references * 30 < canonical.len(),
+            "{format:?} should shrink"
+        );
+        assert!(Graph::deserialize(format, &regenerated).is_err());
+
+        let restored = Graph::deserialize_compact(format, &kept).expect("compact file loads");
+        assert_eq!(topology(&restored), topology(&graph));
+        assert!(
+            restored
+                .nodes
+                .iter()
+                .zip(&graph.nodes)
+                .all(|(restored, node)| restored.id == node.id)
+        );
+
+        let fresh = Graph::deserialize_compact(format, &regenerated).expect("compact file loads");
+        assert_eq!(topology(&fresh), topology(&graph));
+        assert!(fresh.nodes.iter().all(|node| node.id != graph.nodes[0].id));
+    }
+}
+
+#[test]
+fn json_preview_truncates_large_nodes() {
+    let graph = Graph::test_graph();
+    let node = &graph.nodes[2];
+    let full = node.json_preview(usize::MAX);
+    let parsed: Node = serde_json::from_str(&full).expect("untruncated preview is valid JSON");
+    assert_eq!(parsed.id, node.id);
+
+    let total = full.lines().count();
+    let preview = node.json_preview(5);
+    assert_eq!(preview.lines().count(), 6);
+    assert!(preview.ends_with(&format!("… {} more lines", total - 5)));
+}
+
+#[test]
+fn row_scroll_is_view_state() {
+    let mut graph = Graph::test_graph();
+    graph.nodes[2].row_scroll = 1;
+    let saved = graph
+        .serialize(GraphFormat::Json, JsonOptions::default())
+        .expect("test graph serializes");
+    assert!(!saved.contains("row_scroll"), "scrolling is not saved");
+    let mut legacy: serde_json::Value = serde_json::from_str(&saved).expect("saved JSON");
+    legacy["nodes"][2]["row_scroll"] = serde_json::json!(3);
+    let loaded: Graph = serde_json::from_value(legacy).expect("older files still load");
+    assert_eq!(loaded.nodes[2].row_scroll, 0);
+
+    graph.push_undo_checkpoint();
+    graph.nodes[0].pos.x += 40.0;
+    assert!(graph.undo());
+    assert_eq!(graph.nodes[2].row_scroll, 1, "undo keeps the scroll");
+}
+
+#[test]
+fn node_flags_default_when_missing() {
+    let mut value = serde_json::to_value(Graph::test_graph()).expect("graph should serialize");
+    for node in value["nodes"]
+        .as_array_mut()
+        .expect("nodes should serialize as an array")
+    {
+        let node = node.as_object_mut().expect("node should be an object");
+        for flag in [
+            "cache_output",
+            "has_cached_output",
+            "terminal",
+            "width_override",
+            "description",
+            "screen_pinned",
+            "bypass",
+        ] {
+            node.remove(flag);
+        }
+    }
+
+    let graph: Graph = serde_json::from_value(value).expect("older graphs should still load");
+    assert!(graph.validate().is_ok());
+    for node in &graph.nodes {
+        assert!(!node.cache_output && !node.has_cached_output && !node.terminal);
+        assert!(!node.screen_pinned && !node.bypass);
+        assert_eq!(node.row_scroll, 0);
+        assert_eq!(node.width_override, None);
+    }
+}
+
+#[test]
+fn legacy_single_connection_loads_as_a_list() {
+    let graph = Graph::test_graph();
+    let mut value = serde_json::to_value(&graph).expect("graph should serialize");
+    for input in value["nodes"]
+        .as_array_mut()
+        .expect("nodes should serialize as an array")
+        .iter_mut()
+        .flat_map(|node| {
+            node["inputs"]
+                .as_array_mut()
+                .expect("inputs should serialize as an array")
+        })
+    {
+        let input = input.as_object_mut().expect("input should be an object");
+        let connections = input.remove("connections").expect("connections serialize");
+        input.remove("max_connections");
+        let connection = connections
+            .as_array()
+            .and_then(|connections| connections.first())
+            .cloned()
+            .unwrap_or(serde_json::Value::Null);
+        input.insert("connection".to_string(), connection);
+    }
+
+    let restored: Graph = serde_json::from_value(value).expect("older graphs should still load");
+    assert!(restored.validate().is_ok());
+    let wires = |graph: &Graph| -> Vec<(usize, usize)> {
+        graph
+            .nodes
+            .iter()
+            .flat_map(|node| &node.inputs)
+            .map(|input| {
+                (
+                    input.connections.len(),
+                    input
+                        .connections
+                        .first()
+                        .map_or(0, |connection| connection.output_index),
+                )
+            })
+            .collect()
+    };
+    assert_eq!(wires(&restored), wires(&graph));
+    assert_eq!(restored.connections().count(), graph.connections().count());
+    // Old files only ever held one source per input, and a new wire still replaces it.
+    assert!(
+        restored
+            .nodes
+            .iter()
+            .flat_map(|node| &node.inputs)
+            .all(|input| input.max_connections == Some(1))
+    );
+
+    let explicit: Input = serde_json::from_str(r#"{"name": "x", "connections": []}"#)
+        .expect("current inputs should load");
+    assert_eq!(explicit.max_connections, None, "fan-in stays unlimited");
+}
+
 fn assert_file_roundtrip(format: GraphFormat, extension: &str) {
     let graph = Graph::test_graph();
     let detected =
@@ -398,7 +4568,7 @@ fn assert_file_roundtrip(format: GraphFormat, extension: &str) {
     let path = std::env::temp_dir().join(file_name);
 
     graph
-        .serialize_to_file(&path)
+        .serialize_to_file(&path, JsonOptions::default())
         .expect("graph serialization to file should succeed");
     assert!(path.exists(), "serialized graph file should exist");
 